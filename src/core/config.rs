@@ -1,13 +1,46 @@
 //! Application configuration loaded from environment variables.
+//! When running as a Home Assistant add-on, the add-on options are read from `/data/options.json`
+//! and Home Assistant is reached through the Supervisor proxy with the `SUPERVISOR_TOKEN`.
+use std::collections::HashMap;
 use std::env;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
 
 use envconfig::Envconfig;
 use humantime::Duration;
 use reqwest::Url;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::integration::homeassistant::{Backend, EntityOptions};
+use crate::integration::solarlog::DataSource;
+use crate::services::StartupCheck;
 
 pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
 
+/// Default path of the add-on options, overridden by `ADDON_OPTIONS_PATH`.
+pub const ADDON_OPTIONS_PATH: &str = "/data/options.json";
+
+/// Home Assistant URL behind the Supervisor proxy, the API is under `/core/api`.
+static SUPERVISOR_URL: &str = "http://supervisor/core/";
+
+/// Configuration errors.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error(transparent)]
+    Env(#[from] envconfig::Error),
+    #[error("Invalid add-on options '{path}': {message}")]
+    AddonOptions { path: String, message: String },
+    #[error("Missing HOMEASSISTANT_URL and HOMEASSISTANT_TOKEN, or SUPERVISOR_TOKEN")]
+    MissingHomeAssistant,
+    #[error("Missing INFLUXDB_ORG, INFLUXDB_BUCKET or INFLUXDB_TOKEN with INFLUXDB_URL")]
+    MissingInfluxDb,
+    #[error("SYNC_CONSUMPTION_INTERVAL requires SOLARLOG_DATA_SOURCE=open_json")]
+    ConsumptionRequiresOpenJson,
+}
+
 #[derive(Envconfig)]
 pub struct Config {
     #[allow(dead_code)]
@@ -20,16 +53,203 @@ pub struct Config {
     pub solarlog_url: Url,
     #[envconfig(from = "SOLARLOG_PASSWORD")]
     pub solarlog_password: String,
+    #[envconfig(from = "SOLARLOG_DATA_SOURCE", default = "index")]
+    pub solarlog_data_source: DataSource,
     #[envconfig(from = "HOMEASSISTANT_URL")]
-    pub homeassistant_url: Url,
+    pub homeassistant_url: Option<Url>,
     #[envconfig(from = "HOMEASSISTANT_TOKEN")]
-    pub homeassistant_token: String,
+    pub homeassistant_token: Option<String>,
+    #[envconfig(from = "SUPERVISOR_TOKEN")]
+    pub supervisor_token: Option<String>,
+    #[envconfig(from = "HOMEASSISTANT_BACKFILL", default = "false")]
+    pub homeassistant_backfill: bool,
+    #[envconfig(from = "HOMEASSISTANT_ENTITY_PREFIX", default = "solar")]
+    pub homeassistant_entity_prefix: String,
+    #[envconfig(from = "HOMEASSISTANT_NAMES", default = "")]
+    pub homeassistant_names: KeyValues,
+    #[envconfig(from = "HOMEASSISTANT_ICONS", default = "")]
+    pub homeassistant_icons: KeyValues,
+    #[envconfig(from = "HOMEASSISTANT_ATTRIBUTES", default = "")]
+    pub homeassistant_attributes: KeyValues,
+    #[envconfig(from = "HOMEASSISTANT_BACKEND", default = "rest")]
+    pub homeassistant_backend: Backend,
+    #[envconfig(from = "MQTT_URL", default = "mqtt://localhost:1883")]
+    pub mqtt_url: Url,
+    #[envconfig(from = "MQTT_USERNAME")]
+    pub mqtt_username: Option<String>,
+    #[envconfig(from = "MQTT_PASSWORD")]
+    pub mqtt_password: Option<String>,
+    #[envconfig(from = "MQTT_DISCOVERY_PREFIX", default = "homeassistant")]
+    pub mqtt_discovery_prefix: String,
+    #[envconfig(from = "INFLUXDB_URL")]
+    pub influxdb_url: Option<Url>,
+    #[envconfig(from = "INFLUXDB_ORG")]
+    pub influxdb_org: Option<String>,
+    #[envconfig(from = "INFLUXDB_BUCKET")]
+    pub influxdb_bucket: Option<String>,
+    #[envconfig(from = "INFLUXDB_TOKEN")]
+    pub influxdb_token: Option<String>,
+    #[envconfig(from = "INFLUXDB_BATCH_SIZE", default = "500")]
+    pub influxdb_batch_size: usize,
+    #[envconfig(from = "INFLUXDB_FLUSH_INTERVAL", default = "10s")]
+    pub influxdb_flush_interval: Duration,
     #[envconfig(from = "SYNC_POWER_INTERVAL", default = "5s")]
     pub sync_power_interval: Duration,
     #[envconfig(from = "SYNC_ENERGY_INTERVAL", default = "60s")]
     pub sync_energy_interval: Duration,
     #[envconfig(from = "SYNC_STATUS_INTERVAL", default = "60s")]
     pub sync_status_interval: Duration,
+    #[envconfig(from = "SYNC_CONSUMPTION_INTERVAL")]
+    pub sync_consumption_interval: Option<Duration>,
+    #[envconfig(from = "SYNC_DC_STRINGS_INTERVAL")]
+    pub sync_dc_strings_interval: Option<Duration>,
+    #[envconfig(from = "SYNC_INTRADAY_INTERVAL")]
+    pub sync_intraday_interval: Option<Duration>,
+    #[envconfig(from = "SYNC_UNAVAILABLE_AFTER_FAILURES", default = "3")]
+    pub sync_unavailable_after_failures: u32,
+    #[envconfig(from = "SYNC_UNAVAILABLE_AFTER")]
+    pub sync_unavailable_after: Option<Duration>,
+    #[envconfig(from = "NOTIFY_SHUTDOWN_AFTER")]
+    pub notify_shutdown_after: Option<Duration>,
+    #[envconfig(from = "STARTUP_CHECK", default = "warn")]
+    pub startup_check: StartupCheck,
+    #[envconfig(from = "METRICS_ADDRESS")]
+    pub metrics_address: Option<SocketAddr>,
+}
+
+impl Config {
+    /// Loads the configuration from the environment and the add-on options.
+    /// The add-on options are keyed by the lowercase variable names, such as `solarlog_url`,
+    /// and the environment takes precedence. A missing options file is ignored.
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut vars: HashMap<String, String> = env::vars().collect();
+        let path = vars
+            .get("ADDON_OPTIONS_PATH")
+            .cloned()
+            .unwrap_or_else(|| ADDON_OPTIONS_PATH.to_string());
+        for (key, value) in read_addon_options(Path::new(&path))? {
+            vars.entry(key).or_insert(value);
+        }
+        let config = Config::init_from_hashmap(&vars)?;
+        if config.homeassistant_api_url().is_none() || config.homeassistant_api_token().is_none() {
+            return Err(ConfigError::MissingHomeAssistant);
+        }
+        let influxdb_settings = [
+            &config.influxdb_org,
+            &config.influxdb_bucket,
+            &config.influxdb_token,
+        ];
+        if config.influxdb_url.is_some() && influxdb_settings.iter().any(|s| s.is_none()) {
+            return Err(ConfigError::MissingInfluxDb);
+        }
+        // The consumption is only exposed by the Open JSON interface
+        if config.sync_consumption_interval.is_some()
+            && config.solarlog_data_source != DataSource::OpenJson
+        {
+            return Err(ConfigError::ConsumptionRequiresOpenJson);
+        }
+        Ok(config)
+    }
+
+    /// Returns the URL of Home Assistant, or the Supervisor proxy when running as an add-on.
+    pub fn homeassistant_api_url(&self) -> Option<Url> {
+        self.homeassistant_url.clone().or_else(|| {
+            self.supervisor_token
+                .as_ref()
+                .map(|_| Url::parse(SUPERVISOR_URL).expect("invalid Supervisor URL"))
+        })
+    }
+
+    /// Returns the Home Assistant access token, or the Supervisor token when running as an add-on.
+    pub fn homeassistant_api_token(&self) -> Option<String> {
+        self.homeassistant_token
+            .clone()
+            .or_else(|| self.supervisor_token.clone())
+    }
+
+    /// Returns the ID of this bridge on the MQTT broker, derived from the entity prefix,
+    /// so several installations can share one broker.
+    pub fn mqtt_instance_id(&self) -> String {
+        format!("grelsolar_{}", self.homeassistant_entity_prefix)
+    }
+
+    /// Returns the customization of the Home Assistant entities.
+    pub fn homeassistant_entity_options(&self) -> EntityOptions {
+        EntityOptions {
+            prefix: self.homeassistant_entity_prefix.clone(),
+            names: self.homeassistant_names.0.clone(),
+            icons: self.homeassistant_icons.0.clone(),
+            attributes: self
+                .homeassistant_attributes
+                .0
+                .iter()
+                .map(|(key, value)| (key.clone(), parse_attribute_value(value)))
+                .collect(),
+        }
+    }
+}
+
+/// Parses an attribute value as JSON, such as `5` or `true`, or keeps it as a string.
+fn parse_attribute_value(value: &str) -> Value {
+    serde_json::from_str(value).unwrap_or_else(|_| Value::from(value))
+}
+
+/// Comma-separated `key=value` pairs, such as `power=Roof Power,energy=Roof Energy`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyValues(pub HashMap<String, String>);
+
+impl FromStr for KeyValues {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair
+                    .split_once('=')
+                    .ok_or_else(|| format!("missing '=' in '{pair}'"))?;
+                Ok((key.trim().to_string(), value.trim().to_string()))
+            })
+            .collect::<Result<_, _>>()
+            .map(KeyValues)
+    }
+}
+
+/// Reads the add-on options as variables, such as `{"solarlog_url": "http://solarlog"}` as `SOLARLOG_URL`.
+/// Objects are written as `key=value` pairs, such as the names of the sensors.
+fn read_addon_options(path: &Path) -> Result<HashMap<String, String>, ConfigError> {
+    let error = |message: String| ConfigError::AddonOptions {
+        path: path.display().to_string(),
+        message,
+    };
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(error(e.to_string())),
+    };
+    let options: HashMap<String, Value> =
+        serde_json::from_str(&content).map_err(|e| error(e.to_string()))?;
+    log::info!("Loading add-on options from {}", path.display());
+    let mut vars = HashMap::new();
+    for (key, value) in options {
+        let value = match value {
+            Value::Null => continue,
+            Value::String(value) => value,
+            Value::Bool(_) | Value::Number(_) => value.to_string(),
+            Value::Object(pairs) => pairs
+                .iter()
+                .map(|(k, v)| match v {
+                    Value::String(v) => format!("{k}={v}"),
+                    v => format!("{k}={v}"),
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            Value::Array(_) => return Err(error(format!("unsupported list value for '{key}'"))),
+        };
+        vars.insert(key.to_uppercase(), value);
+    }
+    Ok(vars)
 }
 
 pub fn configure_logger() {
@@ -52,11 +272,42 @@ mod tests {
                 ("APP_LOG_STYLE", Some("auto")),
                 ("SOLARLOG_URL", Some("http://localhost:8080")),
                 ("SOLARLOG_PASSWORD", Some("test_password")),
+                ("SOLARLOG_DATA_SOURCE", Some("open_json")),
                 ("HOMEASSISTANT_URL", Some("http://localhost:8001")),
                 ("HOMEASSISTANT_TOKEN", Some("test_token")),
+                ("HOMEASSISTANT_BACKFILL", Some("true")),
+                ("HOMEASSISTANT_ENTITY_PREFIX", Some("roof")),
+                (
+                    "HOMEASSISTANT_NAMES",
+                    Some("power=Roof Power, energy=Roof Energy"),
+                ),
+                ("HOMEASSISTANT_ICONS", Some("power=mdi:solar-power")),
+                (
+                    "HOMEASSISTANT_ATTRIBUTES",
+                    Some("installation=roof,panels=12,tracking=false"),
+                ),
+                ("HOMEASSISTANT_BACKEND", Some("mqtt")),
+                ("MQTT_URL", Some("mqtt://localhost:1884")),
+                ("MQTT_USERNAME", Some("mqtt_user")),
+                ("MQTT_PASSWORD", Some("mqtt_password")),
+                ("MQTT_DISCOVERY_PREFIX", Some("ha")),
+                ("INFLUXDB_URL", Some("http://localhost:8086")),
+                ("INFLUXDB_ORG", Some("home")),
+                ("INFLUXDB_BUCKET", Some("solar")),
+                ("INFLUXDB_TOKEN", Some("influx_token")),
+                ("INFLUXDB_BATCH_SIZE", Some("50")),
+                ("INFLUXDB_FLUSH_INTERVAL", Some("30s")),
                 ("SYNC_POWER_INTERVAL", Some("10s")),
                 ("SYNC_ENERGY_INTERVAL", Some("20s")),
                 ("SYNC_STATUS_INTERVAL", Some("30s")),
+                ("SYNC_CONSUMPTION_INTERVAL", Some("15s")),
+                ("SYNC_DC_STRINGS_INTERVAL", Some("30s")),
+                ("SYNC_INTRADAY_INTERVAL", Some("15m")),
+                ("SYNC_UNAVAILABLE_AFTER_FAILURES", Some("5")),
+                ("SYNC_UNAVAILABLE_AFTER", Some("10m")),
+                ("NOTIFY_SHUTDOWN_AFTER", Some("30m")),
+                ("STARTUP_CHECK", Some("abort")),
+                ("METRICS_ADDRESS", Some("0.0.0.0:9090")),
             ],
             || {
                 let config = Config::init_from_env().unwrap();
@@ -67,11 +318,45 @@ mod tests {
                     Url::parse("http://localhost:8080").unwrap()
                 );
                 assert_eq!(config.solarlog_password, "test_password");
+                assert_eq!(config.solarlog_data_source, DataSource::OpenJson);
+                assert_eq!(
+                    config.homeassistant_api_url(),
+                    Some(Url::parse("http://localhost:8001").unwrap())
+                );
+                assert_eq!(
+                    config.homeassistant_api_token(),
+                    Some("test_token".to_string())
+                );
+                assert!(config.homeassistant_backfill);
+                let entities = config.homeassistant_entity_options();
+                assert_eq!(entities.prefix, "roof");
+                assert_eq!(entities.names["power"], "Roof Power");
+                assert_eq!(entities.names["energy"], "Roof Energy");
+                assert_eq!(entities.icons["power"], "mdi:solar-power");
+                assert_eq!(entities.attributes["installation"], "roof");
+                assert_eq!(entities.attributes["panels"], 12);
+                assert_eq!(entities.attributes["tracking"], false);
+                assert_eq!(config.homeassistant_backend, Backend::Mqtt);
+                assert_eq!(
+                    config.mqtt_url,
+                    Url::parse("mqtt://localhost:1884").unwrap()
+                );
+                assert_eq!(config.mqtt_username, Some("mqtt_user".to_string()));
+                assert_eq!(config.mqtt_password, Some("mqtt_password".to_string()));
+                assert_eq!(config.mqtt_discovery_prefix, "ha");
+                assert_eq!(config.mqtt_instance_id(), "grelsolar_roof");
                 assert_eq!(
-                    config.homeassistant_url,
-                    Url::parse("http://localhost:8001").unwrap()
+                    config.influxdb_url,
+                    Some(Url::parse("http://localhost:8086").unwrap())
+                );
+                assert_eq!(config.influxdb_org, Some("home".to_string()));
+                assert_eq!(config.influxdb_bucket, Some("solar".to_string()));
+                assert_eq!(config.influxdb_token, Some("influx_token".to_string()));
+                assert_eq!(config.influxdb_batch_size, 50);
+                assert_eq!(
+                    config.influxdb_flush_interval,
+                    std::time::Duration::from_secs(30).into()
                 );
-                assert_eq!(config.homeassistant_token, "test_token");
                 assert_eq!(
                     config.sync_power_interval,
                     std::time::Duration::from_secs(10).into()
@@ -84,10 +369,227 @@ mod tests {
                     config.sync_status_interval,
                     std::time::Duration::from_secs(30).into()
                 );
+                assert_eq!(
+                    config.sync_consumption_interval,
+                    Some(std::time::Duration::from_secs(15).into())
+                );
+                assert_eq!(
+                    config.sync_dc_strings_interval,
+                    Some(std::time::Duration::from_secs(30).into())
+                );
+                assert_eq!(
+                    config.sync_intraday_interval,
+                    Some(std::time::Duration::from_secs(900).into())
+                );
+                assert_eq!(config.sync_unavailable_after_failures, 5);
+                assert_eq!(
+                    config.sync_unavailable_after,
+                    Some(std::time::Duration::from_secs(600).into())
+                );
+                assert_eq!(
+                    config.notify_shutdown_after,
+                    Some(std::time::Duration::from_secs(1800).into())
+                );
+                assert_eq!(config.startup_check, StartupCheck::Abort);
+                assert_eq!(
+                    config.metrics_address,
+                    Some(SocketAddr::from(([0, 0, 0, 0], 9090)))
+                );
             },
         );
     }
 
+    #[test]
+    fn test_config_defaults() {
+        with_vars(
+            [
+                ("SOLARLOG_URL", Some("http://localhost:8080")),
+                ("SOLARLOG_PASSWORD", Some("test_password")),
+                ("HOMEASSISTANT_URL", Some("http://localhost:8001")),
+                ("HOMEASSISTANT_TOKEN", Some("test_token")),
+            ],
+            || {
+                let config = Config::init_from_env().unwrap();
+                assert_eq!(
+                    config.homeassistant_entity_options(),
+                    EntityOptions::default()
+                );
+                assert_eq!(config.mqtt_instance_id(), "grelsolar_solar");
+                assert_eq!(config.sync_unavailable_after_failures, 3);
+                assert_eq!(config.sync_unavailable_after, None);
+                assert_eq!(config.notify_shutdown_after, None);
+                assert_eq!(config.startup_check, StartupCheck::Warn);
+                assert_eq!(config.influxdb_url, None);
+                assert_eq!(config.influxdb_batch_size, 500);
+                assert_eq!(config.metrics_address, None);
+            },
+        );
+    }
+
+    /// Writes the add-on options to a temporary file.
+    fn addon_options(name: &str, options: Value) -> String {
+        let path = env::temp_dir().join(format!("grelsolar_{name}_{}.json", std::process::id()));
+        std::fs::write(&path, options.to_string()).unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    fn test_config_load_supervisor() {
+        with_vars(
+            [
+                ("SOLARLOG_URL", Some("http://localhost:8080")),
+                ("SOLARLOG_PASSWORD", Some("test_password")),
+                ("HOMEASSISTANT_URL", None),
+                ("HOMEASSISTANT_TOKEN", None),
+                ("SUPERVISOR_TOKEN", Some("supervisor_token")),
+                ("ADDON_OPTIONS_PATH", Some("/nonexistent/options.json")),
+            ],
+            || {
+                let config = Config::load().unwrap();
+                assert_eq!(
+                    config.homeassistant_api_url(),
+                    Some(Url::parse("http://supervisor/core/").unwrap())
+                );
+                assert_eq!(
+                    config.homeassistant_api_token(),
+                    Some("supervisor_token".to_string())
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_config_load_missing_homeassistant() {
+        with_vars(
+            [
+                ("SOLARLOG_URL", Some("http://localhost:8080")),
+                ("SOLARLOG_PASSWORD", Some("test_password")),
+                ("HOMEASSISTANT_URL", Some("http://localhost:8001")),
+                ("HOMEASSISTANT_TOKEN", None),
+                ("SUPERVISOR_TOKEN", None),
+                ("ADDON_OPTIONS_PATH", Some("/nonexistent/options.json")),
+            ],
+            || {
+                assert!(matches!(
+                    Config::load(),
+                    Err(ConfigError::MissingHomeAssistant)
+                ));
+            },
+        );
+    }
+
+    #[test]
+    fn test_config_load_missing_influxdb() {
+        with_vars(
+            [
+                ("SOLARLOG_URL", Some("http://localhost:8080")),
+                ("SOLARLOG_PASSWORD", Some("test_password")),
+                ("HOMEASSISTANT_URL", Some("http://localhost:8001")),
+                ("HOMEASSISTANT_TOKEN", Some("test_token")),
+                ("INFLUXDB_URL", Some("http://localhost:8086")),
+                ("INFLUXDB_ORG", Some("home")),
+                ("INFLUXDB_BUCKET", Some("solar")),
+                ("INFLUXDB_TOKEN", None),
+                ("ADDON_OPTIONS_PATH", Some("/nonexistent/options.json")),
+            ],
+            || {
+                assert!(matches!(Config::load(), Err(ConfigError::MissingInfluxDb)));
+            },
+        );
+    }
+
+    #[test]
+    fn test_config_load_consumption_requires_open_json() {
+        with_vars(
+            [
+                ("SOLARLOG_URL", Some("http://localhost:8080")),
+                ("SOLARLOG_PASSWORD", Some("test_password")),
+                ("SOLARLOG_DATA_SOURCE", Some("index")),
+                ("HOMEASSISTANT_URL", Some("http://localhost:8001")),
+                ("HOMEASSISTANT_TOKEN", Some("test_token")),
+                ("SYNC_CONSUMPTION_INTERVAL", Some("15s")),
+                ("ADDON_OPTIONS_PATH", Some("/nonexistent/options.json")),
+            ],
+            || {
+                assert!(matches!(
+                    Config::load(),
+                    Err(ConfigError::ConsumptionRequiresOpenJson)
+                ));
+            },
+        );
+    }
+
+    #[test]
+    fn test_config_load_addon_options() {
+        let path = addon_options(
+            "options",
+            serde_json::json!({
+                "solarlog_url": "http://solarlog",
+                "solarlog_password": "addon_password",
+                "homeassistant_backfill": true,
+                "homeassistant_names": {"power": "Roof Power"},
+                "homeassistant_attributes": {"installation": "roof", "panels": 12},
+                "sync_power_interval": "10s",
+                "sync_unavailable_after_failures": 5,
+                "sync_unavailable_after": null,
+            }),
+        );
+        with_vars(
+            [
+                ("SOLARLOG_URL", None),
+                ("SOLARLOG_PASSWORD", Some("env_password")),
+                ("HOMEASSISTANT_URL", None),
+                ("HOMEASSISTANT_TOKEN", None),
+                ("SUPERVISOR_TOKEN", Some("supervisor_token")),
+                ("SYNC_UNAVAILABLE_AFTER", None),
+                ("ADDON_OPTIONS_PATH", Some(path.as_str())),
+            ],
+            || {
+                let config = Config::load().unwrap();
+                assert_eq!(config.solarlog_url, Url::parse("http://solarlog").unwrap());
+                assert_eq!(config.solarlog_password, "env_password");
+                assert!(config.homeassistant_backfill);
+                assert_eq!(config.homeassistant_names.0["power"], "Roof Power");
+                let entities = config.homeassistant_entity_options();
+                assert_eq!(entities.attributes["installation"], "roof");
+                assert_eq!(entities.attributes["panels"], 12);
+                assert_eq!(
+                    config.sync_power_interval,
+                    std::time::Duration::from_secs(10).into()
+                );
+                assert_eq!(config.sync_unavailable_after_failures, 5);
+                assert_eq!(config.sync_unavailable_after, None);
+            },
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_addon_options_invalid() {
+        let path = addon_options("invalid", serde_json::json!({"names": ["power"]}));
+
+        let result = read_addon_options(Path::new(&path));
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::AddonOptions { message, .. }) if message.contains("names")
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_key_values_from_str() {
+        assert_eq!(KeyValues::from_str("").unwrap(), KeyValues::default());
+        assert_eq!(
+            KeyValues::from_str("a=1, b = x=y ,").unwrap().0,
+            HashMap::from([
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "x=y".to_string())
+            ])
+        );
+        assert!(KeyValues::from_str("a=1,b").is_err());
+    }
+
     #[test]
     fn test_configure_logger() {
         with_var("APP_LOG", Some("debug"), || {
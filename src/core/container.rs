@@ -3,7 +3,7 @@
 use std::sync::Arc;
 
 use super::config::Config;
-use crate::integration::{homeassistant, solarlog};
+use crate::integration::{homeassistant, influxdb, solarlog};
 use crate::services;
 
 /// Container for application dependencies.
@@ -11,7 +11,9 @@ pub struct Container {
     config: Arc<Config>,
     solarlog: Arc<solarlog::Client>,
     homeassistant: Arc<homeassistant::Client>,
+    influxdb: Option<Arc<influxdb::Client>>,
     solar_service: Arc<services::SolarBridgeBackgroundService>,
+    metrics_exporter: Option<Arc<services::MetricsExporter>>,
 }
 
 impl Container {
@@ -24,24 +26,96 @@ impl Container {
             config.solarlog_password.clone(),
         ));
 
-        let homeassistant = Arc::new(homeassistant::Client::new(
-            config.homeassistant_url.clone(),
-            config.homeassistant_token.clone(),
-        ));
+        let mut homeassistant = homeassistant::Client::new(
+            config
+                .homeassistant_api_url()
+                .expect("missing Home Assistant URL"),
+            config
+                .homeassistant_api_token()
+                .expect("missing Home Assistant token"),
+        )
+        .with_entity_options(config.homeassistant_entity_options());
+        if config.homeassistant_backend == homeassistant::Backend::Mqtt {
+            homeassistant = homeassistant.with_mqtt(homeassistant::MqttClient::new(
+                config.mqtt_url.clone(),
+                config.mqtt_username.clone(),
+                config.mqtt_password.clone(),
+                config.mqtt_discovery_prefix.clone(),
+                config.mqtt_instance_id(),
+            ));
+        }
+        let homeassistant = Arc::new(homeassistant);
+
+        let influxdb = config.influxdb_url.clone().map(|url| {
+            Arc::new(
+                influxdb::Client::new(
+                    url,
+                    config.influxdb_org.clone().expect("missing InfluxDB org"),
+                    config
+                        .influxdb_bucket
+                        .clone()
+                        .expect("missing InfluxDB bucket"),
+                    config
+                        .influxdb_token
+                        .clone()
+                        .expect("missing InfluxDB token"),
+                )
+                .with_batch(
+                    config.influxdb_batch_size,
+                    config.influxdb_flush_interval.into(),
+                ),
+            )
+        });
 
-        let solar_service = Arc::new(services::SolarBridgeBackgroundService::new(
+        let mut solar_service = services::SolarBridgeBackgroundService::new(
             Arc::clone(&solarlog),
             Arc::clone(&homeassistant),
+            config.solarlog_data_source,
             config.sync_power_interval.into(),
             config.sync_energy_interval.into(),
             config.sync_status_interval.into(),
-        ));
+        )
+        .with_sync_consumption_interval(config.sync_consumption_interval.map(Into::into))
+        .with_sync_dc_strings_interval(config.sync_dc_strings_interval.map(Into::into))
+        .with_sync_intraday_interval(config.sync_intraday_interval.map(Into::into))
+        .with_unavailable_after(
+            Some(config.sync_unavailable_after_failures).filter(|failures| *failures > 0),
+            config.sync_unavailable_after.map(Into::into),
+        )
+        .with_shutdown_notification_after(config.notify_shutdown_after.map(Into::into));
+        if let Some(influxdb) = &influxdb {
+            solar_service = solar_service.with_sink(influxdb.clone());
+        }
+
+        let mut metrics_exporter = None;
+        if let Some(address) = config.metrics_address {
+            let metrics = Arc::new(services::Metrics::default());
+            let client = Arc::clone(&solarlog);
+            metrics.register_circuit_breaker("solarlog", move || client.is_circuit_open());
+            let client = Arc::clone(&solarlog);
+            metrics.register_request_latencies("solarlog", move || client.request_latencies());
+            let client = Arc::clone(&homeassistant);
+            metrics.register_circuit_breaker("homeassistant", move || client.is_circuit_open());
+            let client = Arc::clone(&homeassistant);
+            metrics.register_request_latencies("homeassistant", move || client.request_latencies());
+            if let Some(client) = influxdb.clone() {
+                metrics.register_circuit_breaker("influxdb", move || client.is_circuit_open());
+            }
+            if let Some(client) = influxdb.clone() {
+                metrics.register_request_latencies("influxdb", move || client.request_latencies());
+            }
+            solar_service = solar_service.with_metrics(Arc::clone(&metrics));
+            metrics_exporter = Some(Arc::new(services::MetricsExporter::new(address, metrics)));
+        }
+        let solar_service = Arc::new(solar_service);
 
         Self {
             config,
             solarlog,
             homeassistant,
+            influxdb,
             solar_service,
+            metrics_exporter,
         }
     }
 
@@ -55,6 +129,11 @@ impl Container {
         Arc::clone(&self.solar_service)
     }
 
+    /// Returns a reference to the metrics exporter, `None` without `METRICS_ADDRESS`.
+    pub fn metrics_exporter(&self) -> Option<Arc<services::MetricsExporter>> {
+        self.metrics_exporter.clone()
+    }
+
     /// Returns a reference to the SolarLog client.
     pub fn solarlog_client(&self) -> Arc<solarlog::Client> {
         Arc::clone(&self.solarlog)
@@ -65,8 +144,19 @@ impl Container {
         Arc::clone(&self.homeassistant)
     }
 
+    /// Returns a reference to the InfluxDB client, `None` without InfluxDB.
+    pub fn influxdb_client(&self) -> Option<Arc<influxdb::Client>> {
+        self.influxdb.clone()
+    }
+
     /// Shutdown the container and clean up resources.
+    /// The points waiting to be written to InfluxDB are flushed.
     pub async fn shutdown(&self) {
+        if let Some(influxdb) = &self.influxdb {
+            if let Err(e) = influxdb.flush().await {
+                log::error!("Error flushing InfluxDB points: {e}");
+            }
+        }
         self.solarlog.logout().await;
     }
 }
@@ -82,11 +172,37 @@ mod tests {
             app_log_style: "auto".into(),
             solarlog_url: reqwest::Url::parse("http://localhost:1234").unwrap(),
             solarlog_password: "pw".into(),
-            homeassistant_url: reqwest::Url::parse("http://localhost:2222").unwrap(),
-            homeassistant_token: "token2".into(),
+            solarlog_data_source: solarlog::DataSource::Index,
+            homeassistant_url: Some(reqwest::Url::parse("http://localhost:2222").unwrap()),
+            homeassistant_token: Some("token2".into()),
+            supervisor_token: None,
+            homeassistant_backfill: false,
+            homeassistant_entity_prefix: "solar".into(),
+            homeassistant_names: Default::default(),
+            homeassistant_icons: Default::default(),
+            homeassistant_attributes: Default::default(),
+            homeassistant_backend: homeassistant::Backend::Rest,
+            mqtt_url: reqwest::Url::parse("mqtt://localhost:1883").unwrap(),
+            mqtt_username: None,
+            mqtt_password: None,
+            mqtt_discovery_prefix: "homeassistant".into(),
+            influxdb_url: None,
+            influxdb_org: None,
+            influxdb_bucket: None,
+            influxdb_token: None,
+            influxdb_batch_size: 500,
+            influxdb_flush_interval: Duration::from(std::time::Duration::from_secs(10)),
             sync_power_interval: Duration::from(std::time::Duration::from_secs(10)),
             sync_energy_interval: Duration::from(std::time::Duration::from_secs(2)),
             sync_status_interval: Duration::from(std::time::Duration::from_secs(3)),
+            sync_consumption_interval: None,
+            sync_dc_strings_interval: None,
+            sync_intraday_interval: None,
+            sync_unavailable_after_failures: 3,
+            sync_unavailable_after: None,
+            notify_shutdown_after: None,
+            startup_check: services::StartupCheck::Warn,
+            metrics_address: None,
         }
     }
 
@@ -114,5 +230,34 @@ mod tests {
         assert!(Arc::strong_count(&container.solarlog_client()) >= 1);
         assert!(Arc::strong_count(&container.homeassistant_client()) >= 1);
         assert!(Arc::strong_count(&container.solar_service()) >= 1);
+        assert!(container.influxdb_client().is_none());
+        assert!(container.metrics_exporter().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_container_init_influxdb() {
+        let config = Config {
+            influxdb_url: Some(reqwest::Url::parse("http://localhost:8086").unwrap()),
+            influxdb_org: Some("home".into()),
+            influxdb_bucket: Some("solar".into()),
+            influxdb_token: Some("token3".into()),
+            ..config()
+        };
+        let container = Container::new(config);
+
+        assert!(container.influxdb_client().is_some());
+        assert!(container.metrics_exporter().is_none());
+        assert_eq!(container.influxdb_client().unwrap().pending(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_container_init_metrics() {
+        let config = Config {
+            metrics_address: Some("127.0.0.1:0".parse().unwrap()),
+            ..config()
+        };
+        let container = Container::new(config);
+
+        assert!(container.metrics_exporter().is_some());
     }
 }
@@ -2,19 +2,148 @@
 //! This client is the higher level API client for Home Assistant.
 
 use super::Result;
+use super::entities::{EntityOptions, SensorGroup};
 use super::http_client::HttpClient;
-use super::schemas::StateCreateOrUpdate;
-use chrono::{DateTime, TimeZone};
+use super::mqtt_client::MqttClient;
+use super::schemas::{State, StateCreateOrUpdate, StatisticData, StatisticMetadata};
+use super::sensor::SensorStateBuilder;
+use super::websocket::{EventSubscription, WebSocketClient};
+use crate::integration::Latencies;
+use chrono::{DateTime, TimeZone, Utc};
 use reqwest::Url;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use strum_macros::{Display, EnumString};
+
+/// Backend used to publish the sensor states.
+/// Statistics are always imported over the WebSocket API.
+#[derive(Debug, PartialEq, Eq, EnumString, Display, Clone, Copy, Default)]
+#[strum(serialize_all = "snake_case")]
+pub enum Backend {
+    /// States written with `POST /api/states/...`.
+    #[default]
+    Rest,
+    /// Entities announced with MQTT discovery under a "Solar-Log" device.
+    Mqtt,
+}
+
+/// Outcome of the comparison of a state with the one held by Home Assistant.
+#[derive(Debug, PartialEq, Eq)]
+enum Reconciliation {
+    /// The state differs and must be published.
+    Changed,
+    /// Home Assistant already holds the same state and attributes.
+    UpToDate,
+    /// The energy of a `total_increasing` sensor would go backwards within the same period.
+    Backwards,
+}
+
 pub struct Client {
     http: HttpClient,
+    websocket: WebSocketClient,
+    mqtt: Option<MqttClient>,
+    entities: EntityOptions,
+    /// Last state published for each sensor key, used to mark the sensors unavailable.
+    published: Mutex<HashMap<String, StateCreateOrUpdate>>,
+    /// Sensor keys already reconciled with the state held by Home Assistant.
+    reconciled: Mutex<HashSet<String>>,
+    /// States held by Home Assistant at startup by entity ID, `None` until loaded, see `load_states`.
+    startup_states: Mutex<Option<HashMap<String, State>>>,
 }
 
 impl Client {
     /// Creates a new instance of `Client`.
     pub fn new(url: Url, token: String) -> Self {
+        let websocket = WebSocketClient::new(url.clone(), token.clone());
         let http = HttpClient::new(url, token);
-        Client { http }
+        Client {
+            http,
+            websocket,
+            mqtt: None,
+            entities: EntityOptions::default(),
+            published: Mutex::new(HashMap::new()),
+            reconciled: Mutex::new(HashSet::new()),
+            startup_states: Mutex::new(None),
+        }
+    }
+
+    /// Customizes the entity IDs, names, icons and attributes of the sensors.
+    pub fn with_entity_options(mut self, entities: EntityOptions) -> Self {
+        self.entities = entities;
+        self
+    }
+
+    /// Publishes the sensor states over MQTT instead of the REST API.
+    pub fn with_mqtt(mut self, mqtt: MqttClient) -> Self {
+        self.mqtt = Some(mqtt);
+        self
+    }
+
+    /// Returns `true` if the circuit breaker of the HTTP client is open and rejects the requests.
+    pub fn is_circuit_open(&self) -> bool {
+        self.http.is_circuit_open()
+    }
+
+    /// Returns the latencies of the requests sent by the HTTP client.
+    pub fn request_latencies(&self) -> Latencies {
+        self.http.latencies()
+    }
+
+    /// Checks that the REST API is reachable and the token is valid.
+    /// Returns the message of Home Assistant, such as `API running.`.
+    pub async fn check_api(&self) -> Result<String> {
+        self.http.get_api().await
+    }
+
+    /// Subscribes to the Home Assistant events of the given type, or to all events when `None`.
+    pub async fn subscribe_events(&self, event_type: Option<&str>) -> Result<EventSubscription> {
+        self.websocket.subscribe_events(event_type).await
+    }
+
+    /// Calls a Home Assistant service with the given data.
+    pub async fn call_service(&self, domain: &str, service: &str, data: Value) -> Result<Value> {
+        self.websocket.call_service(domain, service, data).await
+    }
+
+    /// Fires an event on the Home Assistant event bus, automations can trigger on it.
+    pub async fn fire_event(&self, event_type: &str, data: Value) -> Result<()> {
+        self.http.fire_event(event_type, &data).await
+    }
+
+    /// Returns the state held by Home Assistant for the sensor with the given key, such as `energy_total`.
+    /// Returns `None` if Home Assistant does not know the sensor.
+    pub async fn get_state(&self, key: &str) -> Result<Option<State>> {
+        self.http.get_state(&self.entities.entity_id(key)).await
+    }
+
+    /// Returns the states of all Home Assistant entities.
+    pub async fn get_states(&self) -> Result<Vec<State>> {
+        self.websocket.get_states().await
+    }
+
+    /// Reads the states held by Home Assistant for the sensors at startup, the first state of each sensor
+    /// is reconciled with them instead of reading its state. Returns the number of states read.
+    /// Over MQTT, the states are not reconciled and nothing is read.
+    pub async fn load_states(&self) -> Result<usize> {
+        if self.mqtt.is_some() {
+            return Ok(0);
+        }
+        let prefix = self.entities.entity_id("");
+        let states: HashMap<_, _> = self
+            .http
+            .get_states()
+            .await?
+            .into_iter()
+            .filter(|state| state.entity_id.starts_with(&prefix))
+            .map(|state| (state.entity_id.clone(), state))
+            .collect();
+        let count = states.len();
+        *self
+            .startup_states
+            .lock()
+            .expect("poisoned startup states lock") = Some(states);
+        Ok(count)
     }
 
     /// Set the solar energy produced today in Home Assistant.
@@ -23,73 +152,531 @@ impl Client {
         energy_today: i64,
         last_reset: &DateTime<Tz>,
     ) -> Result<()> {
-        let state = Self::create_solar_energy_state(energy_today, last_reset);
-        self.http.set_state("sensor.solar_energy", &state).await?;
+        let state = Self::create_solar_energy_state("Solar Energy", energy_today, last_reset);
+        self.set_state("energy", state).await?;
+        Ok(())
+    }
+
+    /// Set the solar energy produced since installation in Home Assistant.
+    /// Unlike the daily energy, this sensor never resets.
+    pub async fn set_solar_energy_total(&self, energy_total: i64) -> Result<()> {
+        let state = Self::create_solar_energy_total_state("Solar Energy Total", energy_total);
+        self.set_state("energy_total", state).await?;
         Ok(())
     }
 
     /// Set the solar current power in Home Assistant.
     pub async fn set_solar_current_power(&self, power: i64) -> Result<()> {
-        let state = Self::create_solar_current_power_state(power);
-        self.http.set_state("sensor.solar_power", &state).await?;
+        let state = Self::create_solar_current_power_state("Solar Power", power);
+        self.set_state("power", state).await?;
         Ok(())
     }
 
     /// Set the solar current status in Home Assistant.
-    pub async fn set_solar_status(&self, status: &str) -> Result<()> {
-        let state = Self::create_solar_status_state(status);
-        self.http.set_state("sensor.solar_status", &state).await?;
+    /// The sensor is an enum with the given possible statuses as options.
+    pub async fn set_solar_status(&self, status: &str, options: &[String]) -> Result<()> {
+        let state = Self::create_solar_status_state("Solar Status", status, options);
+        self.set_state("status", state).await?;
         Ok(())
     }
 
-    /// Create current power state for solar status.
-    fn create_solar_current_power_state(power: i64) -> StateCreateOrUpdate {
-        StateCreateOrUpdate {
-            state: power.to_string(),
-            attributes: Some(
-                [
-                    ("unit_of_measurement".to_string(), "W".to_string()),
-                    ("friendly_name".to_string(), "Solar Power".to_string()),
-                    ("state_class".to_string(), "measurement".to_string()),
-                ]
-                .into_iter()
-                .collect(),
-            ),
+    /// Set the coarse state of the solar installation in Home Assistant.
+    pub async fn set_solar_state(&self, state: &str) -> Result<()> {
+        let state = Self::create_text_state("Solar State", state);
+        self.set_state("state", state).await?;
+        Ok(())
+    }
+
+    /// Set the energy produced today by the given inverter in Home Assistant.
+    pub async fn set_inverter_energy<Tz: TimeZone>(
+        &self,
+        inverter_id: u8,
+        energy_today: i64,
+        last_reset: &DateTime<Tz>,
+    ) -> Result<()> {
+        let name = format!("Solar Energy Inverter {inverter_id}");
+        let state = Self::create_solar_energy_state(&name, energy_today, last_reset);
+        let key = format!("energy_inverter_{inverter_id}");
+        self.set_state(&key, state).await?;
+        Ok(())
+    }
+
+    /// Set the current power of the given inverter in Home Assistant.
+    pub async fn set_inverter_current_power(&self, inverter_id: u8, power: i64) -> Result<()> {
+        let name = format!("Solar Power Inverter {inverter_id}");
+        let state = Self::create_solar_current_power_state(&name, power);
+        let key = format!("power_inverter_{inverter_id}");
+        self.set_state(&key, state).await?;
+        Ok(())
+    }
+
+    /// Set the current status of the given inverter in Home Assistant.
+    /// The sensor is an enum with the given possible statuses as options.
+    pub async fn set_inverter_status(
+        &self,
+        inverter_id: u8,
+        status: &str,
+        options: &[String],
+    ) -> Result<()> {
+        let name = format!("Solar Status Inverter {inverter_id}");
+        let state = Self::create_solar_status_state(&name, status, options);
+        let key = format!("status_inverter_{inverter_id}");
+        self.set_state(&key, state).await?;
+        Ok(())
+    }
+
+    /// Set the current consumption power in Home Assistant.
+    pub async fn set_consumption_power(&self, power: i64) -> Result<()> {
+        let state = Self::create_solar_current_power_state("Solar Consumption Power", power);
+        self.set_state("consumption_power", state).await?;
+        Ok(())
+    }
+
+    /// Set the energy consumed today in Home Assistant.
+    pub async fn set_consumption_energy<Tz: TimeZone>(
+        &self,
+        energy_today: i64,
+        last_reset: &DateTime<Tz>,
+    ) -> Result<()> {
+        let state =
+            Self::create_solar_energy_state("Solar Consumption Energy", energy_today, last_reset);
+        self.set_state("consumption_energy", state).await?;
+        Ok(())
+    }
+
+    /// Set the power imported from the grid in Home Assistant.
+    pub async fn set_grid_import_power(&self, power: i64) -> Result<()> {
+        let state = Self::create_solar_current_power_state("Solar Grid Import Power", power);
+        self.set_state("grid_import_power", state).await?;
+        Ok(())
+    }
+
+    /// Set the power exported to the grid in Home Assistant.
+    pub async fn set_grid_export_power(&self, power: i64) -> Result<()> {
+        let state = Self::create_solar_current_power_state("Solar Grid Export Power", power);
+        self.set_state("grid_export_power", state).await?;
+        Ok(())
+    }
+
+    /// Set the energy imported from the grid in Home Assistant, a meter for the energy dashboard.
+    pub async fn set_grid_import_energy(&self, energy: i64) -> Result<()> {
+        let state = Self::create_solar_energy_total_state("Solar Grid Import Energy", energy);
+        self.set_state("grid_import_energy", state).await?;
+        Ok(())
+    }
+
+    /// Set the energy exported to the grid in Home Assistant, a meter for the energy dashboard.
+    pub async fn set_grid_export_energy(&self, energy: i64) -> Result<()> {
+        let state = Self::create_solar_energy_total_state("Solar Grid Export Energy", energy);
+        self.set_state("grid_export_energy", state).await?;
+        Ok(())
+    }
+
+    /// Set the self-consumption ratio in Home Assistant, `None` is published as unknown.
+    pub async fn set_self_consumption_ratio(&self, ratio: Option<f64>) -> Result<()> {
+        let state = Self::create_ratio_state("Solar Self-Consumption Ratio", ratio);
+        self.set_state("self_consumption_ratio", state).await?;
+        Ok(())
+    }
+
+    /// Set the DC voltage of the given string of an inverter in Home Assistant, strings are numbered from 1.
+    pub async fn set_dc_string_voltage(
+        &self,
+        inverter_id: u8,
+        string: usize,
+        voltage: f64,
+    ) -> Result<()> {
+        let measurement = ("voltage", "Voltage", "V");
+        self.set_dc_string_measurement(inverter_id, string, measurement, voltage.to_string())
+            .await
+    }
+
+    /// Set the DC current of the given string of an inverter in Home Assistant, strings are numbered from 1.
+    pub async fn set_dc_string_current(
+        &self,
+        inverter_id: u8,
+        string: usize,
+        current: f64,
+    ) -> Result<()> {
+        let measurement = ("current", "Current", "A");
+        self.set_dc_string_measurement(inverter_id, string, measurement, current.to_string())
+            .await
+    }
+
+    /// Set the DC power of the given string of an inverter in Home Assistant, strings are numbered from 1.
+    pub async fn set_dc_string_power(
+        &self,
+        inverter_id: u8,
+        string: usize,
+        power: i64,
+    ) -> Result<()> {
+        let measurement = ("power", "Power", "W");
+        self.set_dc_string_measurement(inverter_id, string, measurement, power.to_string())
+            .await
+    }
+
+    /// Set a DC measurement given by its device class, label and unit.
+    async fn set_dc_string_measurement(
+        &self,
+        inverter_id: u8,
+        string: usize,
+        (device_class, label, unit): (&str, &str, &str),
+        value: String,
+    ) -> Result<()> {
+        let name = format!("Solar DC {label} Inverter {inverter_id} String {string}");
+        let state = Self::create_measurement_state(&name, value, unit, device_class);
+        let key = format!("dc_{device_class}_inverter_{inverter_id}_string_{string}");
+        self.set_state(&key, state).await?;
+        Ok(())
+    }
+
+    /// Set the state of the sensor with the given key with the configured backend.
+    /// Over REST, the states of each sensor are reconciled with the state held by Home Assistant until one is
+    /// published or already held, see `reconcile`. A state held back as going backwards is neither reconciled
+    /// nor published.
+    async fn set_state(&self, key: &str, state: StateCreateOrUpdate) -> Result<()> {
+        let entity_id = self.entities.entity_id(key);
+        let state = self.entities.customize(key, state);
+        let reconciled = self
+            .reconciled
+            .lock()
+            .expect("poisoned reconciled lock")
+            .contains(key);
+        match &self.mqtt {
+            Some(mqtt) => mqtt.set_state(&entity_id, &state).await?,
+            None if !reconciled => match self.reconcile(&entity_id, &state).await {
+                Reconciliation::Changed => self.http.set_state(&entity_id, &state).await?,
+                Reconciliation::UpToDate => {}
+                Reconciliation::Backwards => return Ok(()),
+            },
+            None => self.http.set_state(&entity_id, &state).await?,
         }
+        self.reconciled
+            .lock()
+            .expect("poisoned reconciled lock")
+            .insert(key.to_string());
+        self.published
+            .lock()
+            .expect("poisoned published lock")
+            .insert(key.to_string(), state);
+        Ok(())
+    }
+
+    /// Compares a state of a sensor not yet reconciled since startup with the state held by Home Assistant,
+    /// taken from the states loaded at startup or read otherwise.
+    /// A state going backwards must not be published, Home Assistant would take it as a meter reset.
+    async fn reconcile(&self, entity_id: &str, state: &StateCreateOrUpdate) -> Reconciliation {
+        let startup_state = self
+            .startup_states
+            .lock()
+            .expect("poisoned startup states lock")
+            .as_ref()
+            .map(|states| states.get(entity_id).cloned());
+        let current = match startup_state {
+            Some(current) => current,
+            None => match self.http.get_state(entity_id).await {
+                Ok(current) => current,
+                Err(e) => {
+                    log::warn!(
+                        "Error reading the state of '{entity_id}', publishing it anyway: {e}"
+                    );
+                    None
+                }
+            },
+        };
+        let Some(current) = current else {
+            return Reconciliation::Changed;
+        };
+        let reconciliation = Self::reconciliation(state, &current);
+        match reconciliation {
+            Reconciliation::Changed => {}
+            Reconciliation::UpToDate => {
+                log::debug!("State of '{entity_id}' is up to date: {}", current.state);
+            }
+            Reconciliation::Backwards => {
+                log::warn!(
+                    "Not publishing '{entity_id}', the energy would go backwards from {} to {}",
+                    current.state,
+                    state.state
+                );
+            }
+        }
+        reconciliation
+    }
+
+    /// Compares a state with the state held by Home Assistant.
+    /// The energy goes backwards when a lower value is published for the same `last_reset`.
+    fn reconciliation(state: &StateCreateOrUpdate, current: &State) -> Reconciliation {
+        let attributes = state.attributes.clone().unwrap_or_default();
+        if state.state == current.state
+            && attributes
+                .iter()
+                .all(|(key, value)| current.attributes.get(key) == Some(value))
+        {
+            return Reconciliation::UpToDate;
+        }
+        let total_increasing = attributes.get("state_class") == Some(&json!("total_increasing"))
+            && attributes.get("last_reset") == current.attributes.get("last_reset")
+            && attributes.get("unit_of_measurement")
+                == current.attributes.get("unit_of_measurement");
+        match (state.state.parse::<f64>(), current.state.parse::<f64>()) {
+            (Ok(new), Ok(old)) if total_increasing && new < old => Reconciliation::Backwards,
+            _ => Reconciliation::Changed,
+        }
+    }
+
+    /// Marks the published sensors of the group unavailable, until their next state.
+    /// Over REST, the state is set to `unavailable` with the last attributes.
+    pub async fn set_unavailable(&self, group: SensorGroup) -> Result<()> {
+        let sensors: Vec<_> = self
+            .published
+            .lock()
+            .expect("poisoned published lock")
+            .iter()
+            .filter(|(key, _)| group.contains(key))
+            .map(|(key, state)| (self.entities.entity_id(key), state.clone()))
+            .collect();
+        for (entity_id, state) in sensors {
+            match &self.mqtt {
+                Some(mqtt) => mqtt.set_unavailable(&entity_id).await?,
+                None => {
+                    let state = StateCreateOrUpdate {
+                        state: "unavailable".to_string(),
+                        ..state
+                    };
+                    self.http.set_state(&entity_id, &state).await?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Import the history of the solar energy produced since installation into the Home Assistant statistics.
+    /// Each period is given by its start and its energy in watt-hours (Wh), sorted by start.
+    /// The `total` energy produced since installation ends the last period, so the statistics line up
+    /// with the state of the energy total sensor.
+    pub async fn import_solar_energy_total_statistics<Tz: TimeZone>(
+        &self,
+        periods: &[(DateTime<Tz>, i64)],
+        total: i64,
+    ) -> Result<()> {
+        let (metadata, stats) = Self::create_energy_statistics(
+            &self.entities.entity_id("energy_total"),
+            &self.entities.name("energy_total", "Solar Energy Total"),
+            periods,
+            total,
+        );
+        self.websocket
+            .send_command(json!({
+                "type": "recorder/import_statistics",
+                "metadata": metadata,
+                "stats": stats,
+            }))
+            .await?;
+        Ok(())
+    }
+
+    /// Import the hourly mean, minimum and maximum of the solar power into the Home Assistant statistics.
+    /// Each hour is given by its start and its mean, minimum and maximum power in Watt (W).
+    pub async fn import_solar_power_statistics<Tz: TimeZone>(
+        &self,
+        hours: &[(DateTime<Tz>, f64, i64, i64)],
+    ) -> Result<()> {
+        let (metadata, stats) = Self::create_power_statistics(
+            &self.entities.entity_id("power"),
+            &self.entities.name("power", "Solar Power"),
+            hours,
+        );
+        self.websocket
+            .send_command(json!({
+                "type": "recorder/import_statistics",
+                "metadata": metadata,
+                "stats": stats,
+            }))
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the start of each hour of the solar power statistics between `start` and `end`.
+    /// The hours compiled by Home Assistant from the sensor states are included, so they are not imported again.
+    pub async fn get_solar_power_statistics_hours<Tz: TimeZone>(
+        &self,
+        start: &DateTime<Tz>,
+        end: &DateTime<Tz>,
+    ) -> Result<Vec<DateTime<Utc>>> {
+        let statistic_id = self.entities.entity_id("power");
+        let result = self
+            .websocket
+            .send_command(json!({
+                "type": "recorder/statistics_during_period",
+                "start_time": start.to_rfc3339(),
+                "end_time": end.to_rfc3339(),
+                "statistic_ids": [statistic_id],
+                "period": "hour",
+                "types": ["mean"],
+            }))
+            .await?;
+        Ok(Self::extract_statistics_starts(&result, &statistic_id))
+    }
+
+    /// Extract the start of each period of a statistic, given in milliseconds since the epoch
+    /// or as an RFC 3339 date by older Home Assistant versions.
+    fn extract_statistics_starts(result: &Value, statistic_id: &str) -> Vec<DateTime<Utc>> {
+        result
+            .get(statistic_id)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|period| match &period["start"] {
+                Value::Number(millis) => DateTime::from_timestamp_millis(millis.as_f64()? as i64),
+                Value::String(start) => DateTime::parse_from_rfc3339(start)
+                    .ok()
+                    .map(|start| start.to_utc()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Create the recorder statistics of a power sensor.
+    fn create_power_statistics<Tz: TimeZone>(
+        statistic_id: &str,
+        name: &str,
+        hours: &[(DateTime<Tz>, f64, i64, i64)],
+    ) -> (StatisticMetadata, Vec<StatisticData>) {
+        let metadata = StatisticMetadata {
+            has_mean: true,
+            has_sum: false,
+            name: Some(name.to_string()),
+            source: "recorder".to_string(),
+            statistic_id: statistic_id.to_string(),
+            unit_of_measurement: Some("W".to_string()),
+        };
+        let stats = hours
+            .iter()
+            .map(|(start, mean, min, max)| StatisticData {
+                start: start.to_rfc3339(),
+                mean: Some(*mean),
+                min: Some(*min as f64),
+                max: Some(*max as f64),
+                ..Default::default()
+            })
+            .collect();
+        (metadata, stats)
+    }
+
+    /// Create the recorder statistics of an energy sensor.
+    /// The sum is cumulative and ends at `total`, the energy produced before the first period is its starting point.
+    fn create_energy_statistics<Tz: TimeZone>(
+        statistic_id: &str,
+        name: &str,
+        periods: &[(DateTime<Tz>, i64)],
+        total: i64,
+    ) -> (StatisticMetadata, Vec<StatisticData>) {
+        let metadata = StatisticMetadata {
+            has_mean: false,
+            has_sum: true,
+            name: Some(name.to_string()),
+            source: "recorder".to_string(),
+            statistic_id: statistic_id.to_string(),
+            unit_of_measurement: Some("kWh".to_string()),
+        };
+        let mut sum = (total - periods.iter().map(|(_, energy)| energy).sum::<i64>()).max(0);
+        let stats = periods
+            .iter()
+            .map(|(start, energy)| {
+                sum += energy;
+                let kwh = sum as f64 / 1000.0; // Convert to kWh
+                StatisticData {
+                    start: start.to_rfc3339(),
+                    state: Some(kwh),
+                    sum: Some(kwh),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        (metadata, stats)
+    }
+
+    /// Create current power state for solar status.
+    fn create_solar_current_power_state(name: &str, power: i64) -> StateCreateOrUpdate {
+        SensorStateBuilder::new(power)
+            .unit("W")
+            .name(name)
+            .device_class("power")
+            .state_class("measurement")
+            .build()
+    }
+
+    /// Create the state of a measurement with a device class.
+    fn create_measurement_state(
+        name: &str,
+        value: String,
+        unit: &str,
+        device_class: &str,
+    ) -> StateCreateOrUpdate {
+        SensorStateBuilder::new(value)
+            .unit(unit)
+            .name(name)
+            .device_class(device_class)
+            .state_class("measurement")
+            .build()
     }
 
     /// Create the state for solar energy produced today.
     fn create_solar_energy_state<Tz: TimeZone>(
+        name: &str,
         energy_today: i64,
         last_reset: &DateTime<Tz>,
     ) -> StateCreateOrUpdate {
         let kwh = energy_today as f64 / 1000.0; // Convert to kWh
-        StateCreateOrUpdate {
-            state: kwh.to_string(),
-            attributes: Some(
-                [
-                    ("unit_of_measurement".to_string(), "kWh".to_string()),
-                    ("friendly_name".to_string(), "Solar Energy".to_string()),
-                    ("device_class".to_string(), "energy".to_string()),
-                    ("state_class".to_string(), "total_increasing".to_string()),
-                    ("last_reset".to_string(), last_reset.to_rfc3339()),
-                ]
-                .into_iter()
-                .collect(),
-            ),
-        }
+        SensorStateBuilder::new(kwh)
+            .unit("kWh")
+            .name(name)
+            .device_class("energy")
+            .state_class("total_increasing")
+            .attribute("last_reset", last_reset.to_rfc3339())
+            .build()
     }
 
-    /// Create the state for solar status.
-    fn create_solar_status_state(status: &str) -> StateCreateOrUpdate {
-        StateCreateOrUpdate {
-            state: status.to_string(),
-            attributes: Some(
-                [("friendly_name".to_string(), "Solar Status".to_string())]
-                    .into_iter()
-                    .collect(),
-            ),
-        }
+    /// Create the state for solar energy produced since installation, without last reset.
+    fn create_solar_energy_total_state(name: &str, energy_total: i64) -> StateCreateOrUpdate {
+        let kwh = energy_total as f64 / 1000.0; // Convert to kWh
+        SensorStateBuilder::new(kwh)
+            .unit("kWh")
+            .name(name)
+            .device_class("energy")
+            .state_class("total_increasing")
+            .build()
+    }
+
+    /// Create the state for a ratio in percent.
+    fn create_ratio_state(name: &str, ratio: Option<f64>) -> StateCreateOrUpdate {
+        let state = ratio
+            .map(|ratio| format!("{ratio:.1}"))
+            .unwrap_or_else(|| "unknown".to_string());
+        SensorStateBuilder::new(state)
+            .unit("%")
+            .name(name)
+            .state_class("measurement")
+            .build()
+    }
+
+    /// Create the state for solar status, an enum sensor with the given options.
+    fn create_solar_status_state(
+        name: &str,
+        status: &str,
+        options: &[String],
+    ) -> StateCreateOrUpdate {
+        SensorStateBuilder::new(status)
+            .name(name)
+            .device_class("enum")
+            .options(options)
+            .build()
+    }
+
+    /// Create the state of a plain text sensor.
+    fn create_text_state(name: &str, text: &str) -> StateCreateOrUpdate {
+        SensorStateBuilder::new(text).name(name).build()
     }
 }
 
@@ -115,16 +702,17 @@ mod tests {
             state: expected_state.to_string(),
             attributes: Some(
                 [
-                    ("unit_of_measurement".to_string(), "W".to_string()),
-                    ("friendly_name".to_string(), "Solar Power".to_string()),
-                    ("state_class".to_string(), "measurement".to_string()),
+                    ("unit_of_measurement".to_string(), json!("W")),
+                    ("friendly_name".to_string(), json!("Solar Power")),
+                    ("device_class".to_string(), json!("power")),
+                    ("state_class".to_string(), json!("measurement")),
                 ]
                 .into_iter()
                 .collect(),
             ),
         };
 
-        let state = Client::create_solar_current_power_state(power);
+        let state = Client::create_solar_current_power_state("Solar Power", power);
 
         assert_eq!(state, expected);
     }
@@ -146,21 +734,193 @@ mod tests {
             state: expected_state.to_string(),
             attributes: Some(
                 [
-                    ("unit_of_measurement".to_string(), "kWh".to_string()),
-                    ("friendly_name".to_string(), "Solar Energy".to_string()),
-                    ("device_class".to_string(), "energy".to_string()),
-                    ("state_class".to_string(), "total_increasing".to_string()),
-                    (
-                        "last_reset".to_string(),
-                        "2023-10-01T00:00:00+01:00".to_string(),
-                    ),
+                    ("unit_of_measurement".to_string(), json!("kWh")),
+                    ("friendly_name".to_string(), json!("Solar Energy")),
+                    ("device_class".to_string(), json!("energy")),
+                    ("state_class".to_string(), json!("total_increasing")),
+                    ("last_reset".to_string(), json!("2023-10-01T00:00:00+01:00")),
                 ]
                 .into_iter()
                 .collect(),
             ),
         };
 
-        let state = Client::create_solar_energy_state(energy_today, &last_reset);
+        let state = Client::create_solar_energy_state("Solar Energy", energy_today, &last_reset);
+
+        assert_eq!(state, expected);
+    }
+
+    #[rstest]
+    #[case(48211000, "48211")]
+    #[case(24870500, "24870.5")]
+    #[case(0, "0")]
+    fn test_create_solar_energy_total_state(
+        #[case] energy_total: i64,
+        #[case] expected_state: &str,
+    ) {
+        let expected = StateCreateOrUpdate {
+            state: expected_state.to_string(),
+            attributes: Some(
+                [
+                    ("unit_of_measurement".to_string(), json!("kWh")),
+                    ("friendly_name".to_string(), json!("Solar Energy Total")),
+                    ("device_class".to_string(), json!("energy")),
+                    ("state_class".to_string(), json!("total_increasing")),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        };
+
+        let state = Client::create_solar_energy_total_state("Solar Energy Total", energy_total);
+
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn test_create_measurement_state() {
+        let expected = StateCreateOrUpdate {
+            state: "612.4".to_string(),
+            attributes: Some(
+                [
+                    ("unit_of_measurement".to_string(), json!("V")),
+                    ("friendly_name".to_string(), json!("DC Voltage")),
+                    ("device_class".to_string(), json!("voltage")),
+                    ("state_class".to_string(), json!("measurement")),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        };
+
+        let state =
+            Client::create_measurement_state("DC Voltage", 612.4.to_string(), "V", "voltage");
+
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn test_create_energy_statistics() {
+        let periods = [
+            (
+                chrono::FixedOffset::east_opt(3600)
+                    .unwrap()
+                    .with_ymd_and_hms(2025, 5, 1, 0, 0, 0)
+                    .unwrap(),
+                610000,
+            ),
+            (
+                chrono::FixedOffset::east_opt(7200)
+                    .unwrap()
+                    .with_ymd_and_hms(2025, 6, 1, 0, 0, 0)
+                    .unwrap(),
+                21700,
+            ),
+        ];
+
+        let (metadata, stats) = Client::create_energy_statistics(
+            "sensor.solar_energy_total",
+            "Solar Energy Total",
+            &periods,
+            1631700,
+        );
+
+        assert_eq!(
+            metadata,
+            StatisticMetadata {
+                has_mean: false,
+                has_sum: true,
+                name: Some("Solar Energy Total".to_string()),
+                source: "recorder".to_string(),
+                statistic_id: "sensor.solar_energy_total".to_string(),
+                unit_of_measurement: Some("kWh".to_string()),
+            }
+        );
+        assert_eq!(
+            stats,
+            vec![
+                StatisticData {
+                    start: "2025-05-01T00:00:00+01:00".to_string(),
+                    state: Some(1610.0),
+                    sum: Some(1610.0),
+                    ..Default::default()
+                },
+                StatisticData {
+                    start: "2025-06-01T00:00:00+02:00".to_string(),
+                    state: Some(1631.7),
+                    sum: Some(1631.7),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_statistics_starts() {
+        let result = json!({"sensor.solar_power": [
+            {"start": 1750838400000.0_f64, "end": 1750842000000.0_f64, "mean": 1500.0},
+            {"start": "2025-06-25T09:00:00+00:00", "mean": 2850.0},
+            {"mean": 0.0}
+        ]});
+
+        let starts = Client::extract_statistics_starts(&result, "sensor.solar_power");
+
+        assert_eq!(
+            starts,
+            vec![
+                Utc.with_ymd_and_hms(2025, 6, 25, 8, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2025, 6, 25, 9, 0, 0).unwrap(),
+            ]
+        );
+        assert!(Client::extract_statistics_starts(&Value::Null, "sensor.solar_power").is_empty());
+    }
+
+    #[test]
+    fn test_create_power_statistics() {
+        let start = chrono::FixedOffset::east_opt(7200)
+            .unwrap()
+            .with_ymd_and_hms(2025, 6, 25, 10, 0, 0)
+            .unwrap();
+
+        let (metadata, stats) = Client::create_power_statistics(
+            "sensor.solar_power",
+            "Solar Power",
+            &[(start, 2000.0, 1000, 3000)],
+        );
+
+        assert!(metadata.has_mean);
+        assert!(!metadata.has_sum);
+        assert_eq!(metadata.unit_of_measurement, Some("W".to_string()));
+        assert_eq!(
+            serde_json::to_value(&stats).unwrap(),
+            json!([{
+                "start": "2025-06-25T10:00:00+02:00",
+                "mean": 2000.0,
+                "min": 1000.0,
+                "max": 3000.0
+            }])
+        );
+    }
+
+    #[rstest]
+    #[case(Some(15.0), "15.0")]
+    #[case(Some(33.333), "33.3")]
+    #[case(None, "unknown")]
+    fn test_create_ratio_state(#[case] ratio: Option<f64>, #[case] expected_state: &str) {
+        let expected = StateCreateOrUpdate {
+            state: expected_state.to_string(),
+            attributes: Some(
+                [
+                    ("unit_of_measurement".to_string(), json!("%")),
+                    ("friendly_name".to_string(), json!("Self-Consumption")),
+                    ("state_class".to_string(), json!("measurement")),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        };
+
+        let state = Client::create_ratio_state("Self-Consumption", ratio);
 
         assert_eq!(state, expected);
     }
@@ -169,17 +929,72 @@ mod tests {
     #[case("On-grid")]
     #[case("Idle No irradiation")]
     fn test_create_solar_status_state(#[case] status: &str) {
+        let options = vec!["On-grid".to_string(), "Idle No irradiation".to_string()];
         let expected = StateCreateOrUpdate {
             state: status.to_string(),
             attributes: Some(
-                [("friendly_name".to_string(), "Solar Status".to_string())]
-                    .into_iter()
-                    .collect(),
+                [
+                    ("friendly_name".to_string(), json!("Solar Status")),
+                    ("device_class".to_string(), json!("enum")),
+                    (
+                        "options".to_string(),
+                        json!(["On-grid", "Idle No irradiation"]),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
             ),
         };
 
-        let state = Client::create_solar_status_state(status);
+        let state = Client::create_solar_status_state("Solar Status", status, &options);
 
         assert_eq!(state, expected);
     }
+
+    fn current(state: &str, attributes: Value) -> State {
+        State {
+            entity_id: "sensor.solar_energy".to_string(),
+            state: state.to_string(),
+            attributes: serde_json::from_value(attributes).unwrap(),
+        }
+    }
+
+    #[rstest]
+    #[case("1.234", "2025-06-25T00:00:00+02:00", Reconciliation::UpToDate)]
+    #[case("1.0", "2025-06-25T00:00:00+02:00", Reconciliation::Changed)]
+    #[case("1.5", "2025-06-25T00:00:00+02:00", Reconciliation::Backwards)]
+    #[case("1.5", "2025-06-24T00:00:00+02:00", Reconciliation::Changed)]
+    #[case("unavailable", "2025-06-25T00:00:00+02:00", Reconciliation::Changed)]
+    fn test_reconciliation(
+        #[case] current_state: &str,
+        #[case] current_last_reset: &str,
+        #[case] expected: Reconciliation,
+    ) {
+        let last_reset = chrono::DateTime::parse_from_rfc3339("2025-06-25T00:00:00+02:00").unwrap();
+        let state = Client::create_solar_energy_state("Solar Energy", 1234, &last_reset);
+        let current = current(
+            current_state,
+            json!({
+                "friendly_name": "Solar Energy",
+                "unit_of_measurement": "kWh",
+                "device_class": "energy",
+                "state_class": "total_increasing",
+                "last_reset": current_last_reset,
+                "restored": true,
+            }),
+        );
+
+        assert_eq!(Client::reconciliation(&state, &current), expected);
+    }
+
+    #[test]
+    fn test_reconciliation_measurement() {
+        let state = Client::create_solar_current_power_state("Solar Power", 1000);
+        let current = current("1234", json!({"state_class": "measurement"}));
+
+        assert_eq!(
+            Client::reconciliation(&state, &current),
+            Reconciliation::Changed
+        );
+    }
 }
@@ -0,0 +1,182 @@
+//! Home Assistant entity customization.
+//! Sensors are identified by a key, such as `power` or `energy_inverter_1`.
+//! The entity ID is the key prefixed with `sensor.{prefix}_`, so several installations can share one Home Assistant.
+use serde_json::Value;
+use std::collections::HashMap;
+use strum_macros::Display;
+
+use super::schemas::StateCreateOrUpdate;
+
+/// Default prefix of the entity IDs.
+static DEFAULT_PREFIX: &str = "solar";
+
+/// Entity ID prefix, and per-sensor names and icons keyed by sensor key.
+/// The extra attributes are added to every sensor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityOptions {
+    pub prefix: String,
+    pub names: HashMap<String, String>,
+    pub icons: HashMap<String, String>,
+    pub attributes: HashMap<String, Value>,
+}
+
+impl Default for EntityOptions {
+    fn default() -> Self {
+        EntityOptions {
+            prefix: DEFAULT_PREFIX.to_string(),
+            names: HashMap::new(),
+            icons: HashMap::new(),
+            attributes: HashMap::new(),
+        }
+    }
+}
+
+impl EntityOptions {
+    /// Returns the entity ID of the sensor with the given key.
+    pub fn entity_id(&self, key: &str) -> String {
+        format!("sensor.{}_{key}", self.prefix)
+    }
+
+    /// Returns the name of the sensor with the given key, or the default name.
+    pub fn name(&self, key: &str, default: &str) -> String {
+        self.names
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| default.to_string())
+    }
+
+    /// Applies the name, the icon and the extra attributes to the state of the sensor with the given key.
+    /// The attributes of the state take precedence over the extra attributes.
+    pub fn customize(&self, key: &str, mut state: StateCreateOrUpdate) -> StateCreateOrUpdate {
+        let attributes = state.attributes.get_or_insert_with(HashMap::new);
+        for (name, value) in self.attributes.iter() {
+            attributes
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        if let Some(name) = self.names.get(key) {
+            attributes.insert("friendly_name".to_string(), Value::from(name.as_str()));
+        }
+        if let Some(icon) = self.icons.get(key) {
+            attributes.insert("icon".to_string(), Value::from(icon.as_str()));
+        }
+        state
+    }
+}
+
+/// Sensors updated by the same sync task, marked unavailable together when their source is unreachable.
+#[derive(Debug, PartialEq, Eq, Display, Clone, Copy)]
+#[strum(serialize_all = "snake_case")]
+pub enum SensorGroup {
+    /// Site and inverter power.
+    Power,
+    /// Site and inverter energy, and the lifetime energy.
+    Energy,
+    /// Site and inverter status, and the coarse state.
+    Status,
+    /// Consumption, grid flows and self-consumption ratio.
+    Consumption,
+    /// DC measurements of the strings.
+    DcStrings,
+}
+
+impl SensorGroup {
+    /// Returns whether the sensor with the given key belongs to the group.
+    pub fn contains(self, key: &str) -> bool {
+        match self {
+            SensorGroup::Power => key == "power" || key.starts_with("power_inverter_"),
+            SensorGroup::Energy => {
+                key == "energy" || key == "energy_total" || key.starts_with("energy_inverter_")
+            }
+            SensorGroup::Status => {
+                key == "status" || key == "state" || key.starts_with("status_inverter_")
+            }
+            SensorGroup::Consumption => matches!(
+                key,
+                "consumption_power"
+                    | "consumption_energy"
+                    | "grid_import_power"
+                    | "grid_export_power"
+                    | "grid_import_energy"
+                    | "grid_export_energy"
+                    | "self_consumption_ratio"
+            ),
+            SensorGroup::DcStrings => key.starts_with("dc_"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integration::homeassistant::SensorStateBuilder;
+
+    fn state() -> StateCreateOrUpdate {
+        SensorStateBuilder::new(1234)
+            .unit("W")
+            .name("Solar Power")
+            .build()
+    }
+
+    fn options() -> EntityOptions {
+        EntityOptions {
+            prefix: "roof".to_string(),
+            names: [("power".to_string(), "Roof Power".to_string())].into(),
+            icons: [("power".to_string(), "mdi:solar-power".to_string())].into(),
+            attributes: [
+                ("installation".to_string(), Value::from("roof")),
+                ("panels".to_string(), Value::from(12)),
+                ("unit_of_measurement".to_string(), Value::from("kW")),
+            ]
+            .into(),
+        }
+    }
+
+    #[test]
+    fn test_entity_id() {
+        assert_eq!(
+            EntityOptions::default().entity_id("power"),
+            "sensor.solar_power"
+        );
+        assert_eq!(options().entity_id("power"), "sensor.roof_power");
+    }
+
+    #[test]
+    fn test_name() {
+        assert_eq!(options().name("power", "Solar Power"), "Roof Power");
+        assert_eq!(options().name("energy", "Solar Energy"), "Solar Energy");
+    }
+
+    #[test]
+    fn test_customize() {
+        let state = options().customize("power", state());
+
+        let attributes = state.attributes.unwrap();
+        assert_eq!(attributes["friendly_name"], "Roof Power");
+        assert_eq!(attributes["icon"], "mdi:solar-power");
+        assert_eq!(attributes["installation"], "roof");
+        assert_eq!(attributes["panels"], 12);
+        assert_eq!(attributes["unit_of_measurement"], "W");
+    }
+
+    #[test]
+    fn test_sensor_group_contains() {
+        assert!(SensorGroup::Power.contains("power"));
+        assert!(SensorGroup::Power.contains("power_inverter_1"));
+        assert!(!SensorGroup::Power.contains("consumption_power"));
+        assert!(SensorGroup::Energy.contains("energy_total"));
+        assert!(SensorGroup::Status.contains("state"));
+        assert!(SensorGroup::Consumption.contains("grid_export_power"));
+        assert!(SensorGroup::Consumption.contains("grid_import_energy"));
+        assert!(SensorGroup::DcStrings.contains("dc_voltage_inverter_1_string_2"));
+        assert!(!SensorGroup::DcStrings.contains("energy"));
+    }
+
+    #[test]
+    fn test_customize_default() {
+        assert_eq!(
+            EntityOptions::default().customize("power", state()),
+            state()
+        );
+    }
+}
@@ -8,6 +8,32 @@ pub enum Error {
     RequestRejected,
     #[error("JSON serialization failed: {0}")]
     JsonSerializationFailed(#[from] serde_json::Error),
+    #[error("WebSocket failed: {0}")]
+    WebSocketFailed(Box<tokio_tungstenite::tungstenite::Error>),
+    #[error("WebSocket closed by the server")]
+    WebSocketClosed,
+    #[error("WebSocket timed out")]
+    WebSocketTimeout,
+    #[error("Authentication failed: {0}")]
+    AuthenticationFailed(String),
+    #[error("Command failed: {0}")]
+    CommandFailed(String),
+    #[error("Unexpected message: {0}")]
+    UnexpectedMessage(String),
+    #[error("MQTT publish failed: {0}")]
+    MqttFailed(Box<rumqttc::ClientError>),
     // Add more variants as needed
 }
 pub type Result<T> = std::result::Result<T, Error>;
+
+impl From<tokio_tungstenite::tungstenite::Error> for Error {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        Error::WebSocketFailed(Box::new(error))
+    }
+}
+
+impl From<rumqttc::ClientError> for Error {
+    fn from(error: rumqttc::ClientError) -> Self {
+        Error::MqttFailed(Box::new(error))
+    }
+}
@@ -2,25 +2,22 @@
 //! This is the lower level client for Home Assistant devices.
 #![cfg_attr(coverage_nightly, feature(coverage_attribute))]
 
-use failsafe::{
-    backoff::{self, Constant},
-    failure_policy::{self, ConsecutiveFailures},
-    futures::CircuitBreaker,
-};
 use reqwest::{Client, StatusCode, Url};
-use serde_json::{self};
+use serde_json::{self, Value};
 use std::time::Duration;
-use tokio_retry::RetryIf;
-use tokio_retry::strategy::{ExponentialBackoff, jitter};
 
-use super::schemas::StateCreateOrUpdate;
+use super::schemas::{State, StateCreateOrUpdate};
 use super::{Error, Result};
+use crate::integration::resilience::{
+    self, CircuitBreaker, Latencies, LatencyRecorder, RequestError,
+};
 
 pub struct HttpClient {
     client: Client,
     token: String,
     base_url: Url,
-    circuit_breaker: failsafe::StateMachine<ConsecutiveFailures<Constant>, ()>,
+    circuit_breaker: CircuitBreaker,
+    latency: LatencyRecorder,
 }
 
 impl HttpClient {
@@ -36,31 +33,69 @@ impl HttpClient {
             client,
             token,
             base_url: url,
-            circuit_breaker: Self::circuit_breaker(),
+            circuit_breaker: resilience::circuit_breaker(),
+            latency: LatencyRecorder::default(),
         }
     }
 
+    /// Returns `true` if the circuit breaker is open and rejects the requests.
+    pub fn is_circuit_open(&self) -> bool {
+        !self.circuit_breaker.is_call_permitted()
+    }
+
+    /// Returns the latencies of the requests sent so far.
+    pub fn latencies(&self) -> Latencies {
+        self.latency.latencies()
+    }
+
     /// Creates or updates a state in Home Assistant.
     pub async fn set_state(&self, entity_id: &str, state: &StateCreateOrUpdate) -> Result<()> {
         let body = serde_json::to_string(state)?;
-        RetryIf::spawn(
-            Self::retry_strategy(),
-            || async {
-                self.circuit_breaker
-                    .call_with(
-                        Self::is_recorded_error,
-                        self.request_post_state(entity_id, &body),
-                    )
-                    .await
-                    .map_err(|err| match err {
-                        failsafe::Error::Rejected => Error::RequestRejected,
-                        failsafe::Error::Inner(e) => e,
-                    })
-            },
-            Self::is_retryable_error,
-        )
-        .await?;
-        Ok(())
+        self.call(|| self.request_post_state(entity_id, &body))
+            .await
+    }
+
+    /// Returns the state of an entity, `None` if Home Assistant does not know it.
+    pub async fn get_state(&self, entity_id: &str) -> Result<Option<State>> {
+        self.call(|| self.request_get_state(entity_id)).await
+    }
+
+    /// Returns the states of all entities.
+    pub async fn get_states(&self) -> Result<Vec<State>> {
+        self.call(|| self.request_get_states()).await
+    }
+
+    /// Fires an event on the Home Assistant event bus with the given data.
+    pub async fn fire_event(&self, event_type: &str, data: &Value) -> Result<()> {
+        let body = serde_json::to_string(data)?;
+        self.call(|| self.request_post_event(event_type, &body))
+            .await
+    }
+
+    /// Calls a request through the circuit breaker, retrying on server errors, and records its latency.
+    async fn call<T, F, Fut>(&self, request: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        resilience::call(&self.circuit_breaker, || self.latency.time(request())).await
+    }
+
+    /// Checks that the API is running and the token is valid.
+    /// Returns the message of Home Assistant, such as `API running.`.
+    pub async fn get_api(&self) -> Result<String> {
+        let url = self.base_url.join("api/").expect("cannot create API URL");
+        let body = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let response: Value = serde_json::from_str(&body)?;
+        Ok(response["message"].as_str().unwrap_or_default().to_string())
     }
 
     /// Internal method to post state to Home Assistant.
@@ -81,42 +116,98 @@ impl HttpClient {
         Ok(())
     }
 
-    /// Creates a circuit breaker with a failure policy that allows up to 3 consecutive failures and will retry after 60 seconds.
-    fn circuit_breaker() -> failsafe::StateMachine<ConsecutiveFailures<Constant>, ()> {
-        let backoff = backoff::constant(Duration::from_secs(60));
-        let policy = failure_policy::consecutive_failures(5, backoff);
-        // Creates a circuit breaker with given policy.
-        failsafe::Config::new().failure_policy(policy).build()
+    /// Internal method to post an event to Home Assistant.
+    async fn request_post_event(&self, event_type: &str, body: &str) -> Result<()> {
+        log::debug!("Sending post event request for event '{event_type}': {body}");
+        let url = self
+            .base_url
+            .join(&format!("api/events/{event_type}"))
+            .expect("cannot create event URL");
+        self.client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
     }
 
-    /// Create a retry strategy with exponential backoff starting at 10 milliseconds, with jitter, and a maximum of 3 retries.
-    fn retry_strategy() -> impl Iterator<Item = Duration> {
-        ExponentialBackoff::from_millis(10).map(jitter).take(3)
+    /// Internal method to get the state of an entity from Home Assistant.
+    async fn request_get_state(&self, entity_id: &str) -> Result<Option<State>> {
+        log::debug!("Sending get state request for entity '{entity_id}'");
+        let url = self
+            .base_url
+            .join(&format!("api/states/{entity_id}"))
+            .expect("cannot create state URL");
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let body = response.error_for_status()?.text().await?;
+        Ok(Some(serde_json::from_str(&body)?))
     }
 
-    /// Check if the error is a HTTP 4xx client error.
-    fn is_client_error(error: &reqwest::Error) -> bool {
-        error
-            .status()
-            .map(|status_code| StatusCode::is_client_error(&status_code))
-            .unwrap_or(false)
+    /// Internal method to get the states of all entities from Home Assistant.
+    async fn request_get_states(&self) -> Result<Vec<State>> {
+        log::debug!("Sending get states request");
+        let url = self
+            .base_url
+            .join("api/states")
+            .expect("cannot create states URL");
+        let body = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(serde_json::from_str(&body)?)
     }
+}
 
-    // Predicate function for the retry strategy to determine if an error is retryable.
-    fn is_retryable_error(error: &Error) -> bool {
-        match error {
-            Error::RequestFailed(err) => !HttpClient::is_client_error(err), // Don't retry on client errors
+impl RequestError for Error {
+    fn rejected() -> Self {
+        Error::RequestRejected
+    }
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            Error::RequestFailed(err) => !resilience::is_client_error(err), // Don't retry on client errors
             Error::RequestRejected => false, // Don't retry on circuit breaker rejection
             Error::JsonSerializationFailed(_) => false, // Don't retry on serialization errors
+            // WebSocket and MQTT errors are not raised by the HTTP client
+            Error::WebSocketFailed(_)
+            | Error::WebSocketClosed
+            | Error::WebSocketTimeout
+            | Error::AuthenticationFailed(_)
+            | Error::CommandFailed(_)
+            | Error::UnexpectedMessage(_)
+            | Error::MqttFailed(_) => false,
         }
     }
 
-    /// Predicate function for the circuit breaker to record errors that are not client errors.
-    fn is_recorded_error(error: &Error) -> bool {
-        match error {
-            Error::RequestFailed(err) => !HttpClient::is_client_error(err), // Don't record client errors
+    fn is_recorded(&self) -> bool {
+        match self {
+            Error::RequestFailed(err) => !resilience::is_client_error(err), // Don't record client errors
             Error::RequestRejected => false, // Don't record circuit breaker rejections
             Error::JsonSerializationFailed(_) => false, // Don't record serialization errors
+            // WebSocket and MQTT errors are not raised by the HTTP client
+            Error::WebSocketFailed(_)
+            | Error::WebSocketClosed
+            | Error::WebSocketTimeout
+            | Error::AuthenticationFailed(_)
+            | Error::CommandFailed(_)
+            | Error::UnexpectedMessage(_)
+            | Error::MqttFailed(_) => false,
         }
     }
 }
@@ -126,37 +217,12 @@ impl HttpClient {
 mod tests {
 
     use super::*;
-    use reqwest::StatusCode;
-
-    fn create_reqwest_error_with_status(status: StatusCode) -> reqwest::Error {
-        let response = http::Response::builder()
-            .status(status)
-            .body(Vec::new())
-            .unwrap();
-        reqwest::Response::from(response)
-            .error_for_status()
-            .unwrap_err()
-    }
+    use crate::integration::resilience::create_reqwest_error_with_status;
 
     fn create_json_serialization_error() -> Error {
         Error::JsonSerializationFailed(serde_json::Error::io(std::io::Error::other("fail")))
     }
 
-    #[test]
-    fn test_is_client_error() {
-        let err_400 = create_reqwest_error_with_status(StatusCode::BAD_REQUEST);
-        let err_500 = create_reqwest_error_with_status(StatusCode::INTERNAL_SERVER_ERROR);
-
-        assert!(
-            HttpClient::is_client_error(&err_400),
-            "400 error should be a client error"
-        );
-        assert!(
-            !HttpClient::is_client_error(&err_500),
-            "500 error should not be a client error"
-        );
-    }
-
     #[test]
     fn test_is_retryable_error() {
         let err_400 =
@@ -168,19 +234,16 @@ mod tests {
         let err_json = create_json_serialization_error();
 
         assert!(
-            !HttpClient::is_retryable_error(&err_400),
+            !err_400.is_retryable(),
             "4xx errors should not be retryable"
         );
+        assert!(err_500.is_retryable(), "5xx errors should be retryable");
         assert!(
-            HttpClient::is_retryable_error(&err_500),
-            "5xx errors should be retryable"
-        );
-        assert!(
-            !HttpClient::is_retryable_error(&err_rejected),
+            !err_rejected.is_retryable(),
             "RequestRejected should not be retryable"
         );
         assert!(
-            !HttpClient::is_retryable_error(&err_json),
+            !err_json.is_retryable(),
             "JsonSerializationFailed should not be retryable"
         );
     }
@@ -196,20 +259,14 @@ mod tests {
         let err_rejected = Error::RequestRejected;
         let err_json = create_json_serialization_error();
 
+        assert!(!err_400.is_recorded(), "4xx errors should not be recorded");
+        assert!(err_500.is_recorded(), "5xx errors should be recorded");
         assert!(
-            !HttpClient::is_recorded_error(&err_400),
-            "4xx errors should not be recorded"
-        );
-        assert!(
-            HttpClient::is_recorded_error(&err_500),
-            "5xx errors should be recorded"
-        );
-        assert!(
-            !HttpClient::is_recorded_error(&err_json),
+            !err_json.is_recorded(),
             "JsonSerializationFailed should not be recorded"
         );
         assert!(
-            !HttpClient::is_recorded_error(&err_rejected),
+            !err_rejected.is_recorded(),
             "RequestRejected should not be recorded"
         );
     }
@@ -1,9 +1,18 @@
 //! Home Assistant Integration Module
-//! The integration is done via HTTP JSON API.
+//! The integration is done via HTTP JSON API, or via MQTT discovery for the sensor states.
 mod client;
+mod entities;
 mod error;
 mod http_client;
+mod mqtt_client;
 mod schemas;
+mod sensor;
+mod websocket;
 
-pub use client::Client;
+pub use client::{Backend, Client};
+pub use entities::{EntityOptions, SensorGroup};
 pub use error::{Error, Result};
+pub use mqtt_client::MqttClient;
+pub use schemas::{State, StateCreateOrUpdate};
+pub use sensor::SensorStateBuilder;
+pub use websocket::EventSubscription;
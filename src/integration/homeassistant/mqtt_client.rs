@@ -0,0 +1,338 @@
+//! Home Assistant MQTT client.
+//! The sensors are announced with MQTT discovery and grouped under a single "Solar-Log" device,
+//! so that they are registered in Home Assistant and survive its restarts.
+//! Discovery configs and states are retained by the broker.
+use reqwest::Url;
+use rumqttc::{AsyncClient, Event, EventLoop, LastWill, MqttOptions, Packet, QoS};
+use serde_json::{Map, Value, json};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use super::Result;
+use super::schemas::StateCreateOrUpdate;
+
+/// Attributes that are part of the discovery config instead of the attributes topic.
+static CONFIG_ATTRIBUTES: [&str; 6] = [
+    "friendly_name",
+    "unit_of_measurement",
+    "device_class",
+    "state_class",
+    "icon",
+    "options",
+];
+
+pub struct MqttClient {
+    client: AsyncClient,
+    discovery_prefix: String,
+    /// ID of this bridge, the client ID, the prefix of the topics and unique IDs, and the device identifier.
+    instance_id: String,
+    /// Event loop, spawned on the first publish.
+    eventloop: Mutex<Option<EventLoop>>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+    /// Last discovery config published for each entity.
+    configs: Mutex<HashMap<String, Value>>,
+    /// Entities published as online on their availability topic.
+    online: Mutex<HashSet<String>>,
+}
+
+impl MqttClient {
+    /// Creates a new instance of `MqttClient` from the broker URL, such as `mqtt://localhost:1883`.
+    /// The instance ID must be unique among the bridges sharing the broker.
+    pub fn new(
+        url: Url,
+        username: Option<String>,
+        password: Option<String>,
+        discovery_prefix: String,
+        instance_id: String,
+    ) -> Self {
+        let host = url.host_str().unwrap_or("localhost");
+        let port = url.port().unwrap_or(1883);
+        let mut options = MqttOptions::new(instance_id.clone(), host, port);
+        options.set_keep_alive(Duration::from_secs(30));
+        options.set_last_will(LastWill::new(
+            Self::availability_topic(&instance_id),
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+        if let Some(username) = username {
+            options.set_credentials(username, password.unwrap_or_default());
+        }
+        let (client, eventloop) = AsyncClient::new(options, 64);
+        MqttClient {
+            client,
+            discovery_prefix,
+            instance_id,
+            eventloop: Mutex::new(Some(eventloop)),
+            handle: Mutex::new(None),
+            configs: Mutex::new(HashMap::new()),
+            online: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Publishes the state of an entity, preceded by its discovery config when it changed.
+    pub async fn set_state(&self, entity_id: &str, state: &StateCreateOrUpdate) -> Result<()> {
+        self.start();
+        let (component, object_id) = entity_id.split_once('.').unwrap_or(("sensor", entity_id));
+        let attributes = Self::create_attributes(state);
+        let config = self.create_config(object_id, state, !attributes.is_empty());
+        let changed = self
+            .configs
+            .lock()
+            .expect("poisoned configs lock")
+            .insert(entity_id.to_string(), config.clone())
+            .is_none_or(|previous| previous != config);
+        if changed {
+            let topic = format!(
+                "{}/{component}/{}/{object_id}/config",
+                self.discovery_prefix, self.instance_id
+            );
+            self.publish(topic, config.to_string()).await?;
+        }
+        self.publish(self.state_topic(object_id), state.state.clone())
+            .await?;
+        if !attributes.is_empty() {
+            let topic = self.attributes_topic(object_id);
+            self.publish(topic, Value::Object(attributes).to_string())
+                .await?;
+        }
+        let offline = self
+            .online
+            .lock()
+            .expect("poisoned online lock")
+            .insert(entity_id.to_string());
+        if offline {
+            self.publish(self.entity_availability_topic(object_id), "online".into())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Marks an entity unavailable until its next state.
+    pub async fn set_unavailable(&self, entity_id: &str) -> Result<()> {
+        self.start();
+        let (_, object_id) = entity_id.split_once('.').unwrap_or(("sensor", entity_id));
+        self.publish(self.entity_availability_topic(object_id), "offline".into())
+            .await?;
+        self.online
+            .lock()
+            .expect("poisoned online lock")
+            .remove(entity_id);
+        Ok(())
+    }
+
+    /// Publishes a retained message.
+    /// Waits while the request queue is full, such as before the broker is connected, so no message is lost.
+    async fn publish(&self, topic: String, payload: String) -> Result<()> {
+        log::debug!("Publishing MQTT message on '{topic}': {payload}");
+        self.client
+            .publish(topic, QoS::AtLeastOnce, true, payload)
+            .await?;
+        Ok(())
+    }
+
+    /// Spawns the event loop, which connects to the broker and reconnects on failure.
+    fn start(&self) {
+        let Some(mut eventloop) = self.eventloop.lock().expect("poisoned lock").take() else {
+            return;
+        };
+        let client = self.client.clone();
+        let availability_topic = Self::availability_topic(&self.instance_id);
+        let handle = tokio::spawn(async move {
+            loop {
+                match eventloop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        log::info!("Connected to MQTT broker");
+                        // Published from another task, as the queue is only drained while this loop polls
+                        let client = client.clone();
+                        let topic = availability_topic.clone();
+                        tokio::spawn(async move {
+                            client
+                                .publish(topic, QoS::AtLeastOnce, true, "online")
+                                .await
+                                .ok();
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::warn!("MQTT connection failed: {e}");
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                }
+            }
+        });
+        *self.handle.lock().expect("poisoned lock") = Some(handle);
+    }
+
+    /// Create the discovery config of an entity.
+    fn create_config(
+        &self,
+        object_id: &str,
+        state: &StateCreateOrUpdate,
+        has_attributes: bool,
+    ) -> Value {
+        let attributes = state.attributes.clone().unwrap_or_default();
+        let mut config = json!({
+            "name": attributes.get("friendly_name").and_then(Value::as_str).unwrap_or(object_id),
+            "unique_id": format!("{}_{object_id}", self.instance_id),
+            "object_id": object_id,
+            "state_topic": self.state_topic(object_id),
+            "availability": [
+                {"topic": Self::availability_topic(&self.instance_id)},
+                {"topic": self.entity_availability_topic(object_id)},
+            ],
+            "availability_mode": "all",
+            "device": {
+                "identifiers": [self.instance_id],
+                "name": "Solar-Log",
+                "manufacturer": "Solare Datensysteme",
+                "model": "Solar-Log",
+            },
+        });
+        for key in CONFIG_ATTRIBUTES.iter().skip(1) {
+            if let Some(value) = attributes.get(*key) {
+                config[*key] = value.clone();
+            }
+        }
+        if has_attributes {
+            config["json_attributes_topic"] = json!(self.attributes_topic(object_id));
+        }
+        config
+    }
+
+    /// Create the attributes that are not part of the discovery config, such as `last_reset`.
+    fn create_attributes(state: &StateCreateOrUpdate) -> Map<String, Value> {
+        state
+            .attributes
+            .iter()
+            .flatten()
+            .filter(|(key, _)| !CONFIG_ATTRIBUTES.contains(&key.as_str()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Returns the state topic of an entity.
+    fn state_topic(&self, object_id: &str) -> String {
+        format!("{}/{object_id}/state", self.instance_id)
+    }
+
+    /// Returns the attributes topic of an entity.
+    fn attributes_topic(&self, object_id: &str) -> String {
+        format!("{}/{object_id}/attributes", self.instance_id)
+    }
+
+    /// Returns the availability topic of an entity, `offline` while its source is unreachable.
+    fn entity_availability_topic(&self, object_id: &str) -> String {
+        format!("{}/{object_id}/availability", self.instance_id)
+    }
+
+    /// Returns the availability topic of the bridge, `offline` is published by the broker on disconnect.
+    fn availability_topic(instance_id: &str) -> String {
+        format!("{instance_id}/availability")
+    }
+}
+
+impl Drop for MqttClient {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.lock().expect("poisoned lock").take() {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integration::homeassistant::SensorStateBuilder;
+
+    fn client() -> MqttClient {
+        MqttClient::new(
+            Url::parse("mqtt://localhost:1883").unwrap(),
+            None,
+            None,
+            "homeassistant".to_string(),
+            "grelsolar_solar".to_string(),
+        )
+    }
+
+    fn state() -> StateCreateOrUpdate {
+        SensorStateBuilder::new(1.234)
+            .unit("kWh")
+            .name("Solar Energy")
+            .device_class("energy")
+            .state_class("total_increasing")
+            .attribute("last_reset", "2025-06-25T00:00:00+02:00")
+            .build()
+    }
+
+    #[test]
+    fn test_create_config() {
+        let config = client().create_config("solar_energy", &state(), true);
+
+        assert_eq!(
+            config,
+            json!({
+                "name": "Solar Energy",
+                "unique_id": "grelsolar_solar_solar_energy",
+                "object_id": "solar_energy",
+                "state_topic": "grelsolar_solar/solar_energy/state",
+                "availability": [
+                    {"topic": "grelsolar_solar/availability"},
+                    {"topic": "grelsolar_solar/solar_energy/availability"},
+                ],
+                "availability_mode": "all",
+                "json_attributes_topic": "grelsolar_solar/solar_energy/attributes",
+                "unit_of_measurement": "kWh",
+                "device_class": "energy",
+                "state_class": "total_increasing",
+                "device": {
+                    "identifiers": ["grelsolar_solar"],
+                    "name": "Solar-Log",
+                    "manufacturer": "Solare Datensysteme",
+                    "model": "Solar-Log",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_create_config_without_attributes() {
+        let state = StateCreateOrUpdate {
+            state: "On-grid".to_string(),
+            attributes: None,
+        };
+
+        let config = client().create_config("solar_status", &state, false);
+
+        assert_eq!(config["name"], "solar_status");
+        assert!(config.get("json_attributes_topic").is_none());
+        assert!(config.get("unit_of_measurement").is_none());
+    }
+
+    #[test]
+    fn test_create_config_enum() {
+        let state = SensorStateBuilder::new("On-grid")
+            .name("Solar Status")
+            .device_class("enum")
+            .options(["On-grid", "Shutdown Fault"])
+            .build();
+
+        let config = client().create_config("solar_status", &state, false);
+
+        assert_eq!(config["device_class"], "enum");
+        assert_eq!(config["options"], json!(["On-grid", "Shutdown Fault"]));
+        assert!(MqttClient::create_attributes(&state).is_empty());
+    }
+
+    #[test]
+    fn test_create_attributes() {
+        let attributes = MqttClient::create_attributes(&state());
+
+        assert_eq!(
+            Value::Object(attributes),
+            json!({"last_reset": "2025-06-25T00:00:00+02:00"})
+        );
+    }
+}
@@ -1,10 +1,48 @@
 //! Home Assistant API Schemas
 //! The schemas module defines the data structures used to interact with the Home Assistant API.
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct StateCreateOrUpdate {
     pub state: String,
-    pub attributes: Option<HashMap<String, String>>,
+    pub attributes: Option<HashMap<String, Value>>,
+}
+
+/// State of an entity, as returned by Home Assistant.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct State {
+    pub entity_id: String,
+    pub state: String,
+    #[serde(default)]
+    pub attributes: HashMap<String, Value>,
+}
+
+/// Metadata of an external or recorder statistic.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct StatisticMetadata {
+    pub has_mean: bool,
+    pub has_sum: bool,
+    pub name: Option<String>,
+    pub source: String,
+    pub statistic_id: String,
+    pub unit_of_measurement: Option<String>,
+}
+
+/// Statistic of one period, `start` must be the beginning of an hour.
+/// Sensors with a sum fill `state` and `sum`, sensors with a mean fill `mean`, `min` and `max`.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct StatisticData {
+    pub start: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sum: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
 }
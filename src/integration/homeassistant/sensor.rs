@@ -0,0 +1,108 @@
+//! Home Assistant sensor state builder.
+//! Builds the state of a sensor with its common metadata, the attributes are JSON values.
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+use super::schemas::StateCreateOrUpdate;
+
+/// Builder for the state of a sensor.
+#[derive(Debug, Clone)]
+pub struct SensorStateBuilder {
+    state: String,
+    attributes: HashMap<String, Value>,
+}
+
+impl SensorStateBuilder {
+    /// Creates a new builder with the given state and no attributes.
+    pub fn new(state: impl ToString) -> Self {
+        SensorStateBuilder {
+            state: state.to_string(),
+            attributes: HashMap::new(),
+        }
+    }
+
+    /// Set the friendly name.
+    pub fn name(self, name: &str) -> Self {
+        self.attribute("friendly_name", name)
+    }
+
+    /// Set the unit of measurement, such as `W` or `kWh`.
+    pub fn unit(self, unit: &str) -> Self {
+        self.attribute("unit_of_measurement", unit)
+    }
+
+    /// Set the device class, such as `power`, `energy` or `enum`.
+    pub fn device_class(self, device_class: &str) -> Self {
+        self.attribute("device_class", device_class)
+    }
+
+    /// Set the state class, such as `measurement` or `total_increasing`.
+    pub fn state_class(self, state_class: &str) -> Self {
+        self.attribute("state_class", state_class)
+    }
+
+    /// Set the possible states of an `enum` sensor.
+    pub fn options<T: ToString>(self, options: impl IntoIterator<Item = T>) -> Self {
+        let options: Vec<String> = options.into_iter().map(|o| o.to_string()).collect();
+        self.attribute("options", json!(options))
+    }
+
+    /// Set any other attribute.
+    pub fn attribute(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.attributes.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Builds the state.
+    pub fn build(self) -> StateCreateOrUpdate {
+        StateCreateOrUpdate {
+            state: self.state,
+            attributes: Some(self.attributes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let state = SensorStateBuilder::new(1234)
+            .name("Solar Power")
+            .unit("W")
+            .device_class("power")
+            .state_class("measurement")
+            .attribute("max_power", 9000)
+            .attribute("curtailed", false)
+            .build();
+
+        assert_eq!(
+            serde_json::to_value(&state).unwrap(),
+            json!({
+                "state": "1234",
+                "attributes": {
+                    "friendly_name": "Solar Power",
+                    "unit_of_measurement": "W",
+                    "device_class": "power",
+                    "state_class": "measurement",
+                    "max_power": 9000,
+                    "curtailed": false,
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_build_options() {
+        let state = SensorStateBuilder::new("idle")
+            .device_class("enum")
+            .options(["idle", "producing"])
+            .build();
+
+        assert_eq!(
+            state.attributes.unwrap()["options"],
+            json!(["idle", "producing"])
+        );
+    }
+}
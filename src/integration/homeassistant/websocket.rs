@@ -0,0 +1,228 @@
+//! Home Assistant WebSocket client.
+//! Some features, such as the recorder statistics and the events, are only available over the WebSocket API.
+//! A connection is opened for each command, which is enough for occasional calls.
+//! Event subscriptions keep their own connection open until they are dropped.
+use futures_util::{SinkExt, StreamExt};
+use reqwest::Url;
+use serde_json::{Value, json};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async};
+
+use super::schemas::State;
+use super::{Error, Result};
+
+type Stream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+pub struct WebSocketClient {
+    url: Url,
+    token: String,
+    timeout: Duration,
+}
+
+impl WebSocketClient {
+    /// Creates a new instance of `WebSocketClient` from the Home Assistant base URL.
+    pub fn new(url: Url, token: String) -> Self {
+        WebSocketClient {
+            url: Self::websocket_url(&url),
+            token,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Sends a command and returns its result.
+    /// The `id` field is added to the command.
+    pub async fn send_command(&self, command: Value) -> Result<Value> {
+        let mut stream = self.connect().await?;
+        let result = Self::request(&mut stream, 1, command, self.timeout).await;
+        stream.close(None).await.ok();
+        result
+    }
+
+    /// Calls a service, such as `persistent_notification.create`, and returns its result.
+    pub async fn call_service(&self, domain: &str, service: &str, data: Value) -> Result<Value> {
+        self.send_command(json!({
+            "type": "call_service",
+            "domain": domain,
+            "service": service,
+            "service_data": data,
+        }))
+        .await
+    }
+
+    /// Returns the states of all entities.
+    pub async fn get_states(&self) -> Result<Vec<State>> {
+        let result = self.send_command(json!({"type": "get_states"})).await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Subscribes to the events of the given type, or to all events when `None`.
+    /// The subscription ends when the connection is lost, for example when Home Assistant restarts.
+    pub async fn subscribe_events(&self, event_type: Option<&str>) -> Result<EventSubscription> {
+        let mut stream = self.connect().await?;
+        let mut command = json!({"type": "subscribe_events"});
+        if let Some(event_type) = event_type {
+            command["event_type"] = json!(event_type);
+        }
+        Self::request(&mut stream, 1, command, self.timeout).await?;
+        let (sender, receiver) = mpsc::channel(16);
+        let handle = tokio::spawn(Self::forward_events(stream, 1, sender));
+        Ok(EventSubscription { receiver, handle })
+    }
+
+    /// Forwards the events of the subscription until the connection or the receiver is closed.
+    /// A ping is sent every 30 seconds, so a silent connection loss is detected.
+    async fn forward_events(mut stream: Stream, id: u64, sender: mpsc::Sender<Value>) {
+        let mut ping = tokio::time::interval(Duration::from_secs(30));
+        let mut ping_id = id;
+        loop {
+            tokio::select! {
+                _ = ping.tick() => {
+                    ping_id += 1;
+                    if Self::send(&mut stream, json!({"id": ping_id, "type": "ping"})).await.is_err() {
+                        break;
+                    }
+                }
+                message = stream.next() => {
+                    let text = match message {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                        Some(Ok(_)) => continue,
+                    };
+                    let Ok(message) = serde_json::from_str::<Value>(&text) else {
+                        continue;
+                    };
+                    if message["id"] == id
+                        && message["type"] == "event"
+                        && sender.send(message["event"].clone()).await.is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+        log::debug!("WebSocket event subscription closed");
+        stream.close(None).await.ok();
+    }
+
+    /// Opens a connection and authenticates with the access token.
+    async fn connect(&self) -> Result<Stream> {
+        log::debug!("Connecting to Home Assistant WebSocket API at {}", self.url);
+        let (mut stream, _) = tokio::time::timeout(self.timeout, connect_async(self.url.as_str()))
+            .await
+            .map_err(|_| Error::WebSocketTimeout)??;
+        let message = Self::receive(&mut stream, self.timeout).await?;
+        if message["type"] != "auth_required" {
+            return Err(Error::UnexpectedMessage(message.to_string()));
+        }
+        Self::send(
+            &mut stream,
+            json!({"type": "auth", "access_token": self.token}),
+        )
+        .await?;
+        let message = Self::receive(&mut stream, self.timeout).await?;
+        match message["type"].as_str() {
+            Some("auth_ok") => Ok(stream),
+            Some("auth_invalid") => Err(Error::AuthenticationFailed(
+                message["message"].as_str().unwrap_or_default().to_string(),
+            )),
+            _ => Err(Error::UnexpectedMessage(message.to_string())),
+        }
+    }
+
+    /// Sends a command with the given ID and waits for its result.
+    /// Messages with other IDs, such as events, are ignored.
+    async fn request(
+        stream: &mut Stream,
+        id: u64,
+        mut command: Value,
+        timeout: Duration,
+    ) -> Result<Value> {
+        command["id"] = json!(id);
+        Self::send(stream, command).await?;
+        loop {
+            let message = Self::receive(stream, timeout).await?;
+            if message["id"] != id || message["type"] != "result" {
+                continue;
+            }
+            if message["success"] == true {
+                return Ok(message["result"].clone());
+            }
+            return Err(Error::CommandFailed(
+                message["error"]["message"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            ));
+        }
+    }
+
+    /// Sends a JSON message.
+    async fn send(stream: &mut Stream, message: Value) -> Result<()> {
+        log::debug!("Sending WebSocket message: {message}");
+        stream.send(Message::text(message.to_string())).await?;
+        Ok(())
+    }
+
+    /// Receives the next JSON message, skipping control frames.
+    async fn receive(stream: &mut Stream, timeout: Duration) -> Result<Value> {
+        loop {
+            let message = tokio::time::timeout(timeout, stream.next())
+                .await
+                .map_err(|_| Error::WebSocketTimeout)?
+                .ok_or(Error::WebSocketClosed)??;
+            match message {
+                Message::Text(text) => return Ok(serde_json::from_str(&text)?),
+                Message::Close(_) => return Err(Error::WebSocketClosed),
+                _ => continue,
+            }
+        }
+    }
+
+    /// Returns the WebSocket API URL for the given Home Assistant base URL.
+    fn websocket_url(url: &Url) -> Url {
+        let mut url = url
+            .join("api/websocket")
+            .expect("cannot build WebSocket URL");
+        let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+        url.set_scheme(scheme).expect("cannot set WebSocket scheme");
+        url
+    }
+}
+
+/// Events received from a subscription.
+pub struct EventSubscription {
+    receiver: mpsc::Receiver<Value>,
+    handle: JoinHandle<()>,
+}
+
+impl EventSubscription {
+    /// Waits for the next event, `None` once the connection is closed.
+    pub async fn next(&mut self) -> Option<Value> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for EventSubscription {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("http://localhost:8123", "ws://localhost:8123/api/websocket")]
+    #[case("https://ha.example.com/", "wss://ha.example.com/api/websocket")]
+    #[case("http://supervisor/core/", "ws://supervisor/core/api/websocket")]
+    fn test_websocket_url(#[case] url: &str, #[case] expected: &str) {
+        let url = Url::parse(url).unwrap();
+        assert_eq!(WebSocketClient::websocket_url(&url).as_str(), expected);
+    }
+}
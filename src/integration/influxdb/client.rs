@@ -0,0 +1,206 @@
+//! InfluxDB Client.
+//! This client is the higher level API client for InfluxDB, batching the points before writing them.
+use super::http_client::HttpClient;
+use super::line_protocol::{FieldValue, Point};
+use super::{Error, Result};
+use crate::integration::{Latencies, resilience};
+use chrono::{DateTime, TimeZone, Utc};
+use reqwest::Url;
+use std::sync::Mutex;
+use tokio::time::{Duration, Instant, interval};
+use tokio_util::sync::CancellationToken;
+
+/// Measurement of all the points written by grelsolar.
+static MEASUREMENT: &str = "solar";
+
+/// Tag value of the points of the whole site.
+static SITE: &str = "site";
+
+/// Number of batches kept while InfluxDB is unreachable, the oldest points are dropped beyond.
+const MAX_BUFFERED_BATCHES: usize = 10;
+
+/// Delay between the retries of the points that failed to be written, when each point is written immediately.
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Points waiting to be written.
+#[derive(Default)]
+struct Batch {
+    lines: Vec<String>,
+    since: Option<Instant>,
+}
+
+pub struct Client {
+    http: HttpClient,
+    batch_size: usize,
+    flush_interval: Duration,
+    batch: Mutex<Batch>,
+}
+
+impl Client {
+    /// Creates a new instance of `Client`.
+    /// Each point is written immediately until `with_batch` is called.
+    pub fn new(url: Url, org: String, bucket: String, token: String) -> Self {
+        Client {
+            http: HttpClient::new(url, org, bucket, token),
+            batch_size: 1,
+            flush_interval: Duration::ZERO,
+            batch: Mutex::new(Batch::default()),
+        }
+    }
+
+    /// Writes the points in batches of the given size, or once the oldest point waited for the flush interval.
+    pub fn with_batch(mut self, batch_size: usize, flush_interval: Duration) -> Self {
+        self.batch_size = batch_size.max(1);
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// Returns `true` if the circuit breaker of the HTTP client is open and rejects the requests.
+    pub fn is_circuit_open(&self) -> bool {
+        self.http.is_circuit_open()
+    }
+
+    /// Returns the latencies of the requests sent by the HTTP client.
+    pub fn request_latencies(&self) -> Latencies {
+        self.http.latencies()
+    }
+
+    /// Writes the current power in W, of the whole site without inverter ID.
+    pub async fn write_power(&self, inverter_id: Option<u8>, power: i64) -> Result<()> {
+        self.write(Self::point(inverter_id).field("power", FieldValue::Integer(power)))
+            .await
+    }
+
+    /// Writes the energy produced on the day starting at the given time in Wh, of the whole site without inverter ID.
+    /// The point is timestamped with the start of the day, so each day keeps its last energy.
+    pub async fn write_energy<Tz: TimeZone>(
+        &self,
+        inverter_id: Option<u8>,
+        day_start: &DateTime<Tz>,
+        energy: i64,
+    ) -> Result<()> {
+        let point = Self::point(inverter_id)
+            .field("energy", FieldValue::Integer(energy))
+            .timestamp(day_start.timestamp());
+        self.write(point).await
+    }
+
+    /// Writes the energy produced since installation by all inverters in Wh.
+    pub async fn write_energy_total(&self, energy_total: i64) -> Result<()> {
+        self.write(Self::point(None).field("energy_total", FieldValue::Integer(energy_total)))
+            .await
+    }
+
+    /// Writes the status with its category and fault flag, of the whole site without inverter ID.
+    pub async fn write_status(
+        &self,
+        inverter_id: Option<u8>,
+        status: &str,
+        category: &str,
+        fault: bool,
+    ) -> Result<()> {
+        let point = Self::point(inverter_id)
+            .field("status", FieldValue::String(status.to_string()))
+            .field("category", FieldValue::String(category.to_string()))
+            .field("fault", FieldValue::Boolean(fault));
+        self.write(point).await
+    }
+
+    /// Adds a point to the batch, and writes the batch when it is full or the flush interval elapsed.
+    /// A failed write is logged and its points are kept, `run` retries them in the background.
+    pub async fn write(&self, point: Point) -> Result<()> {
+        let due = {
+            let mut batch = self.batch.lock().expect("poisoned batch lock");
+            batch.lines.push(point.to_string());
+            let since = *batch.since.get_or_insert_with(Instant::now);
+            batch.lines.len() >= self.batch_size || since.elapsed() >= self.flush_interval
+        };
+        if due {
+            if let Err(e) = self.flush().await {
+                log::warn!(
+                    "Error writing to InfluxDB, {} points kept: {e}",
+                    self.pending()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the pending points at each flush interval until the token is cancelled.
+    /// The points that failed to be written are retried at each flush.
+    pub async fn run(&self, token: CancellationToken) {
+        let period = if self.flush_interval.is_zero() {
+            RETRY_INTERVAL
+        } else {
+            self.flush_interval
+        };
+        let mut interval = interval(period);
+        interval.tick().await; // The first tick completes immediately
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {},
+                _ = token.cancelled() => {
+                    log::debug!("influxdb flush task: shutting down");
+                    return;
+                }
+            }
+            if let Err(e) = self.flush().await {
+                log::warn!(
+                    "Error writing to InfluxDB, {} points kept: {e}",
+                    self.pending()
+                );
+            }
+        }
+    }
+
+    /// Writes the pending points.
+    /// On failure the points are kept for the next flush, up to `MAX_BUFFERED_BATCHES` batches,
+    /// unless InfluxDB rejects them with a client error.
+    pub async fn flush(&self) -> Result<()> {
+        let lines = {
+            let mut batch = self.batch.lock().expect("poisoned batch lock");
+            batch.since = None;
+            std::mem::take(&mut batch.lines)
+        };
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let result = self.http.write(&lines.join("\n")).await;
+        match &result {
+            Err(Error::RequestFailed(e)) if resilience::is_client_error(e) => {
+                log::error!(
+                    "InfluxDB rejected the points, dropping {} points",
+                    lines.len()
+                );
+            }
+            Err(_) => {
+                let mut batch = self.batch.lock().expect("poisoned batch lock");
+                let mut kept = lines;
+                kept.append(&mut batch.lines);
+                let max = self.batch_size * MAX_BUFFERED_BATCHES;
+                if kept.len() > max {
+                    log::warn!("InfluxDB unreachable, dropping {} points", kept.len() - max);
+                    kept.drain(..kept.len() - max);
+                }
+                batch.lines = kept;
+                batch.since.get_or_insert_with(Instant::now);
+            }
+            Ok(()) => {}
+        }
+        result
+    }
+
+    /// Returns the number of points waiting to be written.
+    pub fn pending(&self) -> usize {
+        self.batch.lock().expect("poisoned batch lock").lines.len()
+    }
+
+    /// Creates a point tagged with the inverter, or with the site without inverter ID.
+    fn point(inverter_id: Option<u8>) -> Point {
+        let inverter = inverter_id.map_or_else(|| SITE.to_string(), |id| id.to_string());
+        Point::new(MEASUREMENT)
+            .tag("inverter", &inverter)
+            .timestamp(Utc::now().timestamp())
+    }
+}
@@ -0,0 +1,10 @@
+//! Error handling for the InfluxDB client.
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Request failed: {0}")]
+    RequestFailed(#[from] reqwest::Error),
+    #[error("Request rejected by the circuit breaker")]
+    RequestRejected,
+}
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,145 @@
+//! InfluxDB HTTP client.
+//! This is the lower level client for the InfluxDB v2 write API.
+use reqwest::{Client, Url};
+use std::time::Duration;
+
+use super::{Error, Result};
+use crate::integration::resilience::{
+    self, CircuitBreaker, Latencies, LatencyRecorder, RequestError,
+};
+
+pub struct HttpClient {
+    client: Client,
+    base_url: Url,
+    org: String,
+    bucket: String,
+    token: String,
+    circuit_breaker: CircuitBreaker,
+    latency: LatencyRecorder,
+}
+
+impl HttpClient {
+    /// Creates a new instance of `HttpClient`.
+    pub fn new(url: Url, org: String, bucket: String, token: String) -> Self {
+        let client = Client::builder()
+            .pool_idle_timeout(Duration::from_secs(30)) // 30 seconds idle timeout
+            .pool_max_idle_per_host(2) // Maximum 2 idle connections per host
+            .timeout(Duration::from_secs(5)) // 5 seconds timeout, batches can be large
+            .build()
+            .expect("Failed to create HTTP client");
+        HttpClient {
+            client,
+            base_url: url,
+            org,
+            bucket,
+            token,
+            circuit_breaker: resilience::circuit_breaker(),
+            latency: LatencyRecorder::default(),
+        }
+    }
+
+    /// Returns `true` if the circuit breaker is open and rejects the requests.
+    pub fn is_circuit_open(&self) -> bool {
+        !self.circuit_breaker.is_call_permitted()
+    }
+
+    /// Returns the latencies of the requests sent so far.
+    pub fn latencies(&self) -> Latencies {
+        self.latency.latencies()
+    }
+
+    /// Writes the given lines to the bucket, with timestamps in seconds.
+    pub async fn write(&self, lines: &str) -> Result<()> {
+        resilience::call(&self.circuit_breaker, || {
+            self.latency.time(self.request_post_write(lines))
+        })
+        .await
+    }
+
+    /// Internal method to post lines to the write API.
+    async fn request_post_write(&self, lines: &str) -> Result<()> {
+        log::debug!("Sending write request to bucket '{}': {lines}", self.bucket);
+        let url = self
+            .base_url
+            .join("api/v2/write")
+            .expect("cannot create write URL");
+        self.client
+            .post(url)
+            .query(&[
+                ("org", self.org.as_str()),
+                ("bucket", self.bucket.as_str()),
+                ("precision", "s"),
+            ])
+            .header("Authorization", format!("Token {}", self.token))
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(lines.to_string())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+impl RequestError for Error {
+    fn rejected() -> Self {
+        Error::RequestRejected
+    }
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            Error::RequestFailed(err) => !resilience::is_client_error(err), // Don't retry on client errors
+            Error::RequestRejected => false, // Don't retry on circuit breaker rejection
+        }
+    }
+
+    fn is_recorded(&self) -> bool {
+        match self {
+            Error::RequestFailed(err) => !resilience::is_client_error(err), // Don't record client errors
+            Error::RequestRejected => false, // Don't record circuit breaker rejections
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+
+    use super::*;
+    use crate::integration::resilience::create_reqwest_error_with_status;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_is_retryable_error() {
+        let err_400 =
+            Error::RequestFailed(create_reqwest_error_with_status(StatusCode::BAD_REQUEST));
+        let err_503 = Error::RequestFailed(create_reqwest_error_with_status(
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+
+        assert!(
+            !err_400.is_retryable(),
+            "4xx errors should not be retryable"
+        );
+        assert!(err_503.is_retryable(), "5xx errors should be retryable");
+        assert!(
+            !Error::RequestRejected.is_retryable(),
+            "RequestRejected should not be retryable"
+        );
+    }
+
+    #[test]
+    fn test_is_recorded_error() {
+        let err_401 =
+            Error::RequestFailed(create_reqwest_error_with_status(StatusCode::UNAUTHORIZED));
+        let err_500 = Error::RequestFailed(create_reqwest_error_with_status(
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ));
+
+        assert!(!err_401.is_recorded(), "4xx errors should not be recorded");
+        assert!(err_500.is_recorded(), "5xx errors should be recorded");
+        assert!(
+            !Error::RequestRejected.is_recorded(),
+            "RequestRejected should not be recorded"
+        );
+    }
+}
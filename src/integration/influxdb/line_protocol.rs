@@ -0,0 +1,136 @@
+//! InfluxDB line protocol.
+//! A point is written as `measurement,tag=value field=value timestamp`, see
+//! <https://docs.influxdata.com/influxdb/v2/reference/syntax/line-protocol/>.
+use std::fmt;
+
+/// Value of a field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+}
+
+/// Point of a measurement, with its tags, fields and timestamp in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    measurement: String,
+    tags: Vec<(String, String)>,
+    fields: Vec<(String, FieldValue)>,
+    timestamp: Option<i64>,
+}
+
+impl Point {
+    /// Creates a point of the given measurement, without tags nor fields.
+    pub fn new(measurement: &str) -> Self {
+        Point {
+            measurement: measurement.to_string(),
+            tags: Vec::new(),
+            fields: Vec::new(),
+            timestamp: None,
+        }
+    }
+
+    /// Adds a tag.
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Adds a field.
+    pub fn field(mut self, key: &str, value: FieldValue) -> Self {
+        self.fields.push((key.to_string(), value));
+        self
+    }
+
+    /// Sets the timestamp in seconds since the epoch, the server time is used without it.
+    pub fn timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Escapes the commas and spaces of a measurement.
+    fn escape_measurement(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace(',', "\\,")
+            .replace(' ', "\\ ")
+    }
+
+    /// Escapes the commas, equal signs and spaces of a tag key, tag value or field key.
+    fn escape_key(value: &str) -> String {
+        Self::escape_measurement(value).replace('=', "\\=")
+    }
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Integer(value) => write!(f, "{value}i"),
+            FieldValue::Float(value) => write!(f, "{value}"),
+            FieldValue::Boolean(value) => write!(f, "{value}"),
+            FieldValue::String(value) => {
+                write!(
+                    f,
+                    "\"{}\"",
+                    value.replace('\\', "\\\\").replace('"', "\\\"")
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Self::escape_measurement(&self.measurement))?;
+        for (key, value) in &self.tags {
+            write!(f, ",{}={}", Self::escape_key(key), Self::escape_key(value))?;
+        }
+        let fields: Vec<_> = self
+            .fields
+            .iter()
+            .map(|(key, value)| format!("{}={value}", Self::escape_key(key)))
+            .collect();
+        write!(f, " {}", fields.join(","))?;
+        if let Some(timestamp) = self.timestamp {
+            write!(f, " {timestamp}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_to_string() {
+        let point = Point::new("solar")
+            .tag("inverter", "1")
+            .field("power", FieldValue::Integer(1200))
+            .field("ratio", FieldValue::Float(0.5))
+            .field("fault", FieldValue::Boolean(false))
+            .timestamp(1750838400);
+
+        assert_eq!(
+            point.to_string(),
+            "solar,inverter=1 power=1200i,ratio=0.5,fault=false 1750838400"
+        );
+    }
+
+    #[test]
+    fn test_point_to_string_escaped() {
+        let point = Point::new("solar power")
+            .tag("site name", "roof,east=1")
+            .field(
+                "status",
+                FieldValue::String("On-grid \"ok\" \\".to_string()),
+            );
+
+        assert_eq!(
+            point.to_string(),
+            r#"solar\ power,site\ name=roof\,east\=1 status="On-grid \"ok\" \\""#
+        );
+    }
+}
@@ -0,0 +1,10 @@
+//! InfluxDB Integration Module
+//! The integration is done via the InfluxDB v2 HTTP write API, with points in line protocol.
+mod client;
+mod error;
+mod http_client;
+mod line_protocol;
+
+pub use client::Client;
+pub use error::{Error, Result};
+pub use line_protocol::{FieldValue, Point};
@@ -1,4 +1,8 @@
 //! Integration module for the project.
 
 pub mod homeassistant;
+pub mod influxdb;
+mod resilience;
 pub mod solarlog;
+
+pub use resilience::{LATENCY_BUCKETS, Latencies};
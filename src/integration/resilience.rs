@@ -0,0 +1,242 @@
+//! Retries, circuit breaker and latencies shared by the HTTP clients.
+//! Each request is retried on transient errors, and rejected while its service keeps failing.
+use failsafe::{
+    backoff::{self, Constant},
+    failure_policy::{self, ConsecutiveFailures},
+    futures::CircuitBreaker as _,
+};
+use reqwest::StatusCode;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_retry::RetryIf;
+use tokio_retry::strategy::{ExponentialBackoff, jitter};
+
+/// Upper bounds of the request latency histogram buckets in seconds.
+pub const LATENCY_BUCKETS: [f64; 9] = [0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Latencies of the requests sent by an HTTP client, each retry counting as a request.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Latencies {
+    /// Number of requests per bucket of `LATENCY_BUCKETS`, not cumulative.
+    pub buckets: [u64; LATENCY_BUCKETS.len()],
+    /// Number of requests.
+    pub count: u64,
+    /// Sum of the latencies in seconds.
+    pub sum: f64,
+}
+
+/// Recorder of the latencies of the requests sent by an HTTP client.
+#[derive(Default)]
+pub struct LatencyRecorder(Mutex<Latencies>);
+
+impl LatencyRecorder {
+    /// Sends the given request and records its latency, whether it succeeds or not.
+    pub async fn time<F: Future>(&self, request: F) -> F::Output {
+        let started = Instant::now();
+        let output = request.await;
+        self.record(started.elapsed());
+        output
+    }
+
+    /// Returns the latencies recorded so far.
+    pub fn latencies(&self) -> Latencies {
+        self.0.lock().expect("poisoned latencies lock").clone()
+    }
+
+    fn record(&self, latency: Duration) {
+        let mut latencies = self.0.lock().expect("poisoned latencies lock");
+        let seconds = latency.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|le| seconds <= *le) {
+            latencies.buckets[bucket] += 1;
+        }
+        latencies.count += 1;
+        latencies.sum += seconds;
+    }
+}
+
+/// Circuit breaker of an HTTP client.
+pub type CircuitBreaker = failsafe::StateMachine<ConsecutiveFailures<Constant>, ()>;
+
+/// Error of a request called through the circuit breaker.
+pub trait RequestError: Sized {
+    /// Returns the error of a request rejected by the open circuit breaker.
+    fn rejected() -> Self;
+
+    /// Returns `true` if the request is retried after this error.
+    fn is_retryable(&self) -> bool;
+
+    /// Returns `true` if the circuit breaker records this error as a failure.
+    fn is_recorded(&self) -> bool;
+}
+
+/// Calls a request through the circuit breaker, retrying on the retryable errors.
+pub async fn call<T, E, F, Fut>(circuit_breaker: &CircuitBreaker, request: F) -> Result<T, E>
+where
+    E: RequestError,
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    RetryIf::spawn(
+        retry_strategy(),
+        || async {
+            circuit_breaker
+                .call_with(E::is_recorded, request())
+                .await
+                .map_err(|err| match err {
+                    failsafe::Error::Rejected => E::rejected(),
+                    failsafe::Error::Inner(e) => e,
+                })
+        },
+        E::is_retryable,
+    )
+    .await
+}
+
+/// Creates a circuit breaker with a failure policy that allows up to 5 consecutive failures and will retry after 60 seconds.
+pub fn circuit_breaker() -> CircuitBreaker {
+    let backoff = backoff::constant(Duration::from_secs(60));
+    let policy = failure_policy::consecutive_failures(5, backoff);
+    failsafe::Config::new().failure_policy(policy).build()
+}
+
+/// Create a retry strategy with exponential backoff starting at 10 milliseconds, with jitter, and a maximum of 3 retries.
+fn retry_strategy() -> impl Iterator<Item = Duration> {
+    ExponentialBackoff::from_millis(10).map(jitter).take(3)
+}
+
+/// Check if the error is a HTTP 4xx client error.
+pub fn is_client_error(error: &reqwest::Error) -> bool {
+    error
+        .status()
+        .map(|status_code| StatusCode::is_client_error(&status_code))
+        .unwrap_or(false)
+}
+
+/// Creates a request error with the given HTTP status, to test the predicates.
+#[cfg(test)]
+pub fn create_reqwest_error_with_status(status: StatusCode) -> reqwest::Error {
+    let response = http::Response::builder()
+        .status(status)
+        .body(Vec::new())
+        .unwrap();
+    reqwest::Response::from(response)
+        .error_for_status()
+        .unwrap_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug, PartialEq)]
+    enum TestError {
+        Transient,
+        Permanent,
+        Rejected,
+    }
+
+    impl RequestError for TestError {
+        fn rejected() -> Self {
+            TestError::Rejected
+        }
+
+        fn is_retryable(&self) -> bool {
+            *self == TestError::Transient
+        }
+
+        fn is_recorded(&self) -> bool {
+            *self == TestError::Transient
+        }
+    }
+
+    #[test]
+    fn test_is_client_error() {
+        let err_400 = create_reqwest_error_with_status(StatusCode::BAD_REQUEST);
+        let err_500 = create_reqwest_error_with_status(StatusCode::INTERNAL_SERVER_ERROR);
+
+        assert!(
+            is_client_error(&err_400),
+            "400 error should be a client error"
+        );
+        assert!(
+            !is_client_error(&err_500),
+            "500 error should not be a client error"
+        );
+    }
+
+    #[test]
+    fn test_latency_recorder() {
+        let recorder = LatencyRecorder::default();
+        recorder.record(Duration::from_millis(40));
+        recorder.record(Duration::from_secs(10));
+
+        let latencies = recorder.latencies();
+
+        assert_eq!(latencies.count, 2);
+        assert_eq!(latencies.buckets[2], 1, "40 ms is in the 0.05 s bucket");
+        assert_eq!(
+            latencies.buckets.iter().sum::<u64>(),
+            1,
+            "10 s is beyond the last bucket"
+        );
+        assert!((latencies.sum - 10.04).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_latency_recorder_time() {
+        let recorder = LatencyRecorder::default();
+
+        let result = recorder.time(async { Err::<(), _>("failed") }).await;
+
+        assert_eq!(result, Err("failed"));
+        assert_eq!(recorder.latencies().count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_call_retries_retryable_errors() {
+        let circuit_breaker = circuit_breaker();
+        let attempts = AtomicUsize::new(0);
+
+        let result = call(&circuit_breaker, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(TestError::Transient)
+        })
+        .await;
+
+        assert_eq!(result, Err(TestError::Transient));
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_call_does_not_retry_other_errors() {
+        let circuit_breaker = circuit_breaker();
+        let attempts = AtomicUsize::new(0);
+
+        let result = call(&circuit_breaker, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(TestError::Permanent)
+        })
+        .await;
+
+        assert_eq!(result, Err(TestError::Permanent));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_call_rejected_by_open_circuit_breaker() {
+        let circuit_breaker = circuit_breaker();
+        for _ in 0..2 {
+            call(&circuit_breaker, || async {
+                Err::<(), _>(TestError::Transient)
+            })
+            .await
+            .ok();
+        }
+
+        let result = call(&circuit_breaker, || async { Ok::<_, TestError>(()) }).await;
+
+        assert_eq!(result, Err(TestError::Rejected));
+    }
+}
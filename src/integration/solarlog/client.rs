@@ -1,27 +1,51 @@
 //! SolarLog Client.
 //! This client is the higher level API client for SolarLog.
+use super::consumption::Consumption;
+use super::dc_string::DcString;
+use super::history::EnergyHistory;
 use super::http_client::HttpClient;
+use super::index::Index;
+use super::intraday::IntradayCurve;
+use super::live_data::LiveData;
+use super::query::QueryBuilder;
 use super::{Error, Result};
-use chrono::NaiveDate;
+use crate::integration::Latencies;
+use chrono::{Datelike, NaiveDate};
 use reqwest::Url;
 use serde_json::Value;
 use serde_json::Value::Null;
 use serde_json::json;
-use std::str::FromStr;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
 use strum_macros::Display;
+use strum_macros::EnumIter;
 use strum_macros::EnumString;
 
 pub struct Client {
     http: HttpClient,
+    /// Unknown status strings already warned about, so each one is warned about once.
+    unknown_statuses: Mutex<HashSet<String>>,
 }
 
-static CURRENT_POWER: &str = "782";
-static DAILY_ENERGY: &str = "777";
-static MONTHLY_ENERGY: &str = "779";
-static STATUS: &str = "608";
+/// Sub-index of the live data in the Open JSON block.
+static OPEN_JSON_LIVE_DATA: &str = "170";
+
+/// Value reported by Solar-Log for an unused inverter slot.
+static NO_INVERTER: &str = "Err";
+
+/// Source of the power and energy values read from Solar-Log.
+#[derive(Debug, PartialEq, Eq, EnumString, Display, Clone, Copy, Default)]
+#[strum(serialize_all = "snake_case")]
+pub enum DataSource {
+    /// Per-inverter indices (782, 777, ...).
+    #[default]
+    Index,
+    /// Site-wide Open JSON live data block (801/170).
+    OpenJson,
+}
 
 /// Solar-Log inverter status.
-#[derive(Debug, PartialEq, EnumString, Display, Clone)]
+#[derive(Debug, PartialEq, EnumString, EnumIter, Display, Clone)]
 pub enum InverterStatus {
     #[strum(serialize = "Idle Initializing")]
     IdleInitializing,
@@ -71,6 +95,9 @@ pub enum InverterStatus {
     IvScanning,
     #[strum(serialize = "DC input detection")]
     DcInputDetection,
+    /// Status not known by this version, such as a status added by a firmware update.
+    #[strum(default)]
+    Unknown(String),
 }
 
 impl InverterStatus {
@@ -87,6 +114,15 @@ impl InverterStatus {
                 | InverterStatus::ShutdownDcSwitchOff
         )
     }
+    /// Returns `true` if the inverter is shut down by a fault, not by a command, a limit or the DC switch.
+    pub fn is_fault(&self) -> bool {
+        matches!(
+            self,
+            InverterStatus::ShutdownFault
+                | InverterStatus::ShutdownOvgr
+                | InverterStatus::ShutdownCommDisconnected
+        )
+    }
     /// Returns `true` if the inverter status is idle.
     pub fn is_idle(&self) -> bool {
         matches!(
@@ -113,7 +149,10 @@ impl Client {
     /// Creates a new instance of `Client`.
     pub fn new(url: Url, password: String) -> Self {
         let inner = HttpClient::new(url, password);
-        Client { http: inner }
+        Client {
+            http: inner,
+            unknown_statuses: Mutex::new(HashSet::new()),
+        }
     }
 
     /// Login to SolarLog device.
@@ -123,6 +162,16 @@ impl Client {
         Ok(())
     }
 
+    /// Returns `true` if the circuit breaker of the HTTP client is open and rejects the requests.
+    pub fn is_circuit_open(&self) -> bool {
+        self.http.is_circuit_open()
+    }
+
+    /// Returns the latencies of the requests sent by the HTTP client.
+    pub fn request_latencies(&self) -> Latencies {
+        self.http.latencies()
+    }
+
     pub async fn is_logged_in(&self) -> bool {
         self.http.is_logged_in().await
     }
@@ -133,149 +182,302 @@ impl Client {
         self.http.logout().await
     }
 
-    /// Get the power produced or consumed in Watt (W).
-    pub async fn get_current_power(&self) -> Result<i64> {
-        let query = Self::create_inverter_query(CURRENT_POWER, 0);
+    /// Start a batched query combining several indices and inverters into a single request.
+    pub fn query(&self) -> QueryBuilder<'_> {
+        QueryBuilder::new(self)
+    }
+
+    /// Send a raw JSON query to the SolarLog device.
+    pub(super) async fn send_query(&self, query: &str) -> Result<Value> {
+        self.http.query(query).await
+    }
+
+    /// Get the site-wide live data from the Open JSON interface.
+    pub async fn get_live_data(&self) -> Result<LiveData> {
+        let query = json!({ Index::OpenJson.code(): { OPEN_JSON_LIVE_DATA: Null } }).to_string();
         let json_value = self.http.query(&query).await?;
-        Self::extract_inverter_value_as_i64(&json_value, CURRENT_POWER, 0)
+        Self::extract_live_data(&json_value)
     }
 
-    /// Get the inverter status.
-    pub async fn get_status(&self) -> Result<InverterStatus> {
-        let query = Self::create_inverter_query(STATUS, 0);
+    /// Get the consumption of the site along with the production at the same time.
+    /// The values are read from the Open JSON interface, a consumption meter is required.
+    pub async fn get_consumption(&self) -> Result<Consumption> {
+        let live_data = self.get_live_data().await?;
+        Ok(Consumption {
+            last_update: live_data.last_update,
+            power: live_data.consumption_power,
+            energy_of_day: (live_data.last_update.date(), live_data.consumption_day),
+            production_power: live_data.power_ac,
+        })
+    }
+
+    /// Get the IDs of the inverters connected to the SolarLog device.
+    pub async fn get_inverters(&self) -> Result<Vec<u8>> {
+        let query = json!({ Index::Inverters.code(): Null }).to_string();
         let json_value = self.http.query(&query).await?;
-        Self::extract_inverter_status(&json_value)
+        Self::extract_inverters(&json_value)
+    }
+
+    /// Get the power produced or consumed by the first inverter in Watt (W).
+    pub async fn get_current_power(&self) -> Result<i64> {
+        self.get_inverter_current_power(0).await
+    }
+
+    /// Get the status of the first inverter.
+    pub async fn get_status(&self) -> Result<InverterStatus> {
+        self.get_inverter_status(0).await
     }
 
-    /// Get the energy produced or consumed during the specified day in watt-hours (Wh).
+    /// Get the energy produced or consumed by the first inverter during the specified day in watt-hours (Wh).
     pub async fn get_energy_of_day(&self, day: NaiveDate) -> Result<i64> {
-        let query = Self::create_inverter_query(DAILY_ENERGY, 0);
-        let json_value = self.http.query(&query).await?;
-        Self::extract_energy_of_day(&json_value, day)
+        self.get_inverter_energy_of_day(0, day).await
     }
 
-    /// Get the energy produced or consumed during of last day (today) in watt-hours (Wh).
+    /// Get the energy produced or consumed by the first inverter during of last day (today) in watt-hours (Wh).
     pub async fn get_energy_of_last_day(&self) -> Result<(NaiveDate, i64)> {
-        let query = Self::create_inverter_query(DAILY_ENERGY, 0);
-        let json_value = self.http.query(&query).await?;
-        Self::extract_energy_of_last_day(&json_value)
+        self.get_inverter_energy_of_last_day(0).await
     }
 
-    /// Get the energy produced or consumed during the current month in watt-hours (Wh).
+    /// Get the energy produced or consumed by the first inverter during the current month in watt-hours (Wh).
     pub async fn get_energy_of_month(&self, month: NaiveDate) -> Result<i64> {
-        let query = Self::create_inverter_query(MONTHLY_ENERGY, 0);
+        self.get_inverter_energy_of_month(0, month).await
+    }
+
+    /// Get the energy produced or consumed by the first inverter during the specified year in watt-hours (Wh).
+    pub async fn get_energy_of_year(&self, year: NaiveDate) -> Result<i64> {
+        self.get_inverter_energy_of_year(0, year).await
+    }
+
+    /// Get the energy produced or consumed by the first inverter since installation in watt-hours (Wh).
+    pub async fn get_energy_total(&self) -> Result<i64> {
+        self.get_inverter_energy_total(0).await
+    }
+
+    /// Get the intraday curve of the first inverter for the given day, with a sample every 5 minutes.
+    /// SolarLog only keeps the current day, the curve is empty for any other day.
+    pub async fn get_intraday_curve(&self, day: NaiveDate) -> Result<IntradayCurve> {
+        self.get_inverter_intraday_curve(0, day).await
+    }
+
+    /// Get the intraday curve of the given inverter for the given day, with a sample every 5 minutes.
+    pub async fn get_inverter_intraday_curve(
+        &self,
+        inverter_id: u8,
+        day: NaiveDate,
+    ) -> Result<IntradayCurve> {
+        let query = Self::create_inverter_query(Index::IntradayData, inverter_id);
         let json_value = self.http.query(&query).await?;
-        Self::extract_energy_of_month(&json_value, month)
+        let curve: IntradayCurve = Index::IntradayData.extract(&json_value, inverter_id)?;
+        Ok(curve.of_day(day))
     }
 
-    /// Get the value for a specific inverter ID and key as a string.
-    fn create_inverter_query(index: &str, inverter_id: u8) -> String {
-        json!({ index: { inverter_id.to_string(): Null } }).to_string()
+    /// Get the power produced or consumed by the given inverter in Watt (W).
+    pub async fn get_inverter_current_power(&self, inverter_id: u8) -> Result<i64> {
+        let query = Self::create_inverter_query(Index::CurrentPower, inverter_id);
+        let json_value = self.http.query(&query).await?;
+        Index::CurrentPower.extract(&json_value, inverter_id)
     }
 
-    /// Extract the energy for the current day.
-    fn extract_energy_of_day(json_value: &Value, day: NaiveDate) -> Result<i64> {
-        let day_string = day.format("%d.%m.%y").to_string();
-        Self::extract_inverter_value_by_id_as_i64(json_value, DAILY_ENERGY, 0, &day_string)
+    /// Get the status of the given inverter.
+    pub async fn get_inverter_status(&self, inverter_id: u8) -> Result<InverterStatus> {
+        let query = Self::create_inverter_query(Index::Status, inverter_id);
+        let json_value = self.http.query(&query).await?;
+        self.extract_inverter_status(&json_value, inverter_id)
     }
 
-    /// Extract the energy of the last day.
-    fn extract_energy_of_last_day(json_value: &Value) -> Result<(NaiveDate, i64)> {
-        json_value
-            .get(DAILY_ENERGY)
-            .and_then(|v| v.get("0")?.as_array())
-            .and_then(|arr| arr.last())
-            .and_then(|entry| {
-                let date_str = entry
-                    .get(0)?
-                    .as_str()
-                    .and_then(|s| NaiveDate::parse_from_str(s, "%d.%m.%y").ok())?;
-                let wh = entry.get(1)?.as_array()?.first()?.as_i64()?;
-                Some((date_str, wh))
-            })
-            .ok_or_else(|| Error::ValueParseError("cannot extract last day and energy".to_string()))
+    /// Get the DC measurements of each string of the given inverter.
+    pub async fn get_inverter_dc_strings(&self, inverter_id: u8) -> Result<Vec<DcString>> {
+        let query = Self::create_inverter_query(Index::DcStrings, inverter_id);
+        let json_value = self.http.query(&query).await?;
+        Index::DcStrings.extract(&json_value, inverter_id)
     }
 
-    /// Extract the energy for the current month.
-    fn extract_energy_of_month(json_value: &Value, month: NaiveDate) -> Result<i64> {
-        let month_string = month.format("01.%m.%y").to_string();
-        Self::extract_inverter_value_by_id_as_i64(json_value, MONTHLY_ENERGY, 0, &month_string)
+    /// Get the energy produced or consumed by the given inverter during the specified day in watt-hours (Wh).
+    pub async fn get_inverter_energy_of_day(&self, inverter_id: u8, day: NaiveDate) -> Result<i64> {
+        let query = Self::create_inverter_query(Index::DailyEnergy, inverter_id);
+        let json_value = self.http.query(&query).await?;
+        Self::extract_energy_of_day(&json_value, inverter_id, day)
+    }
+
+    /// Get the energy produced or consumed by the given inverter during of last day (today) in watt-hours (Wh).
+    pub async fn get_inverter_energy_of_last_day(
+        &self,
+        inverter_id: u8,
+    ) -> Result<(NaiveDate, i64)> {
+        let query = Self::create_inverter_query(Index::DailyEnergy, inverter_id);
+        let json_value = self.http.query(&query).await?;
+        Self::extract_energy_of_last_day(&json_value, inverter_id)
     }
 
-    /// Extract the status of the first inverter as a enum.
-    pub fn extract_inverter_status(json_value: &Value) -> Result<InverterStatus> {
-        let status_str = Self::extract_inverter_value_as_string(json_value, STATUS, 0)?;
-        InverterStatus::from_str(status_str)
-            .map_err(|_| Error::ValueParseError(format!("Invalid inverter status: {status_str}")))
+    /// Get the energy produced or consumed by the given inverter during the current month in watt-hours (Wh).
+    pub async fn get_inverter_energy_of_month(
+        &self,
+        inverter_id: u8,
+        month: NaiveDate,
+    ) -> Result<i64> {
+        let query = Self::create_inverter_query(Index::MonthlyEnergy, inverter_id);
+        let json_value = self.http.query(&query).await?;
+        Self::extract_energy_of_month(&json_value, inverter_id, month)
+    }
+
+    /// Get the energy produced or consumed by the given inverter during the specified year in watt-hours (Wh).
+    pub async fn get_inverter_energy_of_year(
+        &self,
+        inverter_id: u8,
+        year: NaiveDate,
+    ) -> Result<i64> {
+        let query = Self::create_inverter_query(Index::YearlyEnergy, inverter_id);
+        let json_value = self.http.query(&query).await?;
+        Self::extract_energy_of_year(&json_value, inverter_id, year)
+    }
+
+    /// Get the energy produced or consumed by the given inverter since installation in watt-hours (Wh).
+    /// This is the sum of all the yearly values kept by the SolarLog device.
+    pub async fn get_inverter_energy_total(&self, inverter_id: u8) -> Result<i64> {
+        let query = Self::create_inverter_query(Index::YearlyEnergy, inverter_id);
+        let json_value = self.http.query(&query).await?;
+        Self::extract_energy_total(&json_value, inverter_id)
+    }
+
+    /// Get the daily and monthly energy history of the site, summed over the given inverters.
+    pub async fn get_energy_history(&self, inverters: &[u8]) -> Result<EnergyHistory> {
+        let snapshot = self
+            .query()
+            .inverters(inverters)
+            .energy_history()
+            .send()
+            .await?;
+        Ok(EnergyHistory::sum(snapshot.energy_history.values()))
+    }
+
+    /// Create the query of an index for a specific inverter ID.
+    fn create_inverter_query(index: Index, inverter_id: u8) -> String {
+        json!({ index.code(): { inverter_id.to_string(): Null } }).to_string()
+    }
+
+    /// Extract the Open JSON live data block.
+    fn extract_live_data(json_value: &Value) -> Result<LiveData> {
+        Index::OpenJson.extract_nested(json_value, OPEN_JSON_LIVE_DATA)
+    }
+
+    /// Extract the IDs of the connected inverters, skipping unused slots.
+    fn extract_inverters(json_value: &Value) -> Result<Vec<u8>> {
+        let slots: BTreeMap<u8, String> = Index::Inverters.extract_all(json_value)?;
+        Ok(slots
+            .into_iter()
+            .filter(|(_, model)| model != NO_INVERTER)
+            .map(|(inverter_id, _)| inverter_id)
+            .collect())
+    }
+
+    /// Extract the energy for the current day.
+    fn extract_energy_of_day(json_value: &Value, inverter_id: u8, day: NaiveDate) -> Result<i64> {
+        Self::extract_dated_value(json_value, Index::DailyEnergy, inverter_id, day)
+    }
+
+    /// Extract the energy of the last day.
+    pub(super) fn extract_energy_of_last_day(
+        json_value: &Value,
+        inverter_id: u8,
+    ) -> Result<(NaiveDate, i64)> {
+        Self::extract_last_dated_value(json_value, Index::DailyEnergy, inverter_id)
     }
 
-    /// Extract the value for a specific inverter ID and index as i64.
-    fn extract_inverter_value_as_i64(
+    /// Extract the energy of the last month.
+    pub(super) fn extract_energy_of_last_month(
+        json_value: &Value,
+        inverter_id: u8,
+    ) -> Result<(NaiveDate, i64)> {
+        Self::extract_last_dated_value(json_value, Index::MonthlyEnergy, inverter_id)
+    }
+
+    /// Extract the energy for the current month.
+    fn extract_energy_of_month(
         json_value: &Value,
-        index: &str,
         inverter_id: u8,
+        month: NaiveDate,
     ) -> Result<i64> {
-        let value = json_value[index][inverter_id.to_string()]
-            .as_str()
-            .ok_or_else(|| {
-                Error::ValueParseError(format!(
-                    "Missing value for index {index} and inverter {inverter_id}"
-                ))
-            })?
-            .parse::<i64>()
-            .map_err(|_| {
-                Error::ValueParseError(format!(
-                    "Invalid i64 value for index {index} and inverter {inverter_id}"
-                ))
-            })?;
-        Ok(value)
+        let month = month.with_day(1).expect("invalid month");
+        Self::extract_dated_value(json_value, Index::MonthlyEnergy, inverter_id, month)
     }
 
-    /// Extract the value for a specific inverter ID and index as a string.
-    fn extract_inverter_value_as_string<'a>(
-        json_value: &'a Value,
-        index: &str,
+    /// Extract the energy for the given year.
+    fn extract_energy_of_year(json_value: &Value, inverter_id: u8, year: NaiveDate) -> Result<i64> {
+        let year = year.with_ordinal(1).expect("invalid year");
+        Self::extract_dated_value(json_value, Index::YearlyEnergy, inverter_id, year)
+    }
+
+    /// Extract the energy since installation, summing all the yearly values.
+    pub(super) fn extract_energy_total(json_value: &Value, inverter_id: u8) -> Result<i64> {
+        let years: Vec<(NaiveDate, i64)> = Index::YearlyEnergy.extract(json_value, inverter_id)?;
+        Ok(years.iter().map(|(_, wh)| wh).sum())
+    }
+
+    /// Extract the dated value of the given date for a specific inverter ID and index.
+    fn extract_dated_value(
+        json_value: &Value,
+        index: Index,
         inverter_id: u8,
-    ) -> Result<&'a str> {
-        json_value
-            .get(index)
-            .and_then(|v| v.get(inverter_id.to_string()))
-            .and_then(|v| v.as_str())
+        date: NaiveDate,
+    ) -> Result<i64> {
+        let values: Vec<(NaiveDate, i64)> = index.extract(json_value, inverter_id)?;
+        values
+            .into_iter()
+            .find_map(|(other_date, value)| (other_date == date).then_some(value))
             .ok_or_else(|| {
                 Error::ValueParseError(format!(
-                    "Missing string value for index {index} and inverter {inverter_id}"
+                    "Missing value for index {}, inverter {inverter_id}, date {date}",
+                    index.code()
                 ))
             })
     }
 
-    /// Extract the value for a specific inverter ID and id as an i64.
-    fn extract_inverter_value_by_id_as_i64(
+    /// Extract the last dated value for a specific inverter ID and index.
+    fn extract_last_dated_value(
         json_value: &Value,
-        index: &str,
+        index: Index,
         inverter_id: u8,
-        id: &str,
-    ) -> Result<i64> {
-        let value = json_value
-            .get(index)
-            .and_then(|v| v.get(inverter_id.to_string()))
-            .and_then(|v| v.as_array())
-            .and_then(|arr| {
-                arr.iter().find_map(|element| {
-                    let element_id = element.get(0)?.as_str()?;
-                    let element_value = element.get(1)?.as_array()?.first()?.as_i64()?;
-                    (element_id == id).then_some(element_value)
-                })
-            })
-            .ok_or(Error::ValueParseError(format!(
-                "Missing value for index {index}, inverter {inverter_id}, id {id}"
-            )))?;
-        Ok(value)
+    ) -> Result<(NaiveDate, i64)> {
+        let values: Vec<(NaiveDate, i64)> = index.extract(json_value, inverter_id)?;
+        values.last().copied().ok_or_else(|| {
+            Error::ValueParseError(format!(
+                "Missing last dated value for index {} and inverter {inverter_id}",
+                index.code()
+            ))
+        })
+    }
+
+    /// Extract the status of the given inverter as a enum.
+    /// Unknown status strings are returned as `InverterStatus::Unknown`, and warned about the first time only.
+    pub fn extract_inverter_status(
+        &self,
+        json_value: &Value,
+        inverter_id: u8,
+    ) -> Result<InverterStatus> {
+        let status: InverterStatus = Index::Status.extract(json_value, inverter_id)?;
+        if let InverterStatus::Unknown(status) = &status {
+            if self.is_new_unknown_status(status) {
+                log::warn!("Unknown status '{status}' for inverter {inverter_id}");
+            } else {
+                log::debug!("Unknown status '{status}' for inverter {inverter_id}");
+            }
+        }
+        Ok(status)
+    }
+
+    /// Returns `true` the first time the given unknown status string is seen.
+    fn is_new_unknown_status(&self, status: &str) -> bool {
+        self.unknown_statuses
+            .lock()
+            .expect("poisoned unknown statuses lock")
+            .insert(status.to_string())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::str::FromStr;
 
     #[test]
     fn test_inverter_status_is_shutdown() {
@@ -301,6 +503,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_inverter_status_is_fault() {
+        let fault_statuses = [
+            InverterStatus::ShutdownFault,
+            InverterStatus::ShutdownOvgr,
+            InverterStatus::ShutdownCommDisconnected,
+        ];
+        for status in fault_statuses.iter() {
+            assert!(status.is_fault());
+        }
+        let not_fault_statuses = [
+            InverterStatus::ShutdownCommand,
+            InverterStatus::ShutdownDcSwitchOff,
+            InverterStatus::OnGrid,
+            InverterStatus::Unknown("Standby".to_string()),
+        ];
+        for status in not_fault_statuses.iter() {
+            assert!(!status.is_fault());
+        }
+    }
+
     #[test]
     fn test_inverter_status_is_idle() {
         let idle_statuses = [
@@ -360,8 +583,10 @@ mod tests {
     }
 
     #[test]
-    fn test_inverter_status_from_str_invalid() {
-        assert!(InverterStatus::from_str("Not a status").is_err());
+    fn test_inverter_status_from_str_unknown() {
+        let status = InverterStatus::from_str("Not a status").unwrap();
+        assert_eq!(status, InverterStatus::Unknown("Not a status".to_string()));
+        assert_eq!(status.to_string(), "Not a status");
     }
 
     #[test]
@@ -373,50 +598,8 @@ mod tests {
 
     #[test]
     fn test_create_inverter_query() {
-        let index = "777";
-        let inverter_id = 1u8;
-        let query = Client::create_inverter_query(index, inverter_id);
-        // Should produce a JSON string like: {"777":{"1":null}}
-        let expected =
-            serde_json::json!({ index: { inverter_id.to_string(): serde_json::Value::Null } })
-                .to_string();
-        assert_eq!(query, expected);
-    }
-
-    #[test]
-    fn test_extract_inverter_value_as_i64() {
-        let json = serde_json::json!({"777": {"0": "12345"}});
-        let val = Client::extract_inverter_value_as_i64(&json, "777", 0).unwrap();
-        assert_eq!(val, 12345);
-        let missing = Client::extract_inverter_value_as_i64(&json, "999", 0);
-        assert!(missing.is_err());
-    }
-
-    #[test]
-    fn test_extract_inverter_value_as_i64_invalid_parse() {
-        let json = serde_json::json!({"777": {"0": "notanumber"}});
-        let result = Client::extract_inverter_value_as_i64(&json, "777", 0);
-        assert!(matches!(
-            result,
-            Err(Error::ValueParseError(msg)) if msg.contains("Invalid i64 value for index 777 and inverter 0")
-        ));
-    }
-
-    #[test]
-    fn test_extract_inverter_value_as_string_with_value() {
-        let json = serde_json::json!({"608": {"0": "On-grid"}});
-        let val = Client::extract_inverter_value_as_string(&json, "608", 0).unwrap();
-        assert_eq!(val, "On-grid");
-    }
-
-    #[test]
-    fn test_extract_inverter_value_as_string_missing_value() {
-        let json = serde_json::json!({"608": {"0": "On-grid"}});
-        let missing = Client::extract_inverter_value_as_string(&json, "999", 0);
-        assert!(matches!(
-            missing,
-            Err(Error::ValueParseError(msg)) if msg.contains("Missing string value for index 999 and inverter 0")
-        ));
+        let query = Client::create_inverter_query(Index::DailyEnergy, 1);
+        assert_eq!(query, r#"{"777":{"1":null}}"#);
     }
 
     #[test]
@@ -455,7 +638,7 @@ mod tests {
                     }
                 }
         );
-        let result = Client::extract_energy_of_day(&json, day).expect("cannot extract energy");
+        let result = Client::extract_energy_of_day(&json, 0, day).expect("cannot extract energy");
         assert_eq!(result, 510);
     }
 
@@ -496,12 +679,134 @@ mod tests {
         );
 
         let (date, energy) =
-            Client::extract_energy_of_last_day(&json).expect("cannot extract last day energy");
+            Client::extract_energy_of_last_day(&json, 0).expect("cannot extract last day energy");
 
         assert_eq!(date, NaiveDate::from_ymd_opt(2025, 6, 25).unwrap());
         assert_eq!(energy, 510);
     }
 
+    #[test]
+    fn test_extract_energy_of_last_day_other_inverter() {
+        let json = serde_json::json!(
+            {
+                "777": {
+                    "0": [["24.06.25", [28430]], ["25.06.25", [510]]],
+                    "2": [["24.06.25", [14200]], ["25.06.25", [260]]]
+                }
+            }
+        );
+
+        let (date, energy) =
+            Client::extract_energy_of_last_day(&json, 2).expect("cannot extract last day energy");
+
+        assert_eq!(date, NaiveDate::from_ymd_opt(2025, 6, 25).unwrap());
+        assert_eq!(energy, 260);
+        assert!(Client::extract_energy_of_last_day(&json, 1).is_err());
+    }
+
+    #[test]
+    fn test_extract_live_data() {
+        let json = serde_json::json!({"801": {"170": {
+            "100": "25.06.25 14:05:00",
+            "101": 4120, "102": 4300, "103": 231, "104": 612,
+            "105": 18230, "106": 28430, "107": 550370, "108": 3125400, "109": 48211000,
+            "110": 640, "111": 5210, "112": 9870, "113": 190200, "114": 1320500, "115": 20450000,
+            "116": 9900
+        }}});
+        let live_data = Client::extract_live_data(&json).unwrap();
+        assert_eq!(live_data.power_ac, 4120);
+        assert_eq!(live_data.yield_day, 18230);
+
+        let json = serde_json::json!({"801": {"171": {}}});
+        assert!(matches!(
+            Client::extract_live_data(&json),
+            Err(Error::ValueParseError(msg)) if msg.contains("Missing value for index 801/170")
+        ));
+
+        let json = serde_json::json!({"801": {"170": {"100": "25.06.25 14:05:00"}}});
+        assert!(matches!(
+            Client::extract_live_data(&json),
+            Err(Error::ValueParseError(msg)) if msg.contains("Invalid nested value for index 801/170")
+        ));
+    }
+
+    #[test]
+    fn test_data_source_from_str_and_display() {
+        assert_eq!(DataSource::from_str("index").unwrap(), DataSource::Index);
+        assert_eq!(
+            DataSource::from_str("open_json").unwrap(),
+            DataSource::OpenJson
+        );
+        assert_eq!(DataSource::OpenJson.to_string(), "open_json");
+        assert_eq!(DataSource::default(), DataSource::Index);
+        assert!(DataSource::from_str("modbus").is_err());
+    }
+
+    #[test]
+    fn test_extract_inverters() {
+        let json = serde_json::json!({"740": {"0": "SMA", "2": "Huawei", "1": "Fronius", "3": "Err", "4": "Err"}});
+        let inverters = Client::extract_inverters(&json).unwrap();
+        assert_eq!(inverters, vec![0, 1, 2]);
+
+        let json = serde_json::json!({"740": {"0": "Err"}});
+        assert!(Client::extract_inverters(&json).unwrap().is_empty());
+
+        let json = serde_json::json!({"740": {"x": "SMA"}});
+        assert!(matches!(
+            Client::extract_inverters(&json),
+            Err(Error::ValueParseError(msg)) if msg.contains("Invalid nested value for index 740")
+        ));
+
+        let json = serde_json::json!({"999": {"0": "SMA"}});
+        assert!(Client::extract_inverters(&json).is_err());
+    }
+
+    #[test]
+    fn test_extract_energy_of_last_month() {
+        let json = serde_json::json!(
+            {
+                "779": {
+                    "0": [["01.05.25", [610250]], ["01.06.25", [550370]]]
+                }
+            }
+        );
+
+        let (month, energy) =
+            Client::extract_energy_of_last_month(&json, 0).expect("cannot extract month energy");
+
+        assert_eq!(month, NaiveDate::from_ymd_opt(2025, 6, 1).unwrap());
+        assert_eq!(energy, 550370);
+    }
+
+    #[test]
+    fn test_extract_energy_of_year() {
+        let year = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let json = serde_json::json!(
+            {
+                "854": {
+                    "0": [["01.01.23", [9542100]], ["01.01.24", [10125400]], ["01.01.25", [5203000]]]
+                }
+            }
+        );
+        let energy =
+            Client::extract_energy_of_year(&json, 0, year).expect("cannot extract year energy");
+        assert_eq!(energy, 10125400);
+    }
+
+    #[test]
+    fn test_extract_energy_total() {
+        let json = serde_json::json!(
+            {
+                "854": {
+                    "0": [["01.01.23", [9542100]], ["01.01.24", [10125400]], ["01.01.25", [5203000]]]
+                }
+            }
+        );
+        let energy = Client::extract_energy_total(&json, 0).expect("cannot extract total energy");
+        assert_eq!(energy, 24870500);
+        assert!(Client::extract_energy_total(&json, 1).is_err());
+    }
+
     #[test]
     fn test_extract_energy_for_current_month() {
         let month = NaiveDate::from_ymd_opt(2025, 6, 1).expect("cannot create month date");
@@ -513,7 +818,7 @@ mod tests {
             }
         );
         let month =
-            Client::extract_energy_of_month(&json, month).expect("cannot extract month energy");
+            Client::extract_energy_of_month(&json, 0, month).expect("cannot extract month energy");
         assert_eq!(month, 550370);
     }
 
@@ -521,23 +826,33 @@ mod tests {
     fn test_extract_inverter_status() {
         // Valid status
         let json = serde_json::json!({"608": {"0": "On-grid"}});
-        let status = Client::extract_inverter_status(&json).unwrap();
+        let client = Client::new(Url::parse("http://localhost:8080").unwrap(), String::new());
+        let status = client.extract_inverter_status(&json, 0).unwrap();
         assert_eq!(status, InverterStatus::OnGrid);
 
-        // Invalid status string
+        // Unknown status string
         let json = serde_json::json!({"608": {"0": "Not a status"}});
-        let status = Client::extract_inverter_status(&json);
-        assert!(matches!(
-            status,
-            Err(Error::ValueParseError(msg)) if msg.contains("Invalid inverter status")
-        ));
+        let status = client.extract_inverter_status(&json, 0).unwrap();
+        assert_eq!(status, InverterStatus::Unknown("Not a status".to_string()));
 
         // Missing status
         let json = serde_json::json!({"999": {"0": "On-grid"}});
-        let status = Client::extract_inverter_status(&json);
+        let status = client.extract_inverter_status(&json, 0);
         assert!(matches!(
             status,
-            Err(Error::ValueParseError(msg)) if msg.contains("Missing string value for index 608 and inverter 0")
+            Err(Error::ValueParseError(msg)) if msg.contains("Missing value for index 608 and inverter 0")
         ));
     }
+
+    #[test]
+    fn test_is_new_unknown_status() {
+        let url = Url::parse("http://localhost:8080").unwrap();
+        let client = Client::new(url.clone(), String::new());
+        assert!(client.is_new_unknown_status("Seen once"));
+        assert!(!client.is_new_unknown_status("Seen once"));
+        assert!(client.is_new_unknown_status("Seen twice"));
+
+        let other = Client::new(url, String::new());
+        assert!(other.is_new_unknown_status("Seen once"));
+    }
 }
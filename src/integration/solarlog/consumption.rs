@@ -0,0 +1,164 @@
+//! SolarLog consumption meter.
+//! Consumption is read together with the production so the derived grid flows are consistent.
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+
+/// Longest time between two consumptions integrated into the grid energy, a longer gap restarts the integration.
+const MAX_INTEGRATION_GAP: TimeDelta = TimeDelta::minutes(15);
+
+/// Consumption and production of the site at the same point in time.
+/// Power values are in Watt (W), energy values in watt-hours (Wh).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Consumption {
+    /// Time of the reading on the device (local time).
+    pub last_update: NaiveDateTime,
+    /// Current consumption power.
+    pub power: i64,
+    /// Energy consumed during the day.
+    pub energy_of_day: (NaiveDate, i64),
+    /// Current production power.
+    pub production_power: i64,
+}
+
+impl Consumption {
+    /// Power imported from the grid, when the consumption exceeds the production.
+    pub fn grid_import_power(&self) -> i64 {
+        (self.power - self.production_power).max(0)
+    }
+
+    /// Power exported to the grid, when the production exceeds the consumption.
+    pub fn grid_export_power(&self) -> i64 {
+        (self.production_power - self.power).max(0)
+    }
+
+    /// Part of the production consumed on site.
+    pub fn self_consumption_power(&self) -> i64 {
+        self.power.min(self.production_power).max(0)
+    }
+
+    /// Share of the production consumed on site in percent (%).
+    /// Returns `None` when nothing is produced.
+    pub fn self_consumption_ratio(&self) -> Option<f64> {
+        if self.production_power <= 0 {
+            return None;
+        }
+        Some(self.self_consumption_power() as f64 * 100.0 / self.production_power as f64)
+    }
+}
+
+/// Energy imported from and exported to the grid, integrated from the grid flows of successive consumptions.
+/// Energy values are in watt-hours (Wh).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GridEnergy {
+    /// Energy imported from the grid.
+    pub import: f64,
+    /// Energy exported to the grid.
+    pub export: f64,
+    /// Time and grid flows of the last consumption.
+    last: Option<(NaiveDateTime, i64, i64)>,
+}
+
+impl GridEnergy {
+    /// Creates the grid energy starting from the given imported and exported energy.
+    pub fn new(import: f64, export: f64) -> Self {
+        GridEnergy {
+            import,
+            export,
+            last: None,
+        }
+    }
+
+    /// Adds the energy that flowed since the last consumption, with the mean grid flows over the elapsed time.
+    /// The first consumption only starts the integration, as does a consumption after a gap longer than
+    /// `MAX_INTEGRATION_GAP`. A consumption not newer than the last one is ignored.
+    pub fn add(&mut self, consumption: &Consumption) {
+        let import = consumption.grid_import_power();
+        let export = consumption.grid_export_power();
+        if let Some((time, last_import, last_export)) = self.last {
+            let elapsed = consumption.last_update - time;
+            if elapsed <= TimeDelta::zero() {
+                return;
+            }
+            if elapsed <= MAX_INTEGRATION_GAP {
+                let hours = elapsed.num_milliseconds() as f64 / 3_600_000.0;
+                self.import += (last_import + import) as f64 / 2.0 * hours;
+                self.export += (last_export + export) as f64 / 2.0 * hours;
+            }
+        }
+        self.last = Some((consumption.last_update, import, export));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn consumption(power: i64, production_power: i64) -> Consumption {
+        Consumption {
+            last_update: NaiveDate::from_ymd_opt(2025, 6, 25)
+                .unwrap()
+                .and_hms_opt(14, 5, 0)
+                .unwrap(),
+            power,
+            energy_of_day: (NaiveDate::from_ymd_opt(2025, 6, 25).unwrap(), 5210),
+            production_power,
+        }
+    }
+
+    #[rstest]
+    #[case(600, 4000, 0, 3400, 600, Some(15.0))]
+    #[case(4000, 1000, 3000, 0, 1000, Some(100.0))]
+    #[case(800, 0, 800, 0, 0, None)]
+    #[case(2000, 2000, 0, 0, 2000, Some(100.0))]
+    fn test_derived_values(
+        #[case] power: i64,
+        #[case] production_power: i64,
+        #[case] grid_import: i64,
+        #[case] grid_export: i64,
+        #[case] self_consumption: i64,
+        #[case] ratio: Option<f64>,
+    ) {
+        let consumption = consumption(power, production_power);
+        assert_eq!(consumption.grid_import_power(), grid_import);
+        assert_eq!(consumption.grid_export_power(), grid_export);
+        assert_eq!(consumption.self_consumption_power(), self_consumption);
+        assert_eq!(consumption.self_consumption_ratio(), ratio);
+    }
+
+    #[test]
+    fn test_grid_energy_add() {
+        let mut energy = GridEnergy::new(1000.0, 0.0);
+        let mut exporting = consumption(600, 4200);
+        let mut importing = consumption(3000, 1000);
+
+        energy.add(&exporting);
+        exporting.last_update += TimeDelta::minutes(10);
+        energy.add(&exporting);
+        importing.last_update = exporting.last_update + TimeDelta::minutes(6);
+        energy.add(&importing);
+
+        // 10 min at 3600 W exported, then 6 min from 3600 W exported to 2000 W imported
+        assert_eq!(energy.import, 1100.0);
+        assert_eq!(energy.export, 780.0);
+    }
+
+    #[test]
+    fn test_grid_energy_gap() {
+        let mut energy = GridEnergy::default();
+        let mut importing = consumption(3000, 1000);
+
+        energy.add(&importing);
+        importing.last_update += TimeDelta::hours(1);
+        energy.add(&importing);
+        energy.add(&importing);
+
+        assert_eq!(
+            energy,
+            GridEnergy {
+                import: 0.0,
+                export: 0.0,
+                last: Some((importing.last_update, 2000, 0)),
+            }
+        );
+    }
+}
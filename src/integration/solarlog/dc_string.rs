@@ -0,0 +1,39 @@
+//! SolarLog DC measurements of the inverter strings.
+//! Each inverter reports one entry per MPPT tracker or string, such as `[612.4, 6.8, 4165]`.
+use serde::Deserialize;
+
+/// DC measurement of one string of an inverter.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DcString {
+    /// DC voltage in Volt (V).
+    pub voltage: f64,
+    /// DC current in Ampere (A).
+    pub current: f64,
+    /// DC power in Watt (W).
+    pub power: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_dc_string() {
+        let json = serde_json::json!([612.4, 6.8, 4165]);
+        let dc_string: DcString = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            dc_string,
+            DcString {
+                voltage: 612.4,
+                current: 6.8,
+                power: 4165,
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_dc_string_invalid() {
+        let json = serde_json::json!([612.4, 6.8]);
+        assert!(serde_json::from_value::<DcString>(json).is_err());
+    }
+}
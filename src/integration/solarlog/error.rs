@@ -16,6 +16,8 @@ pub enum Error {
 
     #[error("Query failed: impossible query")]
     QueryImpossible,
+    #[error("Query failed: no index requested")]
+    QueryEmpty,
     #[error("Response JSON error: {0}")]
     ResponseJsonError(#[from] serde_json::Error),
     #[error("Value parse error: {0}")]
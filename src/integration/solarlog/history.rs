@@ -0,0 +1,120 @@
+//! SolarLog energy history.
+//! The device keeps the daily values of the last days and the monthly values since installation.
+use chrono::{Datelike, NaiveDate};
+use std::collections::BTreeMap;
+
+/// Daily and monthly energy values in watt-hours (Wh), keyed by the first day of the period.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnergyHistory {
+    /// Energy of each day.
+    pub daily: BTreeMap<NaiveDate, i64>,
+    /// Energy of each month, keyed by the first day of the month.
+    pub monthly: BTreeMap<NaiveDate, i64>,
+}
+
+impl EnergyHistory {
+    /// Creates the history from the dated values of the SolarLog device.
+    pub fn new(daily: Vec<(NaiveDate, i64)>, monthly: Vec<(NaiveDate, i64)>) -> Self {
+        EnergyHistory {
+            daily: daily.into_iter().collect(),
+            monthly: monthly.into_iter().collect(),
+        }
+    }
+
+    /// Sums the histories of several inverters into a site history.
+    pub fn sum<'a>(histories: impl IntoIterator<Item = &'a EnergyHistory>) -> Self {
+        let mut total = EnergyHistory::default();
+        for history in histories {
+            for (day, wh) in history.daily.iter() {
+                *total.daily.entry(*day).or_default() += wh;
+            }
+            for (month, wh) in history.monthly.iter() {
+                *total.monthly.entry(*month).or_default() += wh;
+            }
+        }
+        total
+    }
+
+    /// Returns the energy of consecutive periods without overlap, sorted by date.
+    /// Daily values are used where available, the rest of each month is attributed to its first day.
+    pub fn periods(&self) -> Vec<(NaiveDate, i64)> {
+        let mut periods = self.daily.clone();
+        for (month, wh) in self.monthly.iter() {
+            let covered: i64 = self
+                .daily
+                .iter()
+                .filter(|(day, _)| day.year() == month.year() && day.month() == month.month())
+                .map(|(_, wh)| wh)
+                .sum();
+            let remainder = wh - covered;
+            if remainder > 0 {
+                *periods.entry(*month).or_default() += remainder;
+            }
+        }
+        periods.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_periods_without_overlap() {
+        let history = EnergyHistory::new(
+            vec![
+                (date(2025, 5, 30), 20000),
+                (date(2025, 5, 31), 22000),
+                (date(2025, 6, 1), 21700),
+                (date(2025, 6, 2), 9550),
+            ],
+            vec![
+                (date(2025, 4, 1), 480000),
+                (date(2025, 5, 1), 610000),
+                (date(2025, 6, 1), 31250),
+            ],
+        );
+
+        let periods = history.periods();
+
+        assert_eq!(
+            periods,
+            vec![
+                (date(2025, 4, 1), 480000),
+                (date(2025, 5, 1), 568000),
+                (date(2025, 5, 30), 20000),
+                (date(2025, 5, 31), 22000),
+                (date(2025, 6, 1), 21700),
+                (date(2025, 6, 2), 9550),
+            ]
+        );
+        let total: i64 = periods.iter().map(|(_, wh)| wh).sum();
+        assert_eq!(total, 480000 + 610000 + 31250);
+    }
+
+    #[test]
+    fn test_sum() {
+        let first = EnergyHistory::new(
+            vec![(date(2025, 6, 1), 21700)],
+            vec![(date(2025, 6, 1), 21700)],
+        );
+        let second = EnergyHistory::new(
+            vec![(date(2025, 6, 1), 10000), (date(2025, 6, 2), 5000)],
+            vec![(date(2025, 6, 1), 15000)],
+        );
+
+        let total = EnergyHistory::sum([&first, &second]);
+
+        assert_eq!(
+            total,
+            EnergyHistory::new(
+                vec![(date(2025, 6, 1), 31700), (date(2025, 6, 2), 5000)],
+                vec![(date(2025, 6, 1), 36700)],
+            )
+        );
+    }
+}
@@ -1,24 +1,21 @@
 //! SolarLog HTTP client.
 //! This is the lower level client for SolarLog.
 use super::error::{Error, Result};
-use failsafe::{
-    backoff::{self, Constant},
-    failure_policy::{self, ConsecutiveFailures},
-    futures::CircuitBreaker,
+use crate::integration::resilience::{
+    self, CircuitBreaker, Latencies, LatencyRecorder, RequestError,
 };
-use reqwest::{Client, StatusCode, Url};
+use reqwest::{Client, Url};
 use serde_json::Value;
 use std::time::Duration;
 use tokio::sync::RwLock;
-use tokio_retry::RetryIf;
-use tokio_retry::strategy::{ExponentialBackoff, jitter};
 
 pub struct HttpClient {
     client: Client,
     password: String,
     base_url: Url,
     token: RwLock<Option<String>>,
-    circuit_breaker: failsafe::StateMachine<ConsecutiveFailures<Constant>, ()>,
+    circuit_breaker: CircuitBreaker,
+    latency: LatencyRecorder,
 }
 
 impl HttpClient {
@@ -36,10 +33,21 @@ impl HttpClient {
             password,
             base_url: url,
             token: RwLock::new(None),
-            circuit_breaker: Self::circuit_breaker(),
+            circuit_breaker: resilience::circuit_breaker(),
+            latency: LatencyRecorder::default(),
         }
     }
 
+    /// Returns `true` if the circuit breaker is open and rejects the requests.
+    pub fn is_circuit_open(&self) -> bool {
+        !self.circuit_breaker.is_call_permitted()
+    }
+
+    /// Returns the latencies of the requests sent so far.
+    pub fn latencies(&self) -> Latencies {
+        self.latency.latencies()
+    }
+
     /// Check if the client is logged in.
     /// Returns `true` if logged in, `false` otherwise.
     pub async fn is_logged_in(&self) -> bool {
@@ -50,21 +58,10 @@ impl HttpClient {
     /// Login to SolarLog device.
     /// If `force` is true, it will always login even if already logged in.
     pub async fn login(&self, force: bool) -> Result<()> {
-        RetryIf::spawn(
-            Self::retry_strategy(),
-            || async {
-                self.circuit_breaker
-                    .call_with(Self::is_recorded_error, self.do_login(force))
-                    .await
-                    .map_err(|err| match err {
-                        failsafe::Error::Inner(e) => e,
-                        failsafe::Error::Rejected => Error::RequestRejected,
-                    })
-            },
-            Self::is_retryable_error,
-        )
-        .await?;
-        Ok(())
+        resilience::call(&self.circuit_breaker, || {
+            self.latency.time(self.do_login(force))
+        })
+        .await
     }
 
     /// Logs out from the SolarLog device.
@@ -91,19 +88,9 @@ impl HttpClient {
 
     /// Query the SolarLog device.
     pub async fn query(&self, query: &str) -> Result<Value> {
-        RetryIf::spawn(
-            Self::retry_strategy(),
-            || async {
-                self.circuit_breaker
-                    .call_with(Self::is_recorded_error, self.do_query(query))
-                    .await
-                    .map_err(|err| match err {
-                        failsafe::Error::Inner(e) => e,
-                        failsafe::Error::Rejected => Error::RequestRejected,
-                    })
-            },
-            Self::is_retryable_error,
-        )
+        resilience::call(&self.circuit_breaker, || {
+            self.latency.time(self.do_query(query))
+        })
         .await
     }
 
@@ -251,43 +238,26 @@ impl HttpClient {
         }
         Ok(text)
     }
+}
 
-    /// Creates a circuit breaker with a failure policy that allows up to 3 consecutive failures and will retry after 60 seconds.
-    fn circuit_breaker() -> failsafe::StateMachine<ConsecutiveFailures<Constant>, ()> {
-        let backoff = backoff::constant(Duration::from_secs(60));
-        let policy = failure_policy::consecutive_failures(5, backoff);
-        // Creates a circuit breaker with given policy.
-        failsafe::Config::new().failure_policy(policy).build()
-    }
-
-    /// Create a retry strategy with exponential backoff starting at 10 milliseconds, with jitter, and a maximum of 3 retries.
-    fn retry_strategy() -> impl Iterator<Item = Duration> {
-        ExponentialBackoff::from_millis(10).map(jitter).take(3)
-    }
-
-    /// Check if the error is a HTTP 4xx client error.
-    fn is_client_error(error: &reqwest::Error) -> bool {
-        error
-            .status()
-            .map(|status_code| StatusCode::is_client_error(&status_code))
-            .unwrap_or(false)
+impl RequestError for Error {
+    fn rejected() -> Self {
+        Error::RequestRejected
     }
 
-    // Predicate function for the retry strategy to determine if an error is retryable.
-    fn is_retryable_error(error: &Error) -> bool {
-        match error {
-            Error::RequestFailed(err) => !Self::is_client_error(err), // Retry if not a client error
+    fn is_retryable(&self) -> bool {
+        match self {
+            Error::RequestFailed(err) => !resilience::is_client_error(err), // Retry if not a client error
             Error::AccessDenied => true, // Retry if token expires or is invalid
             Error::TokenExpired => true, // Retry if login expired
             _ => false,                  // Don't retry on other errors
         }
     }
 
-    /// Predicate function for the circuit breaker to record errors that are not client errors.
-    fn is_recorded_error(error: &Error) -> bool {
-        match error {
-            Error::RequestFailed(err) => !Self::is_client_error(err), // Record if not a client error
-            _ => false,                                               // Don't record other errors
+    fn is_recorded(&self) -> bool {
+        match self {
+            Error::RequestFailed(err) => !resilience::is_client_error(err), // Record if not a client error
+            _ => false, // Don't record other errors
         }
     }
 }
@@ -295,18 +265,9 @@ impl HttpClient {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::integration::resilience::create_reqwest_error_with_status;
     use reqwest::StatusCode;
 
-    fn make_reqwest_error_with_status(status: StatusCode) -> reqwest::Error {
-        let response = reqwest::Response::from(
-            http::response::Builder::new()
-                .status(status)
-                .body(Vec::new())
-                .unwrap(),
-        );
-        response.error_for_status().unwrap_err()
-    }
-
     fn create_json_serialization_error() -> Error {
         Error::ResponseJsonError(serde_json::from_str::<serde_json::Value>("not_json").unwrap_err())
     }
@@ -333,50 +294,39 @@ mod tests {
         ));
     }
 
-    #[test]
-    fn test_is_client_error() {
-        let err_400 = make_reqwest_error_with_status(StatusCode::BAD_REQUEST);
-        let err_500 = make_reqwest_error_with_status(StatusCode::INTERNAL_SERVER_ERROR);
-
-        assert!(HttpClient::is_client_error(&err_400));
-        assert!(!HttpClient::is_client_error(&err_500));
-    }
-
     #[test]
     fn test_is_retryable_error() {
-        let err_400 = Error::RequestFailed(make_reqwest_error_with_status(StatusCode::BAD_REQUEST));
-        let err_500 = Error::RequestFailed(make_reqwest_error_with_status(
+        let err_400 =
+            Error::RequestFailed(create_reqwest_error_with_status(StatusCode::BAD_REQUEST));
+        let err_500 = Error::RequestFailed(create_reqwest_error_with_status(
             StatusCode::INTERNAL_SERVER_ERROR,
         ));
 
-        assert!(!HttpClient::is_retryable_error(&err_400));
-        assert!(HttpClient::is_retryable_error(&err_500));
-        assert!(!HttpClient::is_retryable_error(&Error::WrongPassword));
-        assert!(!HttpClient::is_retryable_error(&Error::QueryImpossible));
-        assert!(HttpClient::is_retryable_error(&Error::AccessDenied));
-        assert!(!HttpClient::is_retryable_error(&Error::RequestRejected));
-        assert!(HttpClient::is_retryable_error(&Error::TokenExpired));
-        assert!(!HttpClient::is_retryable_error(
-            &create_json_serialization_error()
-        ));
+        assert!(!err_400.is_retryable());
+        assert!(err_500.is_retryable());
+        assert!(!Error::WrongPassword.is_retryable());
+        assert!(!Error::QueryImpossible.is_retryable());
+        assert!(Error::AccessDenied.is_retryable());
+        assert!(!Error::RequestRejected.is_retryable());
+        assert!(Error::TokenExpired.is_retryable());
+        assert!(!create_json_serialization_error().is_retryable());
     }
 
     #[test]
     fn test_is_recorded_error() {
-        let err_400 = Error::RequestFailed(make_reqwest_error_with_status(StatusCode::BAD_REQUEST));
-        let err_500 = Error::RequestFailed(make_reqwest_error_with_status(
+        let err_400 =
+            Error::RequestFailed(create_reqwest_error_with_status(StatusCode::BAD_REQUEST));
+        let err_500 = Error::RequestFailed(create_reqwest_error_with_status(
             StatusCode::INTERNAL_SERVER_ERROR,
         ));
 
-        assert!(!HttpClient::is_recorded_error(&err_400));
-        assert!(HttpClient::is_recorded_error(&err_500));
-        assert!(!HttpClient::is_recorded_error(&Error::WrongPassword));
-        assert!(!HttpClient::is_recorded_error(&Error::QueryImpossible));
-        assert!(!HttpClient::is_recorded_error(&Error::AccessDenied));
-        assert!(!HttpClient::is_recorded_error(&Error::RequestRejected));
-        assert!(!HttpClient::is_recorded_error(&Error::TokenExpired));
-        assert!(!HttpClient::is_recorded_error(
-            &create_json_serialization_error()
-        ));
+        assert!(!err_400.is_recorded());
+        assert!(err_500.is_recorded());
+        assert!(!Error::WrongPassword.is_recorded());
+        assert!(!Error::QueryImpossible.is_recorded());
+        assert!(!Error::AccessDenied.is_recorded());
+        assert!(!Error::RequestRejected.is_recorded());
+        assert!(!Error::TokenExpired.is_recorded());
+        assert!(!create_json_serialization_error().is_recorded());
     }
 }
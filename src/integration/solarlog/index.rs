@@ -0,0 +1,466 @@
+//! SolarLog index registry.
+//! Each data point of the `/getjp` endpoint is identified by a numeric index.
+//! The registry describes the shape and units of every known index,
+//! so all the values are read through the same extraction path.
+use super::{DcString, Error, IntradayCurve, IntradaySample, InverterStatus, LiveData, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use strum_macros::Display;
+
+/// Known SolarLog indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Index {
+    /// Current power of each inverter.
+    CurrentPower,
+    /// Energy of each day of each inverter.
+    DailyEnergy,
+    /// Energy of each month of each inverter.
+    MonthlyEnergy,
+    /// Energy of each year of each inverter.
+    YearlyEnergy,
+    /// Status of each inverter.
+    Status,
+    /// DC measurements of each string of each inverter.
+    DcStrings,
+    /// Power and energy of each inverter every 5 minutes of the current day.
+    IntradayData,
+    /// Model of each inverter slot, `Err` for an unused slot.
+    Inverters,
+    /// Open JSON block, the live data is under the sub-index 170.
+    OpenJson,
+}
+
+/// Shape of the value of an index in the `/getjp` response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum Shape {
+    /// One string per inverter, such as `{"782":{"0":"1200"}}`.
+    Scalar,
+    /// A list of dated values per inverter, such as `{"777":{"0":[["25.06.25",[510]]]}}`.
+    Dated,
+    /// A list of timestamped values per inverter, such as `{"778":{"0":[["25.06.25 10:05:00",[4120,18230]]]}}`.
+    Timed,
+    /// A list of measurements per inverter, such as `{"776":{"0":[[612.4,6.8,4165]]}}`.
+    List,
+    /// A map of values, such as `{"801":{"170":{"100":"25.06.25 14:05:00"}}}`.
+    Nested,
+}
+
+/// Unit of the value of an index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum Unit {
+    #[strum(serialize = "W")]
+    Watt,
+    #[strum(serialize = "V")]
+    Volt,
+    #[strum(serialize = "A")]
+    Ampere,
+    #[strum(serialize = "Wh")]
+    WattHour,
+}
+
+impl Index {
+    /// Returns the code, the shape and the units of the index.
+    fn definition(self) -> (&'static str, Shape, &'static [Unit]) {
+        match self {
+            Index::CurrentPower => ("782", Shape::Scalar, &[Unit::Watt]),
+            Index::DailyEnergy => ("777", Shape::Dated, &[Unit::WattHour]),
+            Index::MonthlyEnergy => ("779", Shape::Dated, &[Unit::WattHour]),
+            Index::YearlyEnergy => ("854", Shape::Dated, &[Unit::WattHour]),
+            Index::Status => ("608", Shape::Scalar, &[]),
+            Index::DcStrings => ("776", Shape::List, &[Unit::Volt, Unit::Ampere, Unit::Watt]),
+            Index::IntradayData => ("778", Shape::Timed, &[Unit::Watt, Unit::WattHour]),
+            Index::Inverters => ("740", Shape::Nested, &[]),
+            Index::OpenJson => ("801", Shape::Nested, &[]),
+        }
+    }
+
+    /// Returns the numeric code of the index, such as `782`.
+    pub fn code(self) -> &'static str {
+        self.definition().0
+    }
+
+    /// Returns the shape of the value.
+    pub fn shape(self) -> Shape {
+        self.definition().1
+    }
+
+    /// Returns the units of the measurements of each value, in order, empty if the value has no unit.
+    pub fn units(self) -> &'static [Unit] {
+        self.definition().2
+    }
+
+    /// Returns the raw value of the index in the response.
+    pub(super) fn get(self, json_value: &Value) -> Option<&Value> {
+        json_value.get(self.code())
+    }
+
+    /// Extract the value of the given inverter.
+    /// The type of the value must match the shape of the index.
+    pub(super) fn extract<T: IndexValue>(self, json_value: &Value, inverter_id: u8) -> Result<T> {
+        let value = self
+            .get(json_value)
+            .and_then(|v| v.get(inverter_id.to_string()));
+        self.parse(value, &format!(" and inverter {inverter_id}"))
+    }
+
+    /// Extract the value of the given sub-index of a nested index, such as the live data `170` of `801`.
+    pub(super) fn extract_nested<T: IndexValue>(
+        self,
+        json_value: &Value,
+        sub_index: &str,
+    ) -> Result<T> {
+        let value = self.get(json_value).and_then(|v| v.get(sub_index));
+        self.parse(value, &format!("/{sub_index}"))
+    }
+
+    /// Extract the whole value of the index, such as the model of every inverter slot.
+    pub(super) fn extract_all<T: IndexValue>(self, json_value: &Value) -> Result<T> {
+        self.parse(self.get(json_value), "")
+    }
+
+    /// Parse the value found at the `location` of the index.
+    /// Fails if the type of the value does not match the shape of the index.
+    fn parse<T: IndexValue>(self, value: Option<&Value>, location: &str) -> Result<T> {
+        if T::SHAPE != self.shape() {
+            return Err(Error::ValueParseError(format!(
+                "Index {} holds {} values, not {}",
+                self.code(),
+                self.shape(),
+                T::SHAPE
+            )));
+        }
+        let value = value.ok_or_else(|| {
+            Error::ValueParseError(format!("Missing value for index {}{location}", self.code()))
+        })?;
+        let units: Vec<_> = self.units().iter().map(Unit::to_string).collect();
+        let unit = if units.is_empty() {
+            String::new()
+        } else {
+            format!(" in {}", units.join(", "))
+        };
+        T::parse(value).ok_or_else(|| {
+            Error::ValueParseError(format!(
+                "Invalid {} value{unit} for index {}{location}: {value}",
+                T::SHAPE,
+                self.code()
+            ))
+        })
+    }
+}
+
+/// Value that can be read from an index.
+pub(super) trait IndexValue: Sized {
+    /// Shape of the indices holding this value.
+    const SHAPE: Shape;
+
+    /// Parse the raw value of one inverter, `None` if it is invalid.
+    fn parse(value: &Value) -> Option<Self>;
+}
+
+impl IndexValue for i64 {
+    const SHAPE: Shape = Shape::Scalar;
+
+    fn parse(value: &Value) -> Option<Self> {
+        value.as_str()?.parse().ok()
+    }
+}
+
+impl IndexValue for String {
+    const SHAPE: Shape = Shape::Scalar;
+
+    fn parse(value: &Value) -> Option<Self> {
+        value.as_str().map(str::to_string)
+    }
+}
+
+impl IndexValue for InverterStatus {
+    const SHAPE: Shape = Shape::Scalar;
+
+    /// Unknown status strings are parsed as `InverterStatus::Unknown`.
+    fn parse(value: &Value) -> Option<Self> {
+        let status = value.as_str()?;
+        Some(
+            InverterStatus::from_str(status)
+                .unwrap_or_else(|_| InverterStatus::Unknown(status.to_string())),
+        )
+    }
+}
+
+/// Dated values in the device order, such as the energy of each day.
+/// Invalid entries are skipped, so a single bad entry does not hide the others.
+impl IndexValue for Vec<(NaiveDate, i64)> {
+    const SHAPE: Shape = Shape::Dated;
+
+    fn parse(value: &Value) -> Option<Self> {
+        let values = value
+            .as_array()?
+            .iter()
+            .filter_map(|entry| {
+                let value = parse_dated_entry(entry);
+                if value.is_none() {
+                    log::warn!("Skipping invalid dated value: {entry}");
+                }
+                value
+            })
+            .collect();
+        Some(values)
+    }
+}
+
+/// Parse a single dated entry, such as `["25.06.25",[510]]`.
+fn parse_dated_entry(entry: &Value) -> Option<(NaiveDate, i64)> {
+    let date = entry.get(0)?.as_str()?;
+    let date = NaiveDate::parse_from_str(date, "%d.%m.%y").ok()?;
+    let value = entry.get(1)?.as_array()?.first()?.as_i64()?;
+    Some((date, value))
+}
+
+/// Intraday curve, with the power and the energy of each sample.
+impl IndexValue for IntradayCurve {
+    const SHAPE: Shape = Shape::Timed;
+
+    fn parse(value: &Value) -> Option<Self> {
+        let samples = value
+            .as_array()?
+            .iter()
+            .map(|entry| {
+                let time = entry.get(0)?.as_str()?;
+                let time = NaiveDateTime::parse_from_str(time, "%d.%m.%y %H:%M:%S").ok()?;
+                let values = entry.get(1)?.as_array()?;
+                let power = values.first()?.as_i64()?;
+                let energy = values.get(1)?.as_i64()?;
+                Some(IntradaySample {
+                    time,
+                    power,
+                    energy,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(IntradayCurve::new(samples))
+    }
+}
+
+/// DC measurements of the strings, in the device order.
+impl IndexValue for Vec<DcString> {
+    const SHAPE: Shape = Shape::List;
+
+    fn parse(value: &Value) -> Option<Self> {
+        Vec::<DcString>::deserialize(value).ok()
+    }
+}
+
+/// Model of each inverter slot, keyed by inverter ID.
+impl IndexValue for BTreeMap<u8, String> {
+    const SHAPE: Shape = Shape::Nested;
+
+    fn parse(value: &Value) -> Option<Self> {
+        value
+            .as_object()?
+            .iter()
+            .map(|(id, model)| Some((id.parse().ok()?, model.as_str()?.to_string())))
+            .collect()
+    }
+}
+
+/// Open JSON live data of the whole site.
+impl IndexValue for LiveData {
+    const SHAPE: Shape = Shape::Nested;
+
+    fn parse(value: &Value) -> Option<Self> {
+        LiveData::deserialize(value).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Index::CurrentPower, "782", Shape::Scalar, &[Unit::Watt])]
+    #[case(Index::DailyEnergy, "777", Shape::Dated, &[Unit::WattHour])]
+    #[case(Index::MonthlyEnergy, "779", Shape::Dated, &[Unit::WattHour])]
+    #[case(Index::YearlyEnergy, "854", Shape::Dated, &[Unit::WattHour])]
+    #[case(Index::Status, "608", Shape::Scalar, &[])]
+    #[case(Index::DcStrings, "776", Shape::List, &[Unit::Volt, Unit::Ampere, Unit::Watt])]
+    #[case(Index::IntradayData, "778", Shape::Timed, &[Unit::Watt, Unit::WattHour])]
+    #[case(Index::Inverters, "740", Shape::Nested, &[])]
+    #[case(Index::OpenJson, "801", Shape::Nested, &[])]
+    fn test_definition(
+        #[case] index: Index,
+        #[case] code: &str,
+        #[case] shape: Shape,
+        #[case] units: &[Unit],
+    ) {
+        assert_eq!(index.code(), code);
+        assert_eq!(index.shape(), shape);
+        assert_eq!(index.units(), units);
+    }
+
+    #[test]
+    fn test_unit_display() {
+        assert_eq!(Unit::Watt.to_string(), "W");
+        assert_eq!(Unit::WattHour.to_string(), "Wh");
+        assert_eq!(Unit::Volt.to_string(), "V");
+        assert_eq!(Unit::Ampere.to_string(), "A");
+    }
+
+    #[test]
+    fn test_extract_scalar() {
+        let json = serde_json::json!({"782": {"0": "12345"}, "608": {"0": "On-grid"}});
+        let power: i64 = Index::CurrentPower.extract(&json, 0).unwrap();
+        let status: String = Index::Status.extract(&json, 0).unwrap();
+        assert_eq!(power, 12345);
+        assert_eq!(status, "On-grid");
+    }
+
+    #[test]
+    fn test_extract_scalar_invalid() {
+        let json = serde_json::json!({"782": {"0": "notanumber"}});
+        let result = Index::CurrentPower.extract::<i64>(&json, 0);
+        assert!(matches!(
+            result,
+            Err(Error::ValueParseError(msg)) if msg.contains("Invalid scalar value in W for index 782 and inverter 0")
+        ));
+    }
+
+    #[test]
+    fn test_extract_missing() {
+        let json = serde_json::json!({"608": {"0": "On-grid"}});
+        let missing_index = Index::CurrentPower.extract::<i64>(&json, 0);
+        let missing_inverter = Index::Status.extract::<String>(&json, 1);
+        assert!(matches!(
+            missing_index,
+            Err(Error::ValueParseError(msg)) if msg.contains("Missing value for index 782 and inverter 0")
+        ));
+        assert!(matches!(
+            missing_inverter,
+            Err(Error::ValueParseError(msg)) if msg.contains("Missing value for index 608 and inverter 1")
+        ));
+    }
+
+    #[test]
+    fn test_extract_status() {
+        let json = serde_json::json!({"608": {"0": "On-grid", "1": "Standby"}});
+        let known: InverterStatus = Index::Status.extract(&json, 0).unwrap();
+        let unknown: InverterStatus = Index::Status.extract(&json, 1).unwrap();
+        assert_eq!(known, InverterStatus::OnGrid);
+        assert_eq!(unknown, InverterStatus::Unknown("Standby".to_string()));
+    }
+
+    #[test]
+    fn test_extract_dated() {
+        let json = serde_json::json!({"777": {"0": [["24.06.25", [28430]], ["25.06.25", [510]]]}});
+        let values: Vec<(NaiveDate, i64)> = Index::DailyEnergy.extract(&json, 0).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                (NaiveDate::from_ymd_opt(2025, 6, 24).unwrap(), 28430),
+                (NaiveDate::from_ymd_opt(2025, 6, 25).unwrap(), 510),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_list() {
+        let json = serde_json::json!({"776": {"0": [[612.4, 6.8, 4165], [598.0, 6.5, 3887]]}});
+        let strings: Vec<DcString> = Index::DcStrings.extract(&json, 0).unwrap();
+        assert_eq!(strings.len(), 2);
+        assert_eq!(strings[1].power, 3887);
+
+        let json = serde_json::json!({"776": {"0": [[612.4, 6.8]]}});
+        let result = Index::DcStrings.extract::<Vec<DcString>>(&json, 0);
+        assert!(matches!(
+            result,
+            Err(Error::ValueParseError(msg)) if msg.contains("Invalid list value in V, A, W for index 776 and inverter 0")
+        ));
+    }
+
+    #[test]
+    fn test_extract_timed() {
+        let json = serde_json::json!({"778": {"0": [
+            ["25.06.25 10:05:00", [4120, 18230]],
+            ["25.06.25 10:00:00", [4000, 17890]]
+        ]}});
+        let curve: IntradayCurve = Index::IntradayData.extract(&json, 0).unwrap();
+        let time = NaiveDate::from_ymd_opt(2025, 6, 25)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        assert_eq!(
+            curve.samples[0],
+            IntradaySample {
+                time,
+                power: 4000,
+                energy: 17890
+            }
+        );
+        assert_eq!(curve.samples.len(), 2);
+
+        let json = serde_json::json!({"778": {"0": [["25.06.25", [4120, 18230]]]}});
+        let result = Index::IntradayData.extract::<IntradayCurve>(&json, 0);
+        assert!(matches!(
+            result,
+            Err(Error::ValueParseError(msg)) if msg.contains("Invalid timed value in W, Wh for index 778 and inverter 0")
+        ));
+    }
+
+    #[test]
+    fn test_extract_dated_invalid_entry() {
+        let json =
+            serde_json::json!({"854": {"0": [["01.01.24", [10125400]], ["not a date", [1]]]}});
+        let values: Vec<(NaiveDate, i64)> = Index::YearlyEnergy.extract(&json, 0).unwrap();
+        assert_eq!(
+            values,
+            vec![(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), 10125400)]
+        );
+
+        let json = serde_json::json!({"854": {"0": "not a list"}});
+        let result = Index::YearlyEnergy.extract::<Vec<(NaiveDate, i64)>>(&json, 0);
+        assert!(matches!(
+            result,
+            Err(Error::ValueParseError(msg)) if msg.contains("Invalid dated value in Wh for index 854 and inverter 0")
+        ));
+    }
+
+    #[test]
+    fn test_extract_shape_mismatch() {
+        let json = serde_json::json!({"782": {"0": "12345"}});
+        let result = Index::CurrentPower.extract::<Vec<(NaiveDate, i64)>>(&json, 0);
+        assert!(matches!(
+            result,
+            Err(Error::ValueParseError(msg)) if msg == "Index 782 holds scalar values, not dated"
+        ));
+    }
+
+    #[test]
+    fn test_extract_nested() {
+        let json = serde_json::json!({"801": {"171": {}}});
+        let result = Index::OpenJson.extract_nested::<LiveData>(&json, "170");
+        assert!(matches!(
+            result,
+            Err(Error::ValueParseError(msg)) if msg.contains("Missing value for index 801/170")
+        ));
+    }
+
+    #[test]
+    fn test_extract_all() {
+        let json = serde_json::json!({"740": {"1": "Fronius", "0": "SMA"}});
+        let slots: BTreeMap<u8, String> = Index::Inverters.extract_all(&json).unwrap();
+        assert_eq!(
+            slots,
+            BTreeMap::from([(0, "SMA".to_string()), (1, "Fronius".to_string())])
+        );
+
+        let json = serde_json::json!({"740": {"x": "SMA"}});
+        let result = Index::Inverters.extract_all::<BTreeMap<u8, String>>(&json);
+        assert!(matches!(
+            result,
+            Err(Error::ValueParseError(msg)) if msg.contains("Invalid nested value for index 740")
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+//! SolarLog intraday data.
+//! The device keeps a sample every 5 minutes for the current day.
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
+use std::collections::BTreeMap;
+
+/// Sample of the intraday curve.
+/// The power is in Watt (W), the energy is produced since midnight in watt-hours (Wh).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntradaySample {
+    /// Time of the sample (local time).
+    pub time: NaiveDateTime,
+    /// Average power during the interval.
+    pub power: i64,
+    /// Energy produced since midnight.
+    pub energy: i64,
+}
+
+/// Power of one hour, aggregated from the intraday samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HourlyPower {
+    /// Start of the hour (local time).
+    pub start: NaiveDateTime,
+    /// Mean power during the hour in Watt (W).
+    pub mean: f64,
+    /// Minimum power during the hour in Watt (W).
+    pub min: i64,
+    /// Maximum power during the hour in Watt (W).
+    pub max: i64,
+}
+
+/// Intraday curve, sorted by time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IntradayCurve {
+    pub samples: Vec<IntradaySample>,
+}
+
+impl IntradayCurve {
+    /// Creates the curve from the samples in any order.
+    pub fn new(mut samples: Vec<IntradaySample>) -> Self {
+        samples.sort_by_key(|sample| sample.time);
+        IntradayCurve { samples }
+    }
+
+    /// Sums the curves of several inverters into a site curve.
+    /// Samples are matched by time.
+    pub fn sum<'a>(curves: impl IntoIterator<Item = &'a IntradayCurve>) -> Self {
+        let mut samples: BTreeMap<NaiveDateTime, IntradaySample> = BTreeMap::new();
+        for sample in curves.into_iter().flat_map(|curve| curve.samples.iter()) {
+            samples
+                .entry(sample.time)
+                .and_modify(|total| {
+                    total.power += sample.power;
+                    total.energy += sample.energy;
+                })
+                .or_insert(*sample);
+        }
+        IntradayCurve {
+            samples: samples.into_values().collect(),
+        }
+    }
+
+    /// Returns the curve of the given day only.
+    pub fn of_day(&self, day: NaiveDate) -> Self {
+        let samples = self
+            .samples
+            .iter()
+            .filter(|sample| sample.time.date() == day)
+            .copied()
+            .collect();
+        IntradayCurve { samples }
+    }
+
+    /// Aggregates the power of each complete hour.
+    /// An hour is complete once a sample at or after its end is available, the current hour is left out.
+    pub fn hourly_power(&self) -> Vec<HourlyPower> {
+        let Some(last) = self.samples.last() else {
+            return Vec::new();
+        };
+        let mut hours: BTreeMap<NaiveDateTime, Vec<i64>> = BTreeMap::new();
+        for sample in self.samples.iter() {
+            let start = Self::hour_start(sample.time);
+            if start < Self::hour_start(last.time) {
+                hours.entry(start).or_default().push(sample.power);
+            }
+        }
+        hours
+            .into_iter()
+            .map(|(start, powers)| HourlyPower {
+                start,
+                mean: powers.iter().sum::<i64>() as f64 / powers.len() as f64,
+                min: powers.iter().copied().min().unwrap_or_default(),
+                max: powers.iter().copied().max().unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// Returns the start of the hour of the given time.
+    fn hour_start(time: NaiveDateTime) -> NaiveDateTime {
+        time.with_minute(0)
+            .and_then(|time| time.with_second(0))
+            .and_then(|time| time.with_nanosecond(0))
+            .expect("invalid hour")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn sample(hour: u32, minute: u32, power: i64, energy: i64) -> IntradaySample {
+        IntradaySample {
+            time: time(25, hour, minute),
+            power,
+            energy,
+        }
+    }
+
+    #[test]
+    fn test_new_sorts_samples() {
+        let curve = IntradayCurve::new(vec![sample(10, 5, 1200, 100), sample(10, 0, 1000, 0)]);
+        assert_eq!(curve.samples[0].time, time(25, 10, 0));
+    }
+
+    #[test]
+    fn test_sum() {
+        let first = IntradayCurve::new(vec![sample(10, 0, 1000, 0), sample(10, 5, 1200, 100)]);
+        let second = IntradayCurve::new(vec![sample(10, 5, 600, 50), sample(10, 10, 700, 110)]);
+
+        let total = IntradayCurve::sum([&first, &second]);
+
+        assert_eq!(
+            total.samples,
+            vec![
+                sample(10, 0, 1000, 0),
+                sample(10, 5, 1800, 150),
+                sample(10, 10, 700, 110),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_of_day() {
+        let yesterday = IntradaySample {
+            time: time(24, 23, 55),
+            power: 0,
+            energy: 28430,
+        };
+        let curve = IntradayCurve::new(vec![yesterday, sample(10, 0, 1000, 0)]);
+
+        let today = curve.of_day(NaiveDate::from_ymd_opt(2025, 6, 25).unwrap());
+
+        assert_eq!(today.samples, vec![sample(10, 0, 1000, 0)]);
+    }
+
+    #[test]
+    fn test_hourly_power() {
+        let curve = IntradayCurve::new(vec![
+            sample(10, 0, 1000, 0),
+            sample(10, 30, 2000, 750),
+            sample(10, 55, 3000, 1500),
+            sample(11, 0, 2400, 1700),
+            sample(11, 5, 2600, 1900),
+        ]);
+
+        let hours = curve.hourly_power();
+
+        assert_eq!(
+            hours,
+            vec![HourlyPower {
+                start: time(25, 10, 0),
+                mean: 2000.0,
+                min: 1000,
+                max: 3000,
+            }]
+        );
+        assert!(IntradayCurve::default().hourly_power().is_empty());
+    }
+}
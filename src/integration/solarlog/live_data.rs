@@ -0,0 +1,134 @@
+//! SolarLog Open JSON live data.
+//! Most firmwares expose a block of site-wide live values under index 801/170.
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Deserializer};
+
+/// Site-wide live data read from the Open JSON interface.
+/// Power values are in Watt (W), energy values in watt-hours (Wh).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LiveData {
+    /// Time of the last update on the device (local time).
+    #[serde(rename = "100", deserialize_with = "deserialize_last_update")]
+    pub last_update: NaiveDateTime,
+    /// Current AC power.
+    #[serde(rename = "101")]
+    pub power_ac: i64,
+    /// Current DC power.
+    #[serde(rename = "102")]
+    pub power_dc: i64,
+    /// Current AC voltage in Volt (V).
+    #[serde(rename = "103")]
+    pub voltage_ac: i64,
+    /// Current DC voltage in Volt (V).
+    #[serde(rename = "104")]
+    pub voltage_dc: i64,
+    /// Energy produced today.
+    #[serde(rename = "105")]
+    pub yield_day: i64,
+    /// Energy produced yesterday.
+    #[serde(rename = "106")]
+    pub yield_yesterday: i64,
+    /// Energy produced this month.
+    #[serde(rename = "107")]
+    pub yield_month: i64,
+    /// Energy produced this year.
+    #[serde(rename = "108")]
+    pub yield_year: i64,
+    /// Energy produced since installation.
+    #[serde(rename = "109")]
+    pub yield_total: i64,
+    /// Current consumption power.
+    #[serde(rename = "110")]
+    pub consumption_power: i64,
+    /// Energy consumed today.
+    #[serde(rename = "111")]
+    pub consumption_day: i64,
+    /// Energy consumed yesterday.
+    #[serde(rename = "112")]
+    pub consumption_yesterday: i64,
+    /// Energy consumed this month.
+    #[serde(rename = "113")]
+    pub consumption_month: i64,
+    /// Energy consumed this year.
+    #[serde(rename = "114")]
+    pub consumption_year: i64,
+    /// Energy consumed since installation.
+    #[serde(rename = "115")]
+    pub consumption_total: i64,
+    /// Installed generator power in Watt-peak (Wp).
+    #[serde(rename = "116")]
+    pub installed_power: i64,
+}
+
+/// Deserialize the last update time, formatted like `25.06.25 14:05:00`.
+fn deserialize_last_update<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    NaiveDateTime::parse_from_str(&value, "%d.%m.%y %H:%M:%S").map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_deserialize_live_data() {
+        let json = serde_json::json!({
+            "100": "25.06.25 14:05:00",
+            "101": 4120,
+            "102": 4300,
+            "103": 231,
+            "104": 612,
+            "105": 18230,
+            "106": 28430,
+            "107": 550370,
+            "108": 3125400,
+            "109": 48211000,
+            "110": 640,
+            "111": 5210,
+            "112": 9870,
+            "113": 190200,
+            "114": 1320500,
+            "115": 20450000,
+            "116": 9900
+        });
+
+        let live_data: LiveData = serde_json::from_value(json).unwrap();
+
+        assert_eq!(
+            live_data,
+            LiveData {
+                last_update: NaiveDate::from_ymd_opt(2025, 6, 25)
+                    .unwrap()
+                    .and_hms_opt(14, 5, 0)
+                    .unwrap(),
+                power_ac: 4120,
+                power_dc: 4300,
+                voltage_ac: 231,
+                voltage_dc: 612,
+                yield_day: 18230,
+                yield_yesterday: 28430,
+                yield_month: 550370,
+                yield_year: 3125400,
+                yield_total: 48211000,
+                consumption_power: 640,
+                consumption_day: 5210,
+                consumption_yesterday: 9870,
+                consumption_month: 190200,
+                consumption_year: 1320500,
+                consumption_total: 20450000,
+                installed_power: 9900,
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_live_data_invalid_last_update() {
+        let json = serde_json::json!({"100": "not a date", "101": 4120});
+        let result = serde_json::from_value::<LiveData>(json);
+        assert!(result.is_err());
+    }
+}
@@ -1,8 +1,22 @@
 //! Solar-Log Integration Module
 //! The integration is done via HTTP JSON API.
 mod client;
+mod consumption;
+mod dc_string;
 mod error;
+mod history;
 mod http_client;
+mod index;
+mod intraday;
+mod live_data;
+mod query;
 
-pub use client::{Client, InverterStatus};
+pub use client::{Client, DataSource, InverterStatus};
+pub use consumption::{Consumption, GridEnergy};
+pub use dc_string::DcString;
 pub use error::{Error, Result};
+pub use history::EnergyHistory;
+pub use index::{Index, Shape, Unit};
+pub use intraday::{HourlyPower, IntradayCurve, IntradaySample};
+pub use live_data::LiveData;
+pub use query::{QueryBuilder, Snapshot};
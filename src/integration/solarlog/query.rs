@@ -0,0 +1,354 @@
+//! SolarLog batched queries.
+//! The `/getjp` endpoint accepts several indices and inverters in a single JSON body,
+//! so the values of one snapshot are read at the same time and are consistent with each other.
+use super::client::Client;
+use super::index::Index;
+use super::{DcString, EnergyHistory, Error, IntradayCurve, InverterStatus, Result};
+use chrono::NaiveDate;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Values read by a batched query, keyed by inverter ID.
+/// Only the requested values are filled in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    /// Current power in Watt (W).
+    pub current_power: BTreeMap<u8, i64>,
+    /// Inverter status.
+    pub status: BTreeMap<u8, InverterStatus>,
+    /// Energy of the last day (today) in watt-hours (Wh).
+    pub energy_of_last_day: BTreeMap<u8, (NaiveDate, i64)>,
+    /// Energy of the last month (current month) in watt-hours (Wh).
+    pub energy_of_last_month: BTreeMap<u8, (NaiveDate, i64)>,
+    /// Energy since installation in watt-hours (Wh).
+    pub energy_total: BTreeMap<u8, i64>,
+    /// Daily and monthly energy history.
+    pub energy_history: BTreeMap<u8, EnergyHistory>,
+    /// DC measurements of each string.
+    pub dc_strings: BTreeMap<u8, Vec<DcString>>,
+    /// Intraday curve of the current day.
+    pub intraday_curve: BTreeMap<u8, IntradayCurve>,
+}
+
+/// Builder for a batched query.
+/// Created with `Client::query`, the first inverter is queried unless `inverters` is called.
+pub struct QueryBuilder<'a> {
+    client: &'a Client,
+    inverters: Vec<u8>,
+    current_power: bool,
+    status: bool,
+    energy_of_last_day: bool,
+    energy_of_last_month: bool,
+    energy_total: bool,
+    energy_history: bool,
+    dc_strings: bool,
+    intraday_curve: bool,
+}
+
+impl<'a> QueryBuilder<'a> {
+    /// Creates a new empty query for the first inverter.
+    pub(super) fn new(client: &'a Client) -> Self {
+        QueryBuilder {
+            client,
+            inverters: vec![0],
+            current_power: false,
+            status: false,
+            energy_of_last_day: false,
+            energy_of_last_month: false,
+            energy_total: false,
+            energy_history: false,
+            dc_strings: false,
+            intraday_curve: false,
+        }
+    }
+
+    /// Query the given inverters instead of the first one.
+    pub fn inverters(mut self, inverters: &[u8]) -> Self {
+        self.inverters = inverters.to_vec();
+        self
+    }
+
+    /// Include the current power.
+    pub fn current_power(mut self) -> Self {
+        self.current_power = true;
+        self
+    }
+
+    /// Include the inverter status.
+    pub fn status(mut self) -> Self {
+        self.status = true;
+        self
+    }
+
+    /// Include the energy of the last day.
+    pub fn energy_of_last_day(mut self) -> Self {
+        self.energy_of_last_day = true;
+        self
+    }
+
+    /// Include the energy of the last month.
+    pub fn energy_of_last_month(mut self) -> Self {
+        self.energy_of_last_month = true;
+        self
+    }
+
+    /// Include the energy since installation.
+    pub fn energy_total(mut self) -> Self {
+        self.energy_total = true;
+        self
+    }
+
+    /// Include the daily and monthly energy history.
+    pub fn energy_history(mut self) -> Self {
+        self.energy_history = true;
+        self
+    }
+
+    /// Include the DC measurements of each string.
+    pub fn dc_strings(mut self) -> Self {
+        self.dc_strings = true;
+        self
+    }
+
+    /// Include the intraday curve.
+    pub fn intraday_curve(mut self) -> Self {
+        self.intraday_curve = true;
+        self
+    }
+
+    /// Send the query to the SolarLog device in a single request.
+    pub async fn send(self) -> Result<Snapshot> {
+        let query = self.build()?;
+        let json_value = self.client.send_query(&query).await?;
+        self.extract_snapshot(&json_value)
+    }
+
+    /// Returns the requested indices.
+    fn indices(&self) -> Vec<Index> {
+        [
+            (self.current_power, Index::CurrentPower),
+            (self.status, Index::Status),
+            (self.energy_of_last_day, Index::DailyEnergy),
+            (self.energy_of_last_month, Index::MonthlyEnergy),
+            (self.energy_total, Index::YearlyEnergy),
+            (self.energy_history, Index::DailyEnergy),
+            (self.energy_history, Index::MonthlyEnergy),
+            (self.dc_strings, Index::DcStrings),
+            (self.intraday_curve, Index::IntradayData),
+        ]
+        .into_iter()
+        .filter_map(|(requested, index)| requested.then_some(index))
+        .collect()
+    }
+
+    /// Build the JSON query, such as `{"608":{"0":null},"782":{"0":null}}`.
+    fn build(&self) -> Result<String> {
+        let indices = self.indices();
+        if indices.is_empty() || self.inverters.is_empty() {
+            return Err(Error::QueryEmpty);
+        }
+        let inverters: Map<String, Value> = self
+            .inverters
+            .iter()
+            .map(|inverter_id| (inverter_id.to_string(), Value::Null))
+            .collect();
+        let query: Map<String, Value> = indices
+            .into_iter()
+            .map(|index| (index.code().to_string(), Value::Object(inverters.clone())))
+            .collect();
+        Ok(Value::Object(query).to_string())
+    }
+
+    /// Extract the requested values of every inverter.
+    fn extract_snapshot(&self, json_value: &Value) -> Result<Snapshot> {
+        let mut snapshot = Snapshot::default();
+        for inverter_id in self.inverters.iter().copied() {
+            if self.current_power {
+                let value = Index::CurrentPower.extract(json_value, inverter_id)?;
+                snapshot.current_power.insert(inverter_id, value);
+            }
+            if self.status {
+                let value = self
+                    .client
+                    .extract_inverter_status(json_value, inverter_id)?;
+                snapshot.status.insert(inverter_id, value);
+            }
+            if self.energy_of_last_day {
+                let value = Client::extract_energy_of_last_day(json_value, inverter_id)?;
+                snapshot.energy_of_last_day.insert(inverter_id, value);
+            }
+            if self.energy_of_last_month {
+                let value = Client::extract_energy_of_last_month(json_value, inverter_id)?;
+                snapshot.energy_of_last_month.insert(inverter_id, value);
+            }
+            if self.energy_total {
+                let value = Client::extract_energy_total(json_value, inverter_id)?;
+                snapshot.energy_total.insert(inverter_id, value);
+            }
+            if self.energy_history {
+                let daily = Index::DailyEnergy.extract(json_value, inverter_id)?;
+                let monthly = Index::MonthlyEnergy.extract(json_value, inverter_id)?;
+                let value = EnergyHistory::new(daily, monthly);
+                snapshot.energy_history.insert(inverter_id, value);
+            }
+            if self.dc_strings {
+                let value = Index::DcStrings.extract(json_value, inverter_id)?;
+                snapshot.dc_strings.insert(inverter_id, value);
+            }
+            if self.intraday_curve {
+                let value = Index::IntradayData.extract(json_value, inverter_id)?;
+                snapshot.intraday_curve.insert(inverter_id, value);
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Url;
+
+    fn client() -> Client {
+        Client::new(
+            Url::parse("http://localhost:8080").unwrap(),
+            String::from("test_password"),
+        )
+    }
+
+    #[test]
+    fn test_build_single_index() {
+        let client = client();
+        let query = client.query().current_power().build().unwrap();
+        assert_eq!(query, r#"{"782":{"0":null}}"#);
+    }
+
+    #[test]
+    fn test_build_multiple_indices_and_inverters() {
+        let client = client();
+        let query = client
+            .query()
+            .inverters(&[0, 1])
+            .current_power()
+            .status()
+            .energy_of_last_day()
+            .energy_of_last_month()
+            .energy_total()
+            .build()
+            .unwrap();
+        assert_eq!(
+            query,
+            r#"{"608":{"0":null,"1":null},"777":{"0":null,"1":null},"779":{"0":null,"1":null},"782":{"0":null,"1":null},"854":{"0":null,"1":null}}"#
+        );
+    }
+
+    #[test]
+    fn test_build_empty() {
+        let client = client();
+        assert!(matches!(client.query().build(), Err(Error::QueryEmpty)));
+        assert!(matches!(
+            client.query().inverters(&[]).status().build(),
+            Err(Error::QueryEmpty)
+        ));
+    }
+
+    #[test]
+    fn test_extract_snapshot() {
+        let client = client();
+        let query = client
+            .query()
+            .inverters(&[0, 1])
+            .current_power()
+            .status()
+            .energy_of_last_day()
+            .energy_of_last_month()
+            .energy_total();
+        let json = serde_json::json!({
+            "782": {"0": "1200", "1": "800"},
+            "608": {"0": "On-grid", "1": "Idle No irradiation"},
+            "777": {
+                "0": [["24.06.25", [28430]], ["25.06.25", [510]]],
+                "1": [["24.06.25", [14200]], ["25.06.25", [260]]]
+            },
+            "779": {
+                "0": [["01.06.25", [550370]]],
+                "1": [["01.06.25", [275100]]]
+            },
+            "854": {
+                "0": [["01.01.24", [10125400]], ["01.01.25", [5203000]]],
+                "1": [["01.01.25", [2601500]]]
+            }
+        });
+        let day = NaiveDate::from_ymd_opt(2025, 6, 25).unwrap();
+        let month = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+
+        let snapshot = query.extract_snapshot(&json).unwrap();
+
+        assert_eq!(
+            snapshot,
+            Snapshot {
+                current_power: BTreeMap::from([(0, 1200), (1, 800)]),
+                status: BTreeMap::from([
+                    (0, InverterStatus::OnGrid),
+                    (1, InverterStatus::IdleNoIrradiation)
+                ]),
+                energy_of_last_day: BTreeMap::from([(0, (day, 510)), (1, (day, 260))]),
+                energy_of_last_month: BTreeMap::from([(0, (month, 550370)), (1, (month, 275100))]),
+                energy_total: BTreeMap::from([(0, 15328400), (1, 2601500)]),
+                energy_history: BTreeMap::new(),
+                dc_strings: BTreeMap::new(),
+                intraday_curve: BTreeMap::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_build_energy_history() {
+        let client = client();
+        let query = client
+            .query()
+            .energy_of_last_day()
+            .energy_history()
+            .build()
+            .unwrap();
+        assert_eq!(query, r#"{"777":{"0":null},"779":{"0":null}}"#);
+    }
+
+    #[test]
+    fn test_extract_energy_history() {
+        let client = client();
+        let query = client.query().energy_history();
+        let json = serde_json::json!({
+            "777": {"0": [["24.06.25", [28430]], ["25.06.25", [510]]]},
+            "779": {"0": [["01.05.25", [610000]], ["01.06.25", [550370]]]}
+        });
+        let date = |day, month| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+
+        let snapshot = query.extract_snapshot(&json).unwrap();
+
+        assert_eq!(
+            snapshot.energy_history,
+            BTreeMap::from([(
+                0,
+                EnergyHistory::new(
+                    vec![(date(24, 6), 28430), (date(25, 6), 510)],
+                    vec![(date(1, 5), 610000), (date(1, 6), 550370)],
+                )
+            )])
+        );
+    }
+
+    #[test]
+    fn test_extract_snapshot_missing_value() {
+        let client = client();
+        let query = client.query().inverters(&[0, 1]).current_power();
+        let json = serde_json::json!({"782": {"0": "1200"}});
+
+        let result = query.extract_snapshot(&json);
+
+        assert!(matches!(
+            result,
+            Err(Error::ValueParseError(msg)) if msg.contains("index 782 and inverter 1")
+        ));
+    }
+}
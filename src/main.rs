@@ -1,10 +1,10 @@
 //! grelsolar - A Rust application for solar energy management
 //! The application is small enough to run on a single worker thread,
 //! making it suitable for low-resource environments.
-use envconfig::Envconfig;
 use grelsolar::core::config::{Config, configure_logger};
-use grelsolar::server::server;
+use grelsolar::server::{ServerError, server};
 use tokio::signal;
+use tokio::task::JoinError;
 use tokio::time::{Duration, timeout};
 use tokio_util::sync::CancellationToken;
 
@@ -13,6 +13,7 @@ enum ExitCode {
     RuntimeError = 1,
     ConfigError = 2,
     ShutdownError = 3,
+    StartupCheckError = 4,
 }
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 1)]
@@ -20,7 +21,7 @@ async fn main() {
     dotenvy::dotenv().ok();
     configure_logger();
 
-    let config = match Config::init_from_env() {
+    let config = match Config::load() {
         Ok(cfg) => cfg,
         Err(e) => {
             log::error!("Failed to load configuration: {e}");
@@ -31,10 +32,14 @@ async fn main() {
     let shutdown_token = CancellationToken::new();
     let server_shutdown_token = shutdown_token.clone();
 
-    let app = tokio::spawn(async move { server(config, server_shutdown_token).await });
+    let mut app = tokio::spawn(async move { server(config, server_shutdown_token).await });
 
     // Wait for either Ctrl+C or SIGTERM, then trigger shutdown
+    // The server only stops by itself when the startup check fails
     tokio::select! {
+        join_result = &mut app => {
+            exit(join_result);
+        }
         _ = signal::ctrl_c() => {
             log::info!("Received Ctrl+C, initiating graceful shutdown...");
         }
@@ -56,19 +61,28 @@ async fn main() {
     shutdown_token.cancel();
 
     match timeout(Duration::from_secs(30), app).await {
-        Ok(join_result) => match join_result {
-            Ok(()) => {
-                log::info!("Graceful shutdown completed");
-                std::process::exit(ExitCode::Success as i32);
-            }
-            Err(e) => {
-                log::error!("Application crashed: {e}");
-                std::process::exit(ExitCode::RuntimeError as i32);
-            }
-        },
+        Ok(join_result) => exit(join_result),
         Err(_) => {
             log::error!("Shutdown timed out after 30 seconds");
             std::process::exit(ExitCode::ShutdownError as i32);
         }
     }
 }
+
+/// Exits with the exit code matching the result of the server task.
+fn exit(join_result: Result<Result<(), ServerError>, JoinError>) -> ! {
+    match join_result {
+        Ok(Ok(())) => {
+            log::info!("Graceful shutdown completed");
+            std::process::exit(ExitCode::Success as i32);
+        }
+        Ok(Err(e @ ServerError::StartupCheckFailed(_))) => {
+            log::error!("{e}");
+            std::process::exit(ExitCode::StartupCheckError as i32);
+        }
+        Err(e) => {
+            log::error!("Application crashed: {e}");
+            std::process::exit(ExitCode::RuntimeError as i32);
+        }
+    }
+}
@@ -1,12 +1,60 @@
 //! Server
 use crate::core::config::{APP_NAME, APP_VERSION, Config};
 use crate::core::container::Container;
+use crate::services::{PreflightReport, StartupCheck};
+use thiserror::Error;
 use tokio_util::sync::CancellationToken;
 
+/// Errors stopping the server before it runs.
+#[derive(Debug, Error)]
+pub enum ServerError {
+    #[error("{0}")]
+    StartupCheckFailed(PreflightReport),
+}
+
 /// Run the server with the given configuration and shutdown token
-pub async fn server(config: Config, shutdown_token: CancellationToken) {
+pub async fn server(config: Config, shutdown_token: CancellationToken) -> Result<(), ServerError> {
     let container = Container::new(config);
     log::info!("{APP_NAME} (v{APP_VERSION}) started");
-    container.solar_service().run(shutdown_token).await;
+    let solar_service = container.solar_service();
+    if container.config().startup_check != StartupCheck::Off {
+        let report = tokio::select! {
+            report = solar_service.preflight() => report,
+            _ = shutdown_token.cancelled() => {
+                container.shutdown().await;
+                return Ok(());
+            }
+        };
+        if report.is_ok() {
+            log::info!("{report}");
+        } else if container.config().startup_check == StartupCheck::Abort {
+            container.shutdown().await;
+            return Err(ServerError::StartupCheckFailed(report));
+        } else {
+            log::warn!("{report}");
+        }
+    }
+    if container.config().homeassistant_backfill {
+        match solar_service.backfill_energy_history().await {
+            Ok(count) => log::info!("Energy history backfilled: {count} periods imported"),
+            Err(e) => log::error!("Error backfilling energy history: {e}"),
+        }
+    }
+    let metrics_exporter = container.metrics_exporter();
+    let influxdb = container.influxdb_client();
+    tokio::join!(
+        solar_service.run(shutdown_token.clone()),
+        async {
+            if let Some(exporter) = metrics_exporter {
+                exporter.run(shutdown_token.clone()).await;
+            }
+        },
+        async {
+            if let Some(influxdb) = influxdb {
+                influxdb.run(shutdown_token.clone()).await;
+            }
+        }
+    );
     container.shutdown().await;
+    Ok(())
 }
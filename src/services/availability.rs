@@ -0,0 +1,115 @@
+//! Availability of the synchronized sensors.
+//! The sensors of a sync task become unavailable after several failed polls in a row,
+//! or when the last successful poll is older than the staleness timeout.
+use tokio::time::{Duration, Instant};
+
+/// Health of the polls of a sync task.
+#[derive(Debug)]
+pub struct PollHealth {
+    max_failures: Option<u32>,
+    max_age: Option<Duration>,
+    failures: u32,
+    last_success: Instant,
+    unavailable: bool,
+}
+
+impl PollHealth {
+    /// Creates the health of a sync task, `None` disables the corresponding threshold.
+    pub fn new(max_failures: Option<u32>, max_age: Option<Duration>) -> Self {
+        PollHealth {
+            max_failures,
+            max_age,
+            failures: 0,
+            last_success: Instant::now(),
+            unavailable: false,
+        }
+    }
+
+    /// Returns whether the sensors are marked unavailable.
+    pub fn is_unavailable(&self) -> bool {
+        self.unavailable
+    }
+
+    /// Records a successful poll.
+    /// Returns `true` if the sensors were unavailable and are restored.
+    pub fn success(&mut self) -> bool {
+        self.failures = 0;
+        self.last_success = Instant::now();
+        std::mem::take(&mut self.unavailable)
+    }
+
+    /// Records a failed poll.
+    /// Returns `true` if the sensors should be marked unavailable, see `mark_unavailable`.
+    pub fn failure(&mut self) -> bool {
+        self.failures += 1;
+        if self.unavailable {
+            return false;
+        }
+        let too_many_failures = self.max_failures.is_some_and(|max| self.failures >= max);
+        let too_old = self
+            .max_age
+            .is_some_and(|max| self.last_success.elapsed() >= max);
+        too_many_failures || too_old
+    }
+
+    /// Records that the sensors have been marked unavailable.
+    pub fn mark_unavailable(&mut self) {
+        self.unavailable = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failures() {
+        let mut health = PollHealth::new(Some(3), None);
+
+        assert!(!health.failure());
+        assert!(!health.failure());
+        assert!(health.failure());
+        health.mark_unavailable();
+        assert!(!health.failure());
+        assert!(health.is_unavailable());
+        assert!(health.success());
+        assert!(!health.is_unavailable());
+        assert!(!health.success());
+    }
+
+    #[test]
+    fn test_failures_reset_on_success() {
+        let mut health = PollHealth::new(Some(2), None);
+
+        assert!(!health.failure());
+        assert!(!health.success());
+        assert!(!health.failure());
+        assert!(health.failure());
+    }
+
+    #[test]
+    fn test_failure_not_marked() {
+        let mut health = PollHealth::new(Some(1), None);
+
+        assert!(health.failure());
+        assert!(health.failure());
+    }
+
+    #[test]
+    fn test_max_age() {
+        let mut fresh = PollHealth::new(None, Some(Duration::from_secs(3600)));
+        let mut stale = PollHealth::new(None, Some(Duration::ZERO));
+
+        assert!(!fresh.failure());
+        assert!(stale.failure());
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut health = PollHealth::new(None, None);
+
+        for _ in 0..100 {
+            assert!(!health.failure());
+        }
+    }
+}
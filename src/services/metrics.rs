@@ -0,0 +1,507 @@
+//! Prometheus metrics.
+//! The latest power, energy and status are received as a sink, along with the outcome of each sync task,
+//! and rendered in the Prometheus text exposition format.
+use async_trait::async_trait;
+use chrono::{NaiveDate, Utc};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use strum::IntoEnumIterator;
+use tokio::time::Duration;
+
+use super::sink::Sink;
+use super::source::{Status, StatusCategory};
+use crate::integration::homeassistant::SensorGroup;
+use crate::integration::{LATENCY_BUCKETS, Latencies};
+
+/// Upper bounds of the sync duration histogram buckets in seconds.
+const SYNC_DURATION_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Label value of the values of the whole site.
+static SITE: &str = "site";
+
+/// Returns whether the circuit breaker of a client is open.
+type CircuitBreakerProbe = Box<dyn Fn() -> bool + Send + Sync>;
+
+/// Returns the latencies of the requests sent by a client.
+type LatencyProbe = Box<dyn Fn() -> Latencies + Send + Sync>;
+
+/// Latest values published, keyed by inverter label.
+#[derive(Default)]
+struct Values {
+    power: BTreeMap<String, i64>,
+    energy: BTreeMap<String, i64>,
+    energy_total: Option<i64>,
+    status: BTreeMap<String, Status>,
+}
+
+/// Outcome of the polls of a sync task.
+#[derive(Default)]
+struct SyncStats {
+    successes: u64,
+    failures: u64,
+    /// Number of polls per bucket of `SYNC_DURATION_BUCKETS`, not cumulative.
+    buckets: [u64; SYNC_DURATION_BUCKETS.len()],
+    duration_sum: f64,
+    last_success: Option<i64>,
+}
+
+/// Metrics exported on `/metrics`.
+#[derive(Default)]
+pub struct Metrics {
+    values: Mutex<Values>,
+    syncs: Mutex<BTreeMap<String, SyncStats>>,
+    circuit_breakers: Mutex<Vec<(String, CircuitBreakerProbe)>>,
+    request_latencies: Mutex<Vec<(String, LatencyProbe)>>,
+}
+
+impl Metrics {
+    /// Exports the state of the circuit breaker of the given client, read on each scrape.
+    pub fn register_circuit_breaker<F>(&self, client: &str, is_open: F)
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.circuit_breakers
+            .lock()
+            .expect("poisoned circuit breakers lock")
+            .push((client.to_string(), Box::new(is_open)));
+    }
+
+    /// Exports the latencies of the requests sent by the given client, read on each scrape.
+    pub fn register_request_latencies<F>(&self, client: &str, latencies: F)
+    where
+        F: Fn() -> Latencies + Send + Sync + 'static,
+    {
+        self.request_latencies
+            .lock()
+            .expect("poisoned request latencies lock")
+            .push((client.to_string(), Box::new(latencies)));
+    }
+
+    /// Records the outcome and the duration of a poll of the given sync task.
+    pub fn record_sync(&self, task: &str, success: bool, duration: Duration) {
+        let mut syncs = self.syncs.lock().expect("poisoned syncs lock");
+        let stats = syncs.entry(task.to_string()).or_default();
+        if success {
+            stats.successes += 1;
+            stats.last_success = Some(Utc::now().timestamp());
+        } else {
+            stats.failures += 1;
+        }
+        let seconds = duration.as_secs_f64();
+        if let Some(bucket) = SYNC_DURATION_BUCKETS.iter().position(|le| seconds <= *le) {
+            stats.buckets[bucket] += 1;
+        }
+        stats.duration_sum += seconds;
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.render_values(&mut out);
+        self.render_syncs(&mut out);
+        self.render_circuit_breakers(&mut out);
+        self.render_request_latencies(&mut out);
+        out
+    }
+
+    fn render_values(&self, out: &mut String) {
+        let values = self.values.lock().expect("poisoned values lock");
+        Self::header(out, "grelsolar_power_watts", "gauge", "Current power.");
+        for (inverter, power) in &values.power {
+            Self::sample(
+                out,
+                "grelsolar_power_watts",
+                &[("inverter", inverter)],
+                *power,
+            );
+        }
+        Self::header(
+            out,
+            "grelsolar_energy_today_watt_hours",
+            "gauge",
+            "Energy produced today.",
+        );
+        for (inverter, energy) in &values.energy {
+            Self::sample(
+                out,
+                "grelsolar_energy_today_watt_hours",
+                &[("inverter", inverter)],
+                *energy,
+            );
+        }
+        Self::header(
+            out,
+            "grelsolar_energy_total_watt_hours",
+            "gauge",
+            "Energy produced since installation by all inverters.",
+        );
+        if let Some(energy_total) = values.energy_total {
+            Self::sample(out, "grelsolar_energy_total_watt_hours", &[], energy_total);
+        }
+        Self::header(
+            out,
+            "grelsolar_status_info",
+            "gauge",
+            "Status reported by the source.",
+        );
+        for (inverter, status) in &values.status {
+            let fault = status.fault.to_string();
+            let labels = [
+                ("inverter", inverter.as_str()),
+                ("status", status.text.as_str()),
+                ("fault", fault.as_str()),
+            ];
+            Self::sample(out, "grelsolar_status_info", &labels, 1);
+        }
+        Self::header(
+            out,
+            "grelsolar_status",
+            "gauge",
+            "Category of the status, 1 for the current one.",
+        );
+        for (inverter, status) in &values.status {
+            for category in StatusCategory::iter() {
+                let name = category.to_string();
+                let labels = [("inverter", inverter.as_str()), ("category", &name)];
+                let value = i64::from(category == status.category);
+                Self::sample(out, "grelsolar_status", &labels, value);
+            }
+        }
+    }
+
+    fn render_syncs(&self, out: &mut String) {
+        let syncs = self.syncs.lock().expect("poisoned syncs lock");
+        Self::header(
+            out,
+            "grelsolar_syncs_total",
+            "counter",
+            "Polls of each sync task by result.",
+        );
+        for (task, stats) in syncs.iter() {
+            for (result, count) in [("success", stats.successes), ("failure", stats.failures)] {
+                let labels = [("task", task.as_str()), ("result", result)];
+                Self::sample(out, "grelsolar_syncs_total", &labels, count);
+            }
+        }
+        Self::header(
+            out,
+            "grelsolar_sync_duration_seconds",
+            "histogram",
+            "Duration of the polls of each sync task, requests included.",
+        );
+        for (task, stats) in syncs.iter() {
+            let mut cumulative = 0;
+            for (le, count) in SYNC_DURATION_BUCKETS.iter().zip(stats.buckets) {
+                cumulative += count;
+                let le = le.to_string();
+                let labels = [("task", task.as_str()), ("le", le.as_str())];
+                Self::sample(
+                    out,
+                    "grelsolar_sync_duration_seconds_bucket",
+                    &labels,
+                    cumulative,
+                );
+            }
+            let count = stats.successes + stats.failures;
+            let labels = [("task", task.as_str()), ("le", "+Inf")];
+            Self::sample(
+                out,
+                "grelsolar_sync_duration_seconds_bucket",
+                &labels,
+                count,
+            );
+            let labels = [("task", task.as_str())];
+            Self::sample(
+                out,
+                "grelsolar_sync_duration_seconds_sum",
+                &labels,
+                stats.duration_sum,
+            );
+            Self::sample(out, "grelsolar_sync_duration_seconds_count", &labels, count);
+        }
+        Self::header(
+            out,
+            "grelsolar_last_success_timestamp_seconds",
+            "gauge",
+            "Time of the last successful poll of each sync task.",
+        );
+        for (task, stats) in syncs.iter() {
+            if let Some(last_success) = stats.last_success {
+                Self::sample(
+                    out,
+                    "grelsolar_last_success_timestamp_seconds",
+                    &[("task", task)],
+                    last_success,
+                );
+            }
+        }
+    }
+
+    fn render_circuit_breakers(&self, out: &mut String) {
+        let circuit_breakers = self
+            .circuit_breakers
+            .lock()
+            .expect("poisoned circuit breakers lock");
+        Self::header(
+            out,
+            "grelsolar_circuit_breaker_open",
+            "gauge",
+            "1 while the circuit breaker of the client rejects the requests.",
+        );
+        for (client, is_open) in circuit_breakers.iter() {
+            let value = i64::from(is_open());
+            Self::sample(
+                out,
+                "grelsolar_circuit_breaker_open",
+                &[("client", client)],
+                value,
+            );
+        }
+    }
+
+    fn render_request_latencies(&self, out: &mut String) {
+        let request_latencies = self
+            .request_latencies
+            .lock()
+            .expect("poisoned request latencies lock");
+        Self::header(
+            out,
+            "grelsolar_request_duration_seconds",
+            "histogram",
+            "Latency of the requests sent by each client, each retry counting as a request.",
+        );
+        for (client, latencies) in request_latencies.iter() {
+            let latencies = latencies();
+            let mut cumulative = 0;
+            for (le, count) in LATENCY_BUCKETS.iter().zip(latencies.buckets) {
+                cumulative += count;
+                let le = le.to_string();
+                let labels = [("client", client.as_str()), ("le", le.as_str())];
+                Self::sample(
+                    out,
+                    "grelsolar_request_duration_seconds_bucket",
+                    &labels,
+                    cumulative,
+                );
+            }
+            let labels = [("client", client.as_str()), ("le", "+Inf")];
+            Self::sample(
+                out,
+                "grelsolar_request_duration_seconds_bucket",
+                &labels,
+                latencies.count,
+            );
+            let labels = [("client", client.as_str())];
+            Self::sample(
+                out,
+                "grelsolar_request_duration_seconds_sum",
+                &labels,
+                latencies.sum,
+            );
+            Self::sample(
+                out,
+                "grelsolar_request_duration_seconds_count",
+                &labels,
+                latencies.count,
+            );
+        }
+    }
+
+    fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+    }
+
+    fn sample<T: std::fmt::Display>(
+        out: &mut String,
+        name: &str,
+        labels: &[(&str, &str)],
+        value: T,
+    ) {
+        let _ = write!(out, "{name}");
+        if !labels.is_empty() {
+            let labels: Vec<_> = labels
+                .iter()
+                .map(|(key, value)| format!("{key}=\"{}\"", Self::escape(value)))
+                .collect();
+            let _ = write!(out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(out, " {value}");
+    }
+
+    /// Escapes the backslashes, double quotes and line feeds of a label value.
+    fn escape(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    }
+
+    fn inverter_label(inverter_id: Option<u8>) -> String {
+        inverter_id.map_or_else(|| SITE.to_string(), |id| id.to_string())
+    }
+}
+
+#[async_trait]
+impl Sink for Metrics {
+    fn name(&self) -> &str {
+        "prometheus"
+    }
+
+    async fn publish_power(&self, inverter_id: Option<u8>, power: i64) -> anyhow::Result<()> {
+        let mut values = self.values.lock().expect("poisoned values lock");
+        values
+            .power
+            .insert(Self::inverter_label(inverter_id), power);
+        Ok(())
+    }
+
+    async fn publish_energy(
+        &self,
+        inverter_id: Option<u8>,
+        _day: NaiveDate,
+        energy: i64,
+    ) -> anyhow::Result<()> {
+        let mut values = self.values.lock().expect("poisoned values lock");
+        values
+            .energy
+            .insert(Self::inverter_label(inverter_id), energy);
+        Ok(())
+    }
+
+    async fn publish_energy_total(&self, energy_total: i64) -> anyhow::Result<()> {
+        self.values
+            .lock()
+            .expect("poisoned values lock")
+            .energy_total = Some(energy_total);
+        Ok(())
+    }
+
+    async fn publish_status(&self, inverter_id: Option<u8>, status: &Status) -> anyhow::Result<()> {
+        let mut values = self.values.lock().expect("poisoned values lock");
+        values
+            .status
+            .insert(Self::inverter_label(inverter_id), status.clone());
+        Ok(())
+    }
+
+    /// The category is already exported along with each status.
+    async fn publish_state(&self, _status: &Status) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Removes the values of the group, so stale values are not exported.
+    async fn set_unavailable(&self, group: SensorGroup) -> anyhow::Result<()> {
+        let mut values = self.values.lock().expect("poisoned values lock");
+        match group {
+            SensorGroup::Power => values.power.clear(),
+            SensorGroup::Energy => {
+                values.energy.clear();
+                values.energy_total = None;
+            }
+            SensorGroup::Status => values.status.clear(),
+            SensorGroup::Consumption | SensorGroup::DcStrings => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integration::solarlog::InverterStatus;
+
+    #[tokio::test]
+    async fn test_render_values() {
+        let metrics = Metrics::default();
+        metrics.publish_power(None, 2000).await.unwrap();
+        metrics.publish_power(Some(1), 800).await.unwrap();
+        let day = NaiveDate::from_ymd_opt(2025, 6, 25).unwrap();
+        metrics.publish_energy(None, day, 5100).await.unwrap();
+        metrics.publish_energy_total(24870500).await.unwrap();
+        let status = Status::from(InverterStatus::ShutdownFault);
+        metrics.publish_status(None, &status).await.unwrap();
+
+        let rendered = metrics.render();
+
+        for line in [
+            "# TYPE grelsolar_power_watts gauge",
+            "grelsolar_power_watts{inverter=\"1\"} 800",
+            "grelsolar_power_watts{inverter=\"site\"} 2000",
+            "grelsolar_energy_today_watt_hours{inverter=\"site\"} 5100",
+            "grelsolar_energy_total_watt_hours 24870500",
+            "grelsolar_status_info{inverter=\"site\",status=\"Shutdown Fault\",fault=\"true\"} 1",
+            "grelsolar_status{inverter=\"site\",category=\"producing\"} 0",
+            "grelsolar_status{inverter=\"site\",category=\"shutdown\"} 1",
+        ] {
+            assert!(rendered.lines().any(|l| l == line), "missing {line}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_unavailable() {
+        let metrics = Metrics::default();
+        metrics.publish_power(None, 2000).await.unwrap();
+        metrics.publish_energy_total(1000).await.unwrap();
+
+        metrics.set_unavailable(SensorGroup::Power).await.unwrap();
+
+        let rendered = metrics.render();
+        assert!(!rendered.contains("grelsolar_power_watts{"));
+        assert!(rendered.contains("grelsolar_energy_total_watt_hours 1000"));
+    }
+
+    #[test]
+    fn test_render_syncs() {
+        let metrics = Metrics::default();
+        metrics.record_sync("power", true, Duration::from_millis(80));
+        metrics.record_sync("power", false, Duration::from_secs(20));
+        metrics.register_circuit_breaker("solarlog", || true);
+
+        let rendered = metrics.render();
+
+        for line in [
+            "grelsolar_syncs_total{task=\"power\",result=\"success\"} 1",
+            "grelsolar_syncs_total{task=\"power\",result=\"failure\"} 1",
+            "grelsolar_sync_duration_seconds_bucket{task=\"power\",le=\"0.05\"} 0",
+            "grelsolar_sync_duration_seconds_bucket{task=\"power\",le=\"0.1\"} 1",
+            "grelsolar_sync_duration_seconds_bucket{task=\"power\",le=\"10\"} 1",
+            "grelsolar_sync_duration_seconds_bucket{task=\"power\",le=\"+Inf\"} 2",
+            "grelsolar_sync_duration_seconds_count{task=\"power\"} 2",
+            "grelsolar_circuit_breaker_open{client=\"solarlog\"} 1",
+        ] {
+            assert!(rendered.lines().any(|l| l == line), "missing {line}");
+        }
+        assert!(rendered.contains("grelsolar_last_success_timestamp_seconds{task=\"power\"} "));
+    }
+
+    #[test]
+    fn test_render_request_latencies() {
+        let metrics = Metrics::default();
+        metrics.register_request_latencies("homeassistant", || Latencies {
+            buckets: [0, 1, 2, 0, 0, 0, 0, 0, 0],
+            count: 4,
+            sum: 6.5,
+        });
+
+        let rendered = metrics.render();
+
+        for line in [
+            "# TYPE grelsolar_request_duration_seconds histogram",
+            "grelsolar_request_duration_seconds_bucket{client=\"homeassistant\",le=\"0.01\"} 0",
+            "grelsolar_request_duration_seconds_bucket{client=\"homeassistant\",le=\"0.025\"} 1",
+            "grelsolar_request_duration_seconds_bucket{client=\"homeassistant\",le=\"5\"} 3",
+            "grelsolar_request_duration_seconds_bucket{client=\"homeassistant\",le=\"+Inf\"} 4",
+            "grelsolar_request_duration_seconds_sum{client=\"homeassistant\"} 6.5",
+            "grelsolar_request_duration_seconds_count{client=\"homeassistant\"} 4",
+        ] {
+            assert!(rendered.lines().any(|l| l == line), "missing {line}");
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(Metrics::escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,111 @@
+//! Prometheus metrics exporter.
+//! A minimal HTTP server answering `GET /metrics` with the metrics in the Prometheus text format.
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{Duration, timeout};
+use tokio_util::sync::CancellationToken;
+
+use super::metrics::Metrics;
+
+/// Content type of the Prometheus text exposition format.
+static CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Largest request accepted, the request line and headers of a scrape are far smaller.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Delay to receive the request before the connection is closed.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct MetricsExporter {
+    address: SocketAddr,
+    metrics: Arc<Metrics>,
+}
+
+impl MetricsExporter {
+    /// Creates a new instance of `MetricsExporter`, listening on the given address once running.
+    pub fn new(address: SocketAddr, metrics: Arc<Metrics>) -> Self {
+        MetricsExporter { address, metrics }
+    }
+
+    /// Run the exporter until the token is cancelled.
+    /// The exporter stops with an error log if the address cannot be bound.
+    pub async fn run(&self, token: CancellationToken) {
+        let listener = match TcpListener::bind(self.address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::error!("Error listening for metrics on {}: {e}", self.address);
+                return;
+            }
+        };
+        log::info!("Serving metrics on http://{}/metrics", self.address);
+        self.serve(listener, token).await;
+    }
+
+    /// Answers the connections of the given listener until the token is cancelled.
+    pub async fn serve(&self, listener: TcpListener, token: CancellationToken) {
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        let metrics = Arc::clone(&self.metrics);
+                        tokio::spawn(async move {
+                            if let Err(e) = Self::handle(stream, &metrics).await {
+                                log::debug!("Error answering metrics request: {e}");
+                            }
+                        });
+                    }
+                    Err(e) => log::warn!("Error accepting metrics connection: {e}"),
+                },
+                _ = token.cancelled() => {
+                    log::debug!("metrics_exporter: shutting down");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Answers a single request, then closes the connection.
+    async fn handle(mut stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+        let request = timeout(REQUEST_TIMEOUT, Self::read_request(&mut stream))
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::TimedOut))??;
+        let request_line = request.lines().next().unwrap_or_default();
+        let mut parts = request_line.split_whitespace();
+        let (method, path) = (parts.next(), parts.next());
+        let path = path.map(|path| path.split('?').next().unwrap_or_default());
+        let (status, content_type, body) = match (method, path) {
+            (Some("GET"), Some("/metrics")) => ("200 OK", CONTENT_TYPE, metrics.render()),
+            (Some(_), Some("/metrics")) => (
+                "405 Method Not Allowed",
+                "text/plain",
+                "Method Not Allowed\n".to_string(),
+            ),
+            _ => ("404 Not Found", "text/plain", "Not Found\n".to_string()),
+        };
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+
+    /// Reads the request line and headers, the body of a scrape is empty.
+    async fn read_request(stream: &mut TcpStream) -> std::io::Result<String> {
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+            let read = stream.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+            if request.len() > MAX_REQUEST_SIZE {
+                return Err(std::io::Error::from(std::io::ErrorKind::InvalidData));
+            }
+        }
+        Ok(String::from_utf8_lossy(&request).into_owned())
+    }
+}
@@ -1,3 +1,15 @@
 //! Application Services module.
+mod availability;
+pub mod metrics;
+pub mod metrics_exporter;
+mod notification;
+pub mod preflight;
+pub mod sink;
 pub mod solarbridge;
-pub use solarbridge::SolarBridgeBackgroundService;
+pub mod source;
+pub use metrics::Metrics;
+pub use metrics_exporter::MetricsExporter;
+pub use preflight::{PreflightReport, StartupCheck};
+pub use sink::{Sink, SinkError, Sinks};
+pub use solarbridge::{InverterValues, SolarBridgeBackgroundService};
+pub use source::{Snapshot, SolarLogSource, Source, SourceError, Status, StatusCategory};
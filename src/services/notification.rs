@@ -0,0 +1,129 @@
+//! Shutdown notifications.
+//! A notification is created when an inverter stays shut down longer than the grace period,
+//! and dismissed once the inverter is no longer shut down, so a new shutdown is notified again.
+use chrono::{DateTime, Local};
+use tokio::time::{Duration, Instant};
+
+use super::source::Status;
+
+/// Action to take on the notification of an inverter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShutdownAction {
+    /// Create the notification, the inverter is shut down since the given time.
+    Create { since: DateTime<Local> },
+    /// Dismiss the notification.
+    Dismiss,
+}
+
+/// Shutdown state of an inverter.
+#[derive(Debug)]
+pub struct ShutdownTracker {
+    grace: Duration,
+    since: Option<(Instant, DateTime<Local>)>,
+    notified: bool,
+}
+
+impl ShutdownTracker {
+    /// Creates the tracker of an inverter, notified after being shut down for the grace period.
+    pub fn new(grace: Duration) -> Self {
+        ShutdownTracker {
+            grace,
+            since: None,
+            notified: false,
+        }
+    }
+
+    /// Records the status of the inverter.
+    /// Returns the action to take on the notification, see `done` once it succeeded.
+    pub fn update(&mut self, status: &Status) -> Option<ShutdownAction> {
+        if status.is_shutdown() {
+            let (started, since) = *self
+                .since
+                .get_or_insert_with(|| (Instant::now(), Local::now()));
+            let due = !self.notified && started.elapsed() >= self.grace;
+            return due.then_some(ShutdownAction::Create { since });
+        }
+        self.since = None;
+        self.notified.then_some(ShutdownAction::Dismiss)
+    }
+
+    /// Records that the action on the notification succeeded.
+    pub fn done(&mut self, action: &ShutdownAction) {
+        self.notified = matches!(action, ShutdownAction::Create { .. });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integration::solarlog::InverterStatus;
+
+    #[test]
+    fn test_update() {
+        let mut tracker = ShutdownTracker::new(Duration::ZERO);
+
+        assert_eq!(tracker.update(&InverterStatus::OnGrid.into()), None);
+        let action = tracker
+            .update(&InverterStatus::ShutdownFault.into())
+            .unwrap();
+        assert!(matches!(action, ShutdownAction::Create { .. }));
+        tracker.done(&action);
+        assert_eq!(
+            tracker.update(&InverterStatus::ShutdownCommand.into()),
+            None
+        );
+        assert_eq!(
+            tracker.update(&InverterStatus::OnGrid.into()),
+            Some(ShutdownAction::Dismiss)
+        );
+        tracker.done(&ShutdownAction::Dismiss);
+        assert_eq!(tracker.update(&InverterStatus::OnGrid.into()), None);
+    }
+
+    #[test]
+    fn test_update_shutdown_idle_shutdown() {
+        let mut tracker = ShutdownTracker::new(Duration::ZERO);
+        let first = tracker
+            .update(&InverterStatus::ShutdownFault.into())
+            .unwrap();
+        tracker.done(&first);
+
+        let idle = tracker.update(&InverterStatus::IdleInitializing.into());
+        tracker.done(idle.as_ref().unwrap());
+        let second = tracker.update(&InverterStatus::ShutdownFault.into());
+        tracker.done(second.as_ref().unwrap());
+
+        assert_eq!(idle, Some(ShutdownAction::Dismiss));
+        assert!(
+            matches!(second, Some(ShutdownAction::Create { .. })),
+            "a new shutdown should be notified again"
+        );
+        assert_eq!(
+            tracker.update(&InverterStatus::OnGrid.into()),
+            Some(ShutdownAction::Dismiss)
+        );
+    }
+
+    #[test]
+    fn test_update_grace_period() {
+        let mut tracker = ShutdownTracker::new(Duration::from_secs(3600));
+
+        assert_eq!(tracker.update(&InverterStatus::ShutdownFault.into()), None);
+        assert_eq!(tracker.update(&InverterStatus::ShutdownFault.into()), None);
+        assert_eq!(tracker.update(&InverterStatus::OnGrid.into()), None);
+    }
+
+    #[test]
+    fn test_update_not_done() {
+        let mut tracker = ShutdownTracker::new(Duration::ZERO);
+
+        let first = tracker
+            .update(&InverterStatus::ShutdownFault.into())
+            .unwrap();
+        let second = tracker
+            .update(&InverterStatus::ShutdownFault.into())
+            .unwrap();
+
+        assert_eq!(first, second, "the start of the shutdown should be kept");
+    }
+}
@@ -0,0 +1,118 @@
+//! Startup preflight.
+//! Checks the connection to Home Assistant and SolarLog before the first sync,
+//! so a wrong token or password is reported once and clearly instead of as repeated sync errors.
+use std::fmt;
+use strum_macros::{Display, EnumString};
+
+/// What to do when the startup check fails.
+#[derive(Debug, Default, PartialEq, Eq, EnumString, Display, Clone, Copy)]
+#[strum(serialize_all = "snake_case")]
+pub enum StartupCheck {
+    /// Skip the startup check.
+    Off,
+    /// Log the failures and start anyway.
+    #[default]
+    Warn,
+    /// Stop the application.
+    Abort,
+}
+
+/// Outcome of a single check, with a short description of the result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub result: Result<String, String>,
+}
+
+/// Outcome of all the startup checks, in the order they ran.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PreflightReport {
+    pub checks: Vec<Check>,
+}
+
+impl PreflightReport {
+    /// Records the outcome of a check.
+    pub fn record<T: fmt::Display, E: fmt::Display>(&mut self, name: &str, result: Result<T, E>) {
+        self.checks.push(Check {
+            name: name.to_string(),
+            result: result
+                .map(|value| value.to_string())
+                .map_err(|e| e.to_string()),
+        });
+    }
+
+    /// Returns `true` if all the checks passed.
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(|check| check.result.is_ok())
+    }
+
+    /// Returns the checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|check| check.result.is_err())
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failures = self.failures().count();
+        write!(
+            f,
+            "Startup check: {} passed, {failures} failed",
+            self.checks.len() - failures
+        )?;
+        for check in self.checks.iter() {
+            match &check.result {
+                Ok(value) => write!(f, "\n  [ok]   {}: {value}", check.name)?,
+                Err(e) => write!(f, "\n  [fail] {}: {e}", check.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn report() -> PreflightReport {
+        let mut report = PreflightReport::default();
+        report.record::<_, String>("Home Assistant API", Ok("API running."));
+        report.record::<i64, _>("SolarLog power", Err("access denied"));
+        report
+    }
+
+    #[test]
+    fn test_report() {
+        let report = report();
+
+        assert!(!report.is_ok());
+        assert_eq!(
+            report
+                .failures()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["SolarLog power"]
+        );
+        assert!(PreflightReport::default().is_ok());
+    }
+
+    #[test]
+    fn test_report_display() {
+        assert_eq!(
+            report().to_string(),
+            "Startup check: 1 passed, 1 failed\n  [ok]   Home Assistant API: API running.\n  [fail] SolarLog power: access denied"
+        );
+    }
+
+    #[test]
+    fn test_startup_check_from_str() {
+        assert_eq!(StartupCheck::from_str("off").unwrap(), StartupCheck::Off);
+        assert_eq!(
+            StartupCheck::from_str("abort").unwrap(),
+            StartupCheck::Abort
+        );
+        assert_eq!(StartupCheck::default(), StartupCheck::Warn);
+        assert!(StartupCheck::from_str("fail").is_err());
+    }
+}
@@ -0,0 +1,429 @@
+//! Output sinks.
+//! The power, energy and status read from the source are published to every sink,
+//! such as Home Assistant and InfluxDB. A failing sink does not prevent the others from receiving the values,
+//! and receives them again on the next poll.
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use futures_util::future::{BoxFuture, join_all};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use strum::IntoEnumIterator;
+use thiserror::Error;
+
+use super::solarbridge::SolarBridgeBackgroundService;
+use super::source::Status;
+use crate::integration::homeassistant::{self, SensorGroup};
+use crate::integration::{influxdb, solarlog};
+
+/// Output of the synchronized values.
+/// The inverter ID is `None` for the values of the whole site.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    /// Returns the name of the sink, used in the logs.
+    fn name(&self) -> &str;
+
+    /// Publishes the current power in W.
+    async fn publish_power(&self, inverter_id: Option<u8>, power: i64) -> anyhow::Result<()>;
+
+    /// Publishes the energy produced on the given day in Wh.
+    async fn publish_energy(
+        &self,
+        inverter_id: Option<u8>,
+        day: NaiveDate,
+        energy: i64,
+    ) -> anyhow::Result<()>;
+
+    /// Publishes the energy produced since installation by all inverters in Wh.
+    async fn publish_energy_total(&self, energy_total: i64) -> anyhow::Result<()>;
+
+    /// Publishes the status.
+    async fn publish_status(&self, inverter_id: Option<u8>, status: &Status) -> anyhow::Result<()>;
+
+    /// Publishes the coarse state of the site, derived from its status.
+    async fn publish_state(&self, status: &Status) -> anyhow::Result<()>;
+
+    /// Marks the values of the group unavailable until they are published again.
+    async fn set_unavailable(&self, group: SensorGroup) -> anyhow::Result<()>;
+}
+
+/// Error publishing a value, with the names of the sinks that failed.
+#[derive(Debug, Error)]
+#[error("Error publishing to {}", .0.join(", "))]
+pub struct SinkError(pub Vec<String>);
+
+/// Sinks receiving the same values.
+/// The values a sink failed to receive are published to it again with the next values of the same key.
+#[derive(Clone, Default)]
+pub struct Sinks {
+    sinks: Vec<Arc<dyn Sink>>,
+    /// Keys of the values each sink failed to receive, by index of the sink.
+    failed: Arc<Mutex<HashSet<(usize, String)>>>,
+}
+
+impl Sinks {
+    /// Adds a sink.
+    pub fn push(&mut self, sink: Arc<dyn Sink>) {
+        self.sinks.push(sink);
+    }
+
+    /// Returns the names of the sinks.
+    pub fn names(&self) -> Vec<&str> {
+        self.sinks.iter().map(|sink| sink.name()).collect()
+    }
+
+    /// Returns `true` if a sink failed to receive a value, which is published to it again even if unchanged.
+    pub fn has_failed(&self) -> bool {
+        !self.failed.lock().expect("poisoned failed lock").is_empty()
+    }
+
+    /// Publishes to all sinks concurrently.
+    /// Each failure is logged with the name of the sink, the error lists all the sinks that failed.
+    pub async fn publish<'a, F>(&'a self, publish: F) -> Result<(), SinkError>
+    where
+        F: Fn(&'a dyn Sink) -> BoxFuture<'a, anyhow::Result<()>>,
+    {
+        let targets: Vec<_> = (0..self.sinks.len()).collect();
+        let results = self.publish_to(&targets, publish).await;
+        Self::result(results.into_iter().map(|(_, name, ok)| (name, ok)))
+    }
+
+    /// Publishes the value with the given key, such as `power`, to all sinks when it changed,
+    /// and otherwise only to the sinks that failed to receive it, until they do.
+    pub async fn publish_changed<'a, F>(
+        &'a self,
+        key: &str,
+        changed: bool,
+        publish: F,
+    ) -> Result<(), SinkError>
+    where
+        F: Fn(&'a dyn Sink) -> BoxFuture<'a, anyhow::Result<()>>,
+    {
+        let targets: Vec<_> = {
+            let failed = self.failed.lock().expect("poisoned failed lock");
+            (0..self.sinks.len())
+                .filter(|index| changed || failed.contains(&(*index, key.to_string())))
+                .collect()
+        };
+        if targets.is_empty() {
+            return Ok(());
+        }
+        let results = self.publish_to(&targets, publish).await;
+        let mut failed = self.failed.lock().expect("poisoned failed lock");
+        for (index, _, ok) in results.iter() {
+            if *ok {
+                failed.remove(&(*index, key.to_string()));
+            } else {
+                failed.insert((*index, key.to_string()));
+            }
+        }
+        Self::result(results.into_iter().map(|(_, name, ok)| (name, ok)))
+    }
+
+    /// Publishes to the sinks with the given indices concurrently, and logs each failure.
+    /// Returns the index and name of each sink, and whether it succeeded.
+    async fn publish_to<'a, F>(
+        &'a self,
+        targets: &[usize],
+        publish: F,
+    ) -> Vec<(usize, &'a str, bool)>
+    where
+        F: Fn(&'a dyn Sink) -> BoxFuture<'a, anyhow::Result<()>>,
+    {
+        let sinks: Vec<_> = targets
+            .iter()
+            .map(|index| self.sinks[*index].as_ref())
+            .collect();
+        let results = join_all(sinks.iter().map(|sink| publish(*sink))).await;
+        targets
+            .iter()
+            .zip(sinks)
+            .zip(results)
+            .map(|((index, sink), result)| {
+                if let Err(e) = &result {
+                    log::error!("Error publishing to {}: {e}", sink.name());
+                }
+                (*index, sink.name(), result.is_ok())
+            })
+            .collect()
+    }
+
+    /// Returns the error listing the sinks that failed, if any.
+    fn result<'a>(results: impl Iterator<Item = (&'a str, bool)>) -> Result<(), SinkError> {
+        let failed: Vec<_> = results
+            .filter(|(_, ok)| !ok)
+            .map(|(name, _)| name.to_string())
+            .collect();
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(SinkError(failed))
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for homeassistant::Client {
+    fn name(&self) -> &str {
+        "homeassistant"
+    }
+
+    async fn publish_power(&self, inverter_id: Option<u8>, power: i64) -> anyhow::Result<()> {
+        match inverter_id {
+            Some(inverter_id) => self.set_inverter_current_power(inverter_id, power).await?,
+            None => self.set_solar_current_power(power).await?,
+        }
+        Ok(())
+    }
+
+    async fn publish_energy(
+        &self,
+        inverter_id: Option<u8>,
+        day: NaiveDate,
+        energy: i64,
+    ) -> anyhow::Result<()> {
+        let day_midnight = SolarBridgeBackgroundService::day_midnight(&day)
+            .ok_or_else(|| anyhow::anyhow!("No local midnight on {day}"))?;
+        match inverter_id {
+            Some(inverter_id) => {
+                self.set_inverter_energy(inverter_id, energy, &day_midnight)
+                    .await?
+            }
+            None => self.set_solar_energy(energy, &day_midnight).await?,
+        }
+        Ok(())
+    }
+
+    async fn publish_energy_total(&self, energy_total: i64) -> anyhow::Result<()> {
+        Ok(self.set_solar_energy_total(energy_total).await?)
+    }
+
+    async fn publish_status(&self, inverter_id: Option<u8>, status: &Status) -> anyhow::Result<()> {
+        let options = status_options(status);
+        match inverter_id {
+            Some(inverter_id) => {
+                self.set_inverter_status(inverter_id, &status.text, &options)
+                    .await?
+            }
+            None => self.set_solar_status(&status.text, &options).await?,
+        }
+        Ok(())
+    }
+
+    async fn publish_state(&self, status: &Status) -> anyhow::Result<()> {
+        Ok(self.set_solar_state(&status.category.to_string()).await?)
+    }
+
+    async fn set_unavailable(&self, group: SensorGroup) -> anyhow::Result<()> {
+        Ok(homeassistant::Client::set_unavailable(self, group).await?)
+    }
+}
+
+/// Returns the possible statuses of the Home Assistant enum sensor showing the given status.
+/// These are all the known statuses, followed by the given status if it is not one of them.
+fn status_options(status: &Status) -> Vec<String> {
+    let mut options: Vec<String> = solarlog::InverterStatus::iter()
+        .filter(|status| !matches!(status, solarlog::InverterStatus::Unknown(_)))
+        .map(|status| status.to_string())
+        .collect();
+    if !options.contains(&status.text) {
+        options.push(status.text.clone());
+    }
+    options
+}
+
+#[async_trait]
+impl Sink for influxdb::Client {
+    fn name(&self) -> &str {
+        "influxdb"
+    }
+
+    async fn publish_power(&self, inverter_id: Option<u8>, power: i64) -> anyhow::Result<()> {
+        Ok(self.write_power(inverter_id, power).await?)
+    }
+
+    async fn publish_energy(
+        &self,
+        inverter_id: Option<u8>,
+        day: NaiveDate,
+        energy: i64,
+    ) -> anyhow::Result<()> {
+        let day_midnight = SolarBridgeBackgroundService::day_midnight(&day)
+            .ok_or_else(|| anyhow::anyhow!("No local midnight on {day}"))?;
+        Ok(self
+            .write_energy(inverter_id, &day_midnight, energy)
+            .await?)
+    }
+
+    async fn publish_energy_total(&self, energy_total: i64) -> anyhow::Result<()> {
+        Ok(self.write_energy_total(energy_total).await?)
+    }
+
+    async fn publish_status(&self, inverter_id: Option<u8>, status: &Status) -> anyhow::Result<()> {
+        let category = status.category.to_string();
+        Ok(self
+            .write_status(inverter_id, &status.text, &category, status.fault)
+            .await?)
+    }
+
+    /// The category is already written along with each status.
+    async fn publish_state(&self, _status: &Status) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// The missing points show the gap, nothing is written.
+    async fn set_unavailable(&self, _group: SensorGroup) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Sink recording the published power, failing when `fail` is set.
+    struct TestSink {
+        name: &'static str,
+        fail: AtomicBool,
+        power: Mutex<Vec<i64>>,
+    }
+
+    impl TestSink {
+        fn new(name: &'static str, fail: bool) -> Arc<Self> {
+            Arc::new(TestSink {
+                name,
+                fail: AtomicBool::new(fail),
+                power: Mutex::new(Vec::new()),
+            })
+        }
+
+        fn result(&self) -> anyhow::Result<()> {
+            if self.fail.load(Ordering::SeqCst) {
+                anyhow::bail!("{} is down", self.name);
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Sink for TestSink {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn publish_power(&self, _inverter_id: Option<u8>, power: i64) -> anyhow::Result<()> {
+            self.power.lock().unwrap().push(power);
+            self.result()
+        }
+
+        async fn publish_energy(&self, _: Option<u8>, _: NaiveDate, _: i64) -> anyhow::Result<()> {
+            self.result()
+        }
+
+        async fn publish_energy_total(&self, _: i64) -> anyhow::Result<()> {
+            self.result()
+        }
+
+        async fn publish_status(&self, _: Option<u8>, _: &Status) -> anyhow::Result<()> {
+            self.result()
+        }
+
+        async fn publish_state(&self, _: &Status) -> anyhow::Result<()> {
+            self.result()
+        }
+
+        async fn set_unavailable(&self, _: SensorGroup) -> anyhow::Result<()> {
+            self.result()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish() {
+        let first = TestSink::new("first", false);
+        let second = TestSink::new("second", false);
+        let mut sinks = Sinks::default();
+        sinks.push(first.clone());
+        sinks.push(second.clone());
+
+        let result = sinks
+            .publish(move |sink| sink.publish_power(None, 1200))
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(*first.power.lock().unwrap(), vec![1200]);
+        assert_eq!(*second.power.lock().unwrap(), vec![1200]);
+        assert_eq!(sinks.names(), vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_publish_isolates_failures() {
+        let failing = TestSink::new("failing", true);
+        let working = TestSink::new("working", false);
+        let mut sinks = Sinks::default();
+        sinks.push(failing.clone());
+        sinks.push(working.clone());
+
+        let result = sinks
+            .publish(move |sink| sink.publish_power(Some(1), 800))
+            .await;
+
+        assert_eq!(result.unwrap_err().0, vec!["failing"]);
+        assert_eq!(*failing.power.lock().unwrap(), vec![800]);
+        assert_eq!(*working.power.lock().unwrap(), vec![800]);
+    }
+
+    #[tokio::test]
+    async fn test_publish_changed_retries_failed_sink() {
+        let failing = TestSink::new("failing", true);
+        let working = TestSink::new("working", false);
+        let mut sinks = Sinks::default();
+        sinks.push(failing.clone());
+        sinks.push(working.clone());
+
+        let first = sinks
+            .publish_changed("power", true, move |sink| sink.publish_power(None, 800))
+            .await;
+        failing.fail.store(false, Ordering::SeqCst);
+        let has_failed = sinks.has_failed();
+        let retry = sinks
+            .publish_changed("power", false, move |sink| sink.publish_power(None, 800))
+            .await;
+        let unchanged = sinks
+            .publish_changed("power", false, move |sink| sink.publish_power(None, 800))
+            .await;
+
+        assert_eq!(first.unwrap_err().0, vec!["failing"]);
+        assert!(has_failed);
+        assert!(retry.is_ok());
+        assert!(unchanged.is_ok());
+        assert!(!sinks.has_failed());
+        assert_eq!(
+            *failing.power.lock().unwrap(),
+            vec![800, 800],
+            "the failing sink should receive the value again"
+        );
+        assert_eq!(*working.power.lock().unwrap(), vec![800]);
+    }
+
+    #[tokio::test]
+    async fn test_publish_without_sinks() {
+        let result = Sinks::default()
+            .publish(move |sink| sink.publish_energy_total(1000))
+            .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_status_options() {
+        let options = status_options(&solarlog::InverterStatus::OnGrid.into());
+        assert_eq!(options.len(), 24);
+        assert_eq!(options.first().unwrap(), "Idle Initializing");
+        assert!(options.contains(&"On-grid".to_string()));
+
+        let options =
+            status_options(&solarlog::InverterStatus::Unknown("Standby".to_string()).into());
+        assert_eq!(options.len(), 25);
+        assert_eq!(options.last().unwrap(), "Standby");
+    }
+}
@@ -1,65 +1,338 @@
 //! Solar Bridge Background Service.
 //! This service bridges SolarLog and Home Assistant, enabling automatic synchronization of solar production data between the two systems.
 
-use chrono::{DateTime, NaiveDate};
-use std::sync::Arc;
-use tokio::time::{Duration, interval};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{RwLock, watch};
+use tokio::time::{Duration, Instant, interval};
 use tokio_util::sync::CancellationToken;
 
+use super::availability::PollHealth;
+use super::metrics::Metrics;
+use super::notification::{ShutdownAction, ShutdownTracker};
+use super::preflight::PreflightReport;
+use super::sink::{Sink, Sinks};
+use super::source::{Snapshot, SolarLogSource, Source, SourceError, Status};
+use crate::integration::homeassistant::SensorGroup;
 use crate::integration::{homeassistant, solarlog};
 
+/// Delay before subscribing again to the Home Assistant events after a failure.
+const RESTART_WATCH_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Home Assistant event fired when the status of an inverter changes.
+pub const STATUS_CHANGED_EVENT: &str = "grelsolar_status_changed";
+
+/// Home Assistant event fired when an inverter is shut down by a fault.
+pub const FAULT_EVENT: &str = "grelsolar_fault";
+
+/// Values read from each inverter, keyed by inverter ID.
+pub type InverterValues<T> = BTreeMap<u8, T>;
+
+/// Consumption along with the grid energy integrated up to it.
+pub type ConsumptionReading = (solarlog::Consumption, solarlog::GridEnergy);
+
 pub struct SolarBridgeBackgroundService {
     solarlog: Arc<solarlog::Client>,
     homeassistant: Arc<homeassistant::Client>,
+    /// Source of the power, energy and status, SolarLog unless replaced.
+    source: Arc<dyn Source>,
+    /// Sinks of the power, energy and status, Home Assistant first.
+    sinks: Sinks,
+    /// Metrics of the sync tasks, `None` without the metrics exporter.
+    metrics: Option<Arc<Metrics>>,
     sync_power_interval: Duration,
     sync_energy_interval: Duration,
     sync_status_interval: Duration,
+    sync_consumption_interval: Option<Duration>,
+    sync_dc_strings_interval: Option<Duration>,
+    sync_intraday_interval: Option<Duration>,
+    unavailable_after_failures: Option<u32>,
+    unavailable_after: Option<Duration>,
+    shutdown_notification_after: Option<Duration>,
+    inverters: RwLock<Vec<u8>>,
+    /// Last snapshot read from the source and when it was read, shared by the power, energy and status tasks.
+    snapshot: Mutex<Option<(Instant, Arc<Snapshot>)>>,
+    /// Held while reading a snapshot, so the tasks polling at the same time share a single read.
+    snapshot_read: tokio::sync::Mutex<()>,
+    /// Last power read from each inverter.
+    latest_power: Mutex<InverterValues<i64>>,
+    /// Energy imported from and exported to the grid, `None` until the first consumption.
+    grid_energy: Mutex<Option<solarlog::GridEnergy>>,
+    /// Shutdown state of each inverter, for the shutdown notifications.
+    shutdowns: Mutex<InverterValues<ShutdownTracker>>,
+    /// Last status of each inverter the status events were fired for, kept on resync.
+    fired_status: Mutex<InverterValues<Status>>,
+    /// Notifies the sync tasks to publish all states again.
+    resync: watch::Sender<()>,
 }
 
 impl SolarBridgeBackgroundService {
     /// Creates a new instance of `SolarService`.
+    /// Only the first inverter is synchronized until `discover_inverters` is called.
+    /// Power and energy are read from the given data source, the status is always read per inverter.
     pub fn new(
         solarlog: Arc<solarlog::Client>,
         homeassistant: Arc<homeassistant::Client>,
+        data_source: solarlog::DataSource,
         sync_power_interval: Duration,
         sync_energy_interval: Duration,
         sync_status_interval: Duration,
     ) -> Self {
+        let source = Arc::new(SolarLogSource::new(solarlog.clone(), data_source));
+        let mut sinks = Sinks::default();
+        sinks.push(homeassistant.clone());
         SolarBridgeBackgroundService {
             solarlog,
             homeassistant,
+            source,
+            sinks,
+            metrics: None,
             sync_power_interval,
             sync_energy_interval,
             sync_status_interval,
+            sync_consumption_interval: None,
+            sync_dc_strings_interval: None,
+            sync_intraday_interval: None,
+            unavailable_after_failures: None,
+            unavailable_after: None,
+            shutdown_notification_after: None,
+            inverters: RwLock::new(vec![0]),
+            snapshot: Mutex::new(None),
+            snapshot_read: tokio::sync::Mutex::new(()),
+            latest_power: Mutex::new(InverterValues::new()),
+            grid_energy: Mutex::new(None),
+            shutdowns: Mutex::new(InverterValues::new()),
+            fired_status: Mutex::new(InverterValues::new()),
+            resync: watch::Sender::new(()),
+        }
+    }
+
+    /// Reads the power, energy and status from the given source instead of SolarLog.
+    /// The consumption, DC strings, intraday curve and history are still read from SolarLog.
+    pub fn with_source(mut self, source: Arc<dyn Source>) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Publishes the power, energy and status to the given sink too, in addition to Home Assistant.
+    pub fn with_sink(mut self, sink: Arc<dyn Sink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Records the outcome of the sync tasks in the given metrics, which also receive the power, energy and status.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.sinks.push(metrics.clone());
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Synchronizes the consumption at the given interval, `None` to disable it.
+    pub fn with_sync_consumption_interval(mut self, interval: Option<Duration>) -> Self {
+        self.sync_consumption_interval = interval;
+        self
+    }
+
+    /// Records the outcome of a poll of the given task and the duration since `started` in the metrics.
+    fn record_sync<T>(&self, task: &str, result: &Result<T, anyhow::Error>, started: Instant) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_sync(task, result.is_ok(), started.elapsed());
         }
     }
 
+    /// Synchronizes the DC measurements of each string at the given interval, `None` to disable it.
+    pub fn with_sync_dc_strings_interval(mut self, interval: Option<Duration>) -> Self {
+        self.sync_dc_strings_interval = interval;
+        self
+    }
+
+    /// Fills the gaps of the power history from the intraday curve at the given interval, `None` to disable it.
+    pub fn with_sync_intraday_interval(mut self, interval: Option<Duration>) -> Self {
+        self.sync_intraday_interval = interval;
+        self
+    }
+
+    /// Marks the sensors of a sync task unavailable after the given number of failed polls in a row,
+    /// or when the last successful poll is older than the given timeout, `None` to disable either.
+    pub fn with_unavailable_after(
+        mut self,
+        failures: Option<u32>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        self.unavailable_after_failures = failures;
+        self.unavailable_after = timeout;
+        self
+    }
+
+    /// Creates a Home Assistant persistent notification when an inverter stays shut down longer than the given
+    /// grace period, dismissed once it is no longer shut down, `None` to disable it.
+    pub fn with_shutdown_notification_after(mut self, grace: Option<Duration>) -> Self {
+        self.shutdown_notification_after = grace;
+        self
+    }
+
     /// Run the background service to synchronize data between SolarLog and Home Assistant.
+    /// The states held by Home Assistant are read first, so only the sensors that differ are published.
     pub async fn run(&self, token: CancellationToken) {
+        match self.discover_inverters().await {
+            Ok(inverters) => log::info!("Synchronizing inverters: {inverters:?}"),
+            Err(e) => log::warn!("Error discovering inverters, using the first one only: {e}"),
+        }
+        match self.homeassistant.load_states().await {
+            Ok(count) => log::info!("Reconciling with {count} Home Assistant states"),
+            Err(e) => {
+                log::warn!("Error reading the Home Assistant states, reading each one instead: {e}")
+            }
+        }
         tokio::join!(
             self.sync_solar_power_task(self.sync_power_interval, token.clone()),
             self.sync_solar_energy_task(self.sync_energy_interval, token.clone()),
-            self.sync_solar_status_task(self.sync_status_interval, token.clone())
+            self.sync_solar_status_task(self.sync_status_interval, token.clone()),
+            self.sync_consumption_task(self.sync_consumption_interval, token.clone()),
+            self.sync_dc_strings_task(self.sync_dc_strings_interval, token.clone()),
+            self.sync_intraday_task(self.sync_intraday_interval, token.clone()),
+            self.watch_homeassistant_restart_task(token.clone())
         );
     }
 
+    /// Publishes all states again immediately, instead of waiting for the next change.
+    /// The values are read again from the source first.
+    pub fn resync(&self) {
+        self.clear_snapshot();
+        self.resync.send_replace(());
+    }
+
+    /// Watches Home Assistant restarts and publishes all states again once it is back.
+    /// States written over the REST API are lost on restart, so they are pushed when the `homeassistant_started`
+    /// event is received, and after reconnecting since the event may have been missed while disconnected.
+    async fn watch_homeassistant_restart_task(&self, token: CancellationToken) {
+        let mut connected_once = false;
+        loop {
+            match self
+                .homeassistant
+                .subscribe_events(Some("homeassistant_started"))
+                .await
+            {
+                Ok(mut subscription) => {
+                    if connected_once {
+                        log::info!("Reconnected to Home Assistant, publishing all states");
+                        self.resync();
+                    }
+                    connected_once = true;
+                    loop {
+                        tokio::select! {
+                            event = subscription.next() => match event {
+                                Some(_) => {
+                                    log::info!("Home Assistant started, publishing all states");
+                                    self.resync();
+                                }
+                                None => {
+                                    log::warn!("Connection to Home Assistant lost");
+                                    break;
+                                }
+                            },
+                            _ = token.cancelled() => {
+                                log::debug!("watch_homeassistant_restart_task: shutting down");
+                                return;
+                            }
+                        }
+                    }
+                }
+                Err(e) => log::debug!("Error subscribing to Home Assistant events: {e}"),
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(RESTART_WATCH_RETRY_INTERVAL) => {},
+                _ = token.cancelled() => {
+                    log::debug!("watch_homeassistant_restart_task: shutting down");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Discovers the inverters connected to the source and synchronizes all of them from now on.
+    /// Fails if the source reports no inverter, the current inverters are kept in that case.
+    pub async fn discover_inverters(&self) -> Result<Vec<u8>, anyhow::Error> {
+        let inverters = self
+            .source
+            .discover_inverters()
+            .await
+            .map_err(SourceError)?;
+        if inverters.is_empty() {
+            anyhow::bail!("no inverter found");
+        }
+        *self.inverters.write().await = inverters.clone();
+        self.clear_snapshot();
+        Ok(inverters)
+    }
+
+    /// Imports the daily and monthly energy history of SolarLog into the Home Assistant statistics.
+    /// The inverters are discovered first, so the history of the whole site is imported.
+    /// The history ends at the energy total read from the source, as the energy total sensor does.
+    /// Returns the number of imported periods.
+    pub async fn backfill_energy_history(&self) -> Result<usize, anyhow::Error> {
+        if let Err(e) = self.discover_inverters().await {
+            log::warn!("Error discovering inverters, using the first one only: {e}");
+        }
+        let inverters = self.inverters().await;
+        let history = self.solarlog.get_energy_history(&inverters).await?;
+        let periods: Vec<_> = history
+            .periods()
+            .into_iter()
+            .filter_map(|(day, wh)| match Self::day_midnight(&day) {
+                Some(midnight) => Some((midnight, wh)),
+                None => {
+                    log::warn!(
+                        "Skipping energy history of {day}, its local midnight does not exist"
+                    );
+                    None
+                }
+            })
+            .collect();
+        if periods.is_empty() {
+            return Ok(0);
+        }
+        let total = self.read_snapshot().await?.energy_total;
+        self.homeassistant
+            .import_solar_energy_total_statistics(&periods, total)
+            .await?;
+        Ok(periods.len())
+    }
+
+    /// Returns the IDs of the synchronized inverters.
+    pub async fn inverters(&self) -> Vec<u8> {
+        self.inverters.read().await.clone()
+    }
+
     /// Periodically retrieves the current power from SolarLog and updates Home Assistant if it changes.
     /// This method runs in a loop, polling the SolarLog API at the specified interval.
     /// # Arguments
     /// * `period` - The interval at which to poll SolarLog for current power data.
     async fn sync_solar_power_task(&self, period: Duration, token: CancellationToken) {
-        let mut last_power = None;
+        let mut last_power: Option<InverterValues<i64>> = None;
+        let mut health = self.poll_health();
         let mut interval = interval(period);
+        let mut resync = self.resync.subscribe();
 
         loop {
             tokio::select! {
                 _ = interval.tick() => {},
+                _ = resync.changed() => {
+                    last_power = None;
+                }
                 _ = token.cancelled() => {
                     log::debug!("sync_solar_power_task: shutting down");
                     return;
                 }
             }
-            match self.sync_solar_power(last_power).await {
+            let last = last_power.as_ref().filter(|_| !health.is_unavailable());
+            let started = Instant::now();
+            let result = self.sync_solar_power(last).await;
+            self.record_poll(&mut health, SensorGroup::Power, &result, started)
+                .await;
+            match result {
                 Ok(power) => last_power = power,
                 Err(e) => log::error!("Error syncing solar power: {e}"),
             }
@@ -71,21 +344,44 @@ impl SolarBridgeBackgroundService {
     /// # Arguments
     /// * `period` - The interval at which to poll SolarLog for inverter status data.
     async fn sync_solar_energy_task(&self, period: Duration, token: CancellationToken) {
-        let mut last_value: Option<(NaiveDate, i64)> = None;
+        let mut last_value: Option<InverterValues<(NaiveDate, i64)>> = None;
+        let mut last_total: Option<i64> = None;
+        let mut health = self.poll_health();
         let mut interval = interval(period);
+        let mut resync = self.resync.subscribe();
 
         loop {
             tokio::select! {
                 _ = interval.tick() => {},
+                _ = resync.changed() => {
+                    last_value = None;
+                    last_total = None;
+                }
                 _ = token.cancelled() => {
                     log::debug!("sync_solar_energy_task: shutting down");
                     return;
                 }
             }
-            match self.sync_solar_energy(last_value).await {
+            let unavailable = health.is_unavailable();
+            let last = last_value.as_ref().filter(|_| !unavailable);
+            let started = Instant::now();
+            let result = self.sync_solar_energy(last).await;
+            self.record_poll(&mut health, SensorGroup::Energy, &result, started)
+                .await;
+            match result {
                 Ok(energy) => last_value = energy,
                 Err(e) => log::error!("Error syncing solar energy: {e}"),
             }
+            // The total is republished when its group is restored, or marked unavailable by this poll.
+            let unavailable = unavailable || health.is_unavailable();
+            let last = last_total.filter(|_| !unavailable);
+            let started = Instant::now();
+            let result = self.sync_solar_energy_total(last).await;
+            self.record_sync("energy_total", &result, started);
+            match result {
+                Ok(total) => last_total = total,
+                Err(e) => log::error!("Error syncing solar energy total: {e}"),
+            }
         }
     }
 
@@ -94,89 +390,803 @@ impl SolarBridgeBackgroundService {
     /// # Arguments
     /// * `period` - The interval at which to poll SolarLog for inverter status data.
     async fn sync_solar_status_task(&self, period: Duration, token: CancellationToken) {
-        let mut last_status: Option<solarlog::InverterStatus> = None;
+        let mut last_status: Option<InverterValues<Status>> = None;
+        let mut health = self.poll_health();
         let mut interval = interval(period);
+        let mut resync = self.resync.subscribe();
         loop {
             tokio::select! {
                 _ = interval.tick() => {},
+                _ = resync.changed() => {
+                    last_status = None;
+                }
                 _ = token.cancelled() => {
                     log::debug!("sync_solar_status_task: shutting down");
                     return;
                 }
             }
-            match self.sync_solar_status(last_status.as_ref()).await {
-                Ok(status) => last_status = status,
+            let last = last_status.as_ref().filter(|_| !health.is_unavailable());
+            let started = Instant::now();
+            let result = self.sync_solar_status(last).await;
+            self.record_poll(&mut health, SensorGroup::Status, &result, started)
+                .await;
+            match result {
+                Ok(status) => {
+                    if let Some(status) = status.as_ref() {
+                        if let Err(e) = self.notify_shutdowns(status).await {
+                            log::error!("Error notifying inverter shutdown: {e}");
+                        }
+                    }
+                    last_status = status;
+                }
                 Err(e) => log::error!("Error syncing solar status: {e}"),
             }
         }
     }
 
-    /// Synchronizes the current solar power with Home Assistant.
+    /// Periodically retrieves the consumption from SolarLog and updates Home Assistant if it changes.
+    /// This method runs in a loop, polling the SolarLog API at the specified interval.
+    /// # Arguments
+    /// * `period` - The interval at which to poll SolarLog for consumption data, `None` to disable it.
+    async fn sync_consumption_task(&self, period: Option<Duration>, token: CancellationToken) {
+        let Some(period) = period else {
+            return;
+        };
+        let mut last_consumption: Option<ConsumptionReading> = None;
+        let mut health = self.poll_health();
+        let mut interval = interval(period);
+        let mut resync = self.resync.subscribe();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {},
+                _ = resync.changed() => {
+                    last_consumption = None;
+                }
+                _ = token.cancelled() => {
+                    log::debug!("sync_consumption_task: shutting down");
+                    return;
+                }
+            }
+            let last = last_consumption
+                .as_ref()
+                .filter(|_| !health.is_unavailable());
+            let started = Instant::now();
+            let result = self.sync_consumption(last).await;
+            self.record_poll(&mut health, SensorGroup::Consumption, &result, started)
+                .await;
+            match result {
+                Ok(consumption) => last_consumption = consumption,
+                Err(e) => log::error!("Error syncing consumption: {e}"),
+            }
+        }
+    }
+
+    /// Periodically retrieves the DC measurements of each string from SolarLog and updates Home Assistant if they change.
+    /// This method runs in a loop, polling the SolarLog API at the specified interval.
+    /// # Arguments
+    /// * `period` - The interval at which to poll SolarLog for DC data, `None` to disable it.
+    async fn sync_dc_strings_task(&self, period: Option<Duration>, token: CancellationToken) {
+        let Some(period) = period else {
+            return;
+        };
+        let mut last_dc_strings: Option<InverterValues<Vec<solarlog::DcString>>> = None;
+        let mut health = self.poll_health();
+        let mut interval = interval(period);
+        let mut resync = self.resync.subscribe();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {},
+                _ = resync.changed() => {
+                    last_dc_strings = None;
+                }
+                _ = token.cancelled() => {
+                    log::debug!("sync_dc_strings_task: shutting down");
+                    return;
+                }
+            }
+            let last = last_dc_strings
+                .as_ref()
+                .filter(|_| !health.is_unavailable());
+            let started = Instant::now();
+            let result = self.sync_dc_strings(last).await;
+            self.record_poll(&mut health, SensorGroup::DcStrings, &result, started)
+                .await;
+            match result {
+                Ok(dc_strings) => last_dc_strings = dc_strings,
+                Err(e) => log::error!("Error syncing DC strings: {e}"),
+            }
+        }
+    }
+
+    /// Creates the poll health of a sync task with the configured thresholds.
+    fn poll_health(&self) -> PollHealth {
+        PollHealth::new(self.unavailable_after_failures, self.unavailable_after)
+    }
+
+    /// Records the result of a poll, and marks the sensors of the group unavailable when the source is unreachable.
+    /// Only source and SolarLog errors count as failed polls,
+    /// a sink that failed receives the values again on the next poll, see `Sinks::publish_changed`.
+    /// While unavailable, the next poll publishes all sensors again, which restores them.
+    /// The outcome and the duration since `started` are recorded in the metrics of the group.
+    async fn record_poll<T>(
+        &self,
+        health: &mut PollHealth,
+        group: SensorGroup,
+        result: &Result<T, anyhow::Error>,
+        started: Instant,
+    ) {
+        self.record_sync(&group.to_string(), result, started);
+        match result {
+            Ok(_) => {
+                if health.success() {
+                    log::info!("SolarLog reachable again, {group} sensors restored");
+                }
+            }
+            Err(e) if e.is::<SourceError>() || e.is::<solarlog::Error>() => {
+                if !health.failure() {
+                    return;
+                }
+                match self
+                    .sinks
+                    .publish(move |sink| sink.set_unavailable(group))
+                    .await
+                {
+                    Ok(()) => {
+                        health.mark_unavailable();
+                        log::warn!("SolarLog unreachable, {group} sensors marked unavailable");
+                    }
+                    Err(e) => log::error!("Error marking {group} sensors unavailable: {e}"),
+                }
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Synchronizes the DC measurements of each string of each inverter with Home Assistant.
+    /// All inverters are read in a single SolarLog request, only the changed measurements are published.
+    pub async fn sync_dc_strings(
+        &self,
+        last_dc_strings: Option<&InverterValues<Vec<solarlog::DcString>>>,
+    ) -> Result<Option<InverterValues<Vec<solarlog::DcString>>>, anyhow::Error> {
+        let dc_strings = self.read_dc_strings().await?;
+        for (inverter_id, strings) in dc_strings.iter() {
+            for (index, dc_string) in strings.iter().enumerate() {
+                let last = last_dc_strings
+                    .and_then(|last| last.get(inverter_id))
+                    .and_then(|last| last.get(index));
+                let string = index + 1;
+                if last.map(|last| last.voltage) != Some(dc_string.voltage) {
+                    self.homeassistant
+                        .set_dc_string_voltage(*inverter_id, string, dc_string.voltage)
+                        .await?;
+                }
+                if last.map(|last| last.current) != Some(dc_string.current) {
+                    self.homeassistant
+                        .set_dc_string_current(*inverter_id, string, dc_string.current)
+                        .await?;
+                }
+                if last.map(|last| last.power) != Some(dc_string.power) {
+                    self.homeassistant
+                        .set_dc_string_power(*inverter_id, string, dc_string.power)
+                        .await?;
+                }
+            }
+        }
+        Ok(Some(dc_strings))
+    }
+
+    /// Periodically retrieves the intraday curve from SolarLog and imports the missing hours into Home Assistant.
+    /// This method runs in a loop, polling the SolarLog API at the specified interval.
+    /// # Arguments
+    /// * `period` - The interval at which to poll SolarLog for the intraday curve, `None` to disable it.
+    async fn sync_intraday_task(&self, period: Option<Duration>, token: CancellationToken) {
+        let Some(period) = period else {
+            return;
+        };
+        let mut last_hour: Option<NaiveDateTime> = None;
+        let mut interval = interval(period);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {},
+                _ = token.cancelled() => {
+                    log::debug!("sync_intraday_task: shutting down");
+                    return;
+                }
+            }
+            let started = Instant::now();
+            let result = self.sync_intraday(last_hour).await;
+            self.record_sync("intraday", &result, started);
+            match result {
+                Ok(hour) => last_hour = hour,
+                Err(e) => log::error!("Error syncing intraday curve: {e}"),
+            }
+        }
+    }
+
+    /// Fills the gaps of the solar power history with the intraday curve of all inverters.
+    /// The complete hours of the current day after `last_hour` are imported into the Home Assistant statistics,
+    /// so the hours missed while the bridge or Home Assistant was down are pushed once both are up again.
+    /// The hours Home Assistant already compiled from the power sensor are kept as they are.
+    /// Returns the start of the last complete hour.
+    pub async fn sync_intraday(
+        &self,
+        last_hour: Option<NaiveDateTime>,
+    ) -> Result<Option<NaiveDateTime>, anyhow::Error> {
+        let inverters = self.inverters().await;
+        let snapshot = self
+            .solarlog
+            .query()
+            .inverters(&inverters)
+            .intraday_curve()
+            .send()
+            .await?;
+        let curve = solarlog::IntradayCurve::sum(snapshot.intraday_curve.values());
+        let Some(day) = curve.samples.last().map(|sample| sample.time.date()) else {
+            return Ok(last_hour);
+        };
+        let hours: Vec<_> = curve
+            .of_day(day)
+            .hourly_power()
+            .into_iter()
+            .filter(|hour| last_hour.is_none_or(|last_hour| hour.start > last_hour))
+            .collect();
+        let Some(last) = hours.last().map(|hour| hour.start) else {
+            return Ok(last_hour);
+        };
+        let hours: Vec<_> = hours
+            .iter()
+            .filter_map(|hour| {
+                let start = hour.start.and_local_timezone(chrono::Local).earliest()?;
+                Some((start, hour.mean, hour.min, hour.max))
+            })
+            .collect();
+        let (Some(first), Some(end)) = (
+            hours.first().map(|hour| hour.0),
+            hours.last().map(|hour| hour.0 + TimeDelta::hours(1)),
+        ) else {
+            return Ok(Some(last));
+        };
+        let compiled = self
+            .homeassistant
+            .get_solar_power_statistics_hours(&first, &end)
+            .await?;
+        let missing: Vec<_> = hours
+            .into_iter()
+            .filter(|hour| !compiled.contains(&hour.0.to_utc()))
+            .collect();
+        if !missing.is_empty() {
+            self.homeassistant
+                .import_solar_power_statistics(&missing)
+                .await?;
+            log::info!("Solar power history filled up to {last}");
+        }
+        Ok(Some(last))
+    }
+
+    /// Synchronizes the consumption, the grid flows and the self-consumption ratio with Home Assistant.
+    /// The grid flows are integrated into the grid import and export energy meters.
+    /// Only the sensors whose value changed are published.
+    pub async fn sync_consumption(
+        &self,
+        last: Option<&ConsumptionReading>,
+    ) -> Result<Option<ConsumptionReading>, anyhow::Error> {
+        let consumption = self.solarlog.get_consumption().await?;
+        let grid_energy = self.add_grid_energy(&consumption).await;
+        let reading = (consumption, grid_energy);
+        if last == Some(&reading) {
+            return Ok(Some(reading));
+        }
+        let (consumption, grid_energy) = &reading;
+        let last_consumption = last.map(|(consumption, _)| consumption);
+        let last_grid_energy = last.map(|(_, grid_energy)| grid_energy);
+        if last_consumption.map(|last| last.power) != Some(consumption.power) {
+            self.homeassistant
+                .set_consumption_power(consumption.power)
+                .await?;
+        }
+        if last_consumption.map(|last| last.energy_of_day) != Some(consumption.energy_of_day) {
+            let (day, wh) = consumption.energy_of_day;
+            let midnight = Self::day_midnight(&day)
+                .ok_or_else(|| anyhow::anyhow!("No local midnight on {day}"))?;
+            self.homeassistant
+                .set_consumption_energy(wh, &midnight)
+                .await?;
+        }
+        if last_consumption.map(|last| last.grid_import_power())
+            != Some(consumption.grid_import_power())
+        {
+            self.homeassistant
+                .set_grid_import_power(consumption.grid_import_power())
+                .await?;
+        }
+        if last_consumption.map(|last| last.grid_export_power())
+            != Some(consumption.grid_export_power())
+        {
+            self.homeassistant
+                .set_grid_export_power(consumption.grid_export_power())
+                .await?;
+        }
+        let import = grid_energy.import.round() as i64;
+        if last_grid_energy.map(|last| last.import.round() as i64) != Some(import) {
+            self.homeassistant.set_grid_import_energy(import).await?;
+        }
+        let export = grid_energy.export.round() as i64;
+        if last_grid_energy.map(|last| last.export.round() as i64) != Some(export) {
+            self.homeassistant.set_grid_export_energy(export).await?;
+        }
+        if last_consumption.map(|last| last.self_consumption_ratio())
+            != Some(consumption.self_consumption_ratio())
+        {
+            self.homeassistant
+                .set_self_consumption_ratio(consumption.self_consumption_ratio())
+                .await?;
+        }
+        Ok(Some(reading))
+    }
+
+    /// Integrates the grid flows of the consumption into the grid energy, and returns it.
+    /// The grid energy starts from the meters held by Home Assistant, so they keep counting across restarts.
+    async fn add_grid_energy(&self, consumption: &solarlog::Consumption) -> solarlog::GridEnergy {
+        let started = self
+            .grid_energy
+            .lock()
+            .expect("poisoned grid energy lock")
+            .is_some();
+        let initial = if started {
+            solarlog::GridEnergy::default()
+        } else {
+            solarlog::GridEnergy::new(
+                self.homeassistant_energy("grid_import_energy").await,
+                self.homeassistant_energy("grid_export_energy").await,
+            )
+        };
+        let mut grid_energy = self.grid_energy.lock().expect("poisoned grid energy lock");
+        let grid_energy = grid_energy.get_or_insert(initial);
+        grid_energy.add(consumption);
+        grid_energy.clone()
+    }
+
+    /// Returns the energy in Wh of the meter with the given key held by Home Assistant, zero if unknown.
+    async fn homeassistant_energy(&self, key: &str) -> f64 {
+        match self.homeassistant.get_state(key).await {
+            Ok(state) => state
+                .and_then(|state| state.state.parse::<f64>().ok())
+                .map_or(0.0, |kwh| kwh * 1000.0),
+            Err(e) => {
+                log::warn!("Error reading the {key} meter, starting from zero: {e}");
+                0.0
+            }
+        }
+    }
+
+    /// Synchronizes the current solar power with the sinks.
+    /// The power is taken from the snapshot shared with the energy and status syncs.
+    /// The site total is always published, the power of each inverter only when there are several.
+    /// A failing sink is logged and does not fail the sync, so the other sinks do not receive the same values again,
+    /// the failing sink receives the values again on the next sync until it succeeds.
     pub async fn sync_solar_power(
         &self,
-        last_power: Option<i64>,
-    ) -> Result<Option<i64>, anyhow::Error> {
-        let power = self.solarlog.get_current_power().await?;
-        if last_power == Some(power) {
+        last_power: Option<&InverterValues<i64>>,
+    ) -> Result<Option<InverterValues<i64>>, anyhow::Error> {
+        let power = self.read_snapshot().await?.power.clone();
+        *self.latest_power.lock().expect("poisoned power lock") = power.clone();
+        if last_power == Some(&power) && !self.sinks.has_failed() {
             return Ok(Some(power));
         }
-        // Only reach
-        self.homeassistant.set_solar_current_power(power).await?;
+        if power.len() > 1 {
+            for (inverter_id, value) in power.iter() {
+                let changed = last_power.and_then(|last| last.get(inverter_id)) != Some(value);
+                let (inverter_id, value) = (Some(*inverter_id), *value);
+                self.sinks
+                    .publish_changed(&Self::key("power", inverter_id), changed, move |sink| {
+                        sink.publish_power(inverter_id, value)
+                    })
+                    .await
+                    .ok();
+            }
+        }
+        let total = Self::total_power(&power);
+        let changed = last_power.map(Self::total_power) != Some(total);
+        self.sinks
+            .publish_changed("power", changed, move |sink| {
+                sink.publish_power(None, total)
+            })
+            .await
+            .ok();
         Ok(Some(power))
     }
 
-    /// Synchronizes the solar energy produced today with Home Assistant.
+    /// Synchronizes the solar energy produced today with the sinks.
+    /// The energy is taken from the snapshot shared with the power and status syncs.
+    /// The site total is always published, the energy of each inverter only when there are several.
+    /// A failing sink is logged and does not fail the sync, it receives the energy again on the next sync.
     pub async fn sync_solar_energy(
         &self,
-        last_value: Option<(NaiveDate, i64)>,
-    ) -> Result<Option<(NaiveDate, i64)>, anyhow::Error> {
-        let value = self.solarlog.get_energy_of_last_day().await?;
-        if last_value == Some(value) {
-            return Ok(Some(value));
+        last_value: Option<&InverterValues<(NaiveDate, i64)>>,
+    ) -> Result<Option<InverterValues<(NaiveDate, i64)>>, anyhow::Error> {
+        let energy = self.read_snapshot().await?.energy.clone();
+        if last_value == Some(&energy) && !self.sinks.has_failed() {
+            return Ok(Some(energy));
+        }
+        if energy.len() > 1 {
+            for (inverter_id, value) in energy.iter() {
+                let changed = last_value.and_then(|last| last.get(inverter_id)) != Some(value);
+                let (inverter_id, (day, value)) = (Some(*inverter_id), *value);
+                self.sinks
+                    .publish_changed(&Self::key("energy", inverter_id), changed, move |sink| {
+                        sink.publish_energy(inverter_id, day, value)
+                    })
+                    .await
+                    .ok();
+            }
+        }
+        if let Some((day, total)) = Self::total_energy(&energy) {
+            let changed = last_value.and_then(Self::total_energy) != Some((day, total));
+            self.sinks
+                .publish_changed("energy", changed, move |sink| {
+                    sink.publish_energy(None, day, total)
+                })
+                .await
+                .ok();
         }
-        self.set_solar_energy(value).await?;
-        Ok(Some(value))
+        Ok(Some(energy))
     }
 
-    /// Synchronizes the SolarLog device status with Home Assistant.
+    /// Synchronizes the solar energy produced since installation by all inverters with the sinks.
+    /// The total is taken from the snapshot shared with the power and status syncs.
+    /// A failing sink is logged and does not fail the sync, it receives the total again on the next sync.
+    pub async fn sync_solar_energy_total(
+        &self,
+        last_total: Option<i64>,
+    ) -> Result<Option<i64>, anyhow::Error> {
+        let total = self.read_snapshot().await?.energy_total;
+        let changed = last_total != Some(total);
+        self.sinks
+            .publish_changed("energy_total", changed, move |sink| {
+                sink.publish_energy_total(total)
+            })
+            .await
+            .ok();
+        Ok(Some(total))
+    }
+
+    /// Synchronizes the SolarLog device status with the sinks.
+    /// The status is taken from the snapshot shared with the power and energy syncs.
+    /// The site status is the status of the first inverter, the status of each inverter is only published when there are several.
+    /// The coarse state of the site is published along with its status when it changes.
+    /// Once the status is published, the status events are fired for each inverter whose status changed since the
+    /// events were last fired, or since the last status before any event. A resync does not fire them again.
+    /// A failing sink is logged and does not fail the sync, it receives the status again on the next sync.
     pub async fn sync_solar_status(
         &self,
-        last_status: Option<&solarlog::InverterStatus>,
-    ) -> Result<Option<solarlog::InverterStatus>, anyhow::Error> {
-        let status = self.solarlog.get_status().await?;
-        if last_status == Some(&status) {
+        last_status: Option<&InverterValues<Status>>,
+    ) -> Result<Option<InverterValues<Status>>, anyhow::Error> {
+        let status = self.read_snapshot().await?.status.clone();
+        if last_status == Some(&status) && !self.sinks.has_failed() {
             return Ok(Some(status));
         }
-        let status_str = status.to_string();
-        self.homeassistant.set_solar_status(&status_str).await?;
+        if status.len() > 1 {
+            for (inverter_id, value) in status.iter() {
+                let changed = last_status.and_then(|last| last.get(inverter_id)) != Some(value);
+                let inverter_id = Some(*inverter_id);
+                self.sinks
+                    .publish_changed(&Self::key("status", inverter_id), changed, move |sink| {
+                        sink.publish_status(inverter_id, value)
+                    })
+                    .await
+                    .ok();
+            }
+        }
+        if let Some(site_status) = status.values().next() {
+            let changed = last_status.and_then(|last| last.values().next()) != Some(site_status);
+            self.sinks
+                .publish_changed("status", changed, move |sink| {
+                    sink.publish_status(None, site_status)
+                })
+                .await
+                .ok();
+            let last_state = last_status
+                .and_then(|last| last.values().next())
+                .map(|last| last.category);
+            let changed = last_state != Some(site_status.category);
+            self.sinks
+                .publish_changed("state", changed, move |sink| {
+                    sink.publish_state(site_status)
+                })
+                .await
+                .ok();
+        }
+        let changes: Vec<_> = {
+            let mut fired_status = self
+                .fired_status
+                .lock()
+                .expect("poisoned fired status lock");
+            status
+                .iter()
+                .filter_map(|(inverter_id, value)| {
+                    let last = fired_status
+                        .insert(*inverter_id, value.clone())
+                        .or_else(|| last_status.and_then(|last| last.get(inverter_id)).cloned());
+                    last.filter(|last| last != value)
+                        .map(|last| (*inverter_id, last, value))
+                })
+                .collect()
+        };
+        for (inverter_id, last, value) in changes {
+            self.fire_status_events(inverter_id, &last, value).await;
+        }
         Ok(Some(status))
     }
 
-    async fn set_solar_energy(&self, value: (NaiveDate, i64)) -> Result<(), homeassistant::Error> {
-        let day_midnight = Self::day_midnight(&value.0);
-        self.homeassistant
-            .set_solar_energy(value.1, &day_midnight)
-            .await
+    /// Creates a persistent notification for each inverter shut down longer than the grace period,
+    /// and dismisses it once the inverter is no longer shut down. Disabled without grace period.
+    /// The notification shows the status, the time the shutdown started and the last power read.
+    pub async fn notify_shutdowns(
+        &self,
+        status: &InverterValues<Status>,
+    ) -> Result<(), anyhow::Error> {
+        let Some(grace) = self.shutdown_notification_after else {
+            return Ok(());
+        };
+        let actions: Vec<_> = {
+            let mut shutdowns = self.shutdowns.lock().expect("poisoned shutdowns lock");
+            status
+                .iter()
+                .filter_map(|(inverter_id, value)| {
+                    let tracker = shutdowns
+                        .entry(*inverter_id)
+                        .or_insert_with(|| ShutdownTracker::new(grace));
+                    tracker
+                        .update(value)
+                        .map(|action| (*inverter_id, value, action))
+                })
+                .collect()
+        };
+        for (inverter_id, value, action) in actions {
+            let notification_id = format!("grelsolar_shutdown_inverter_{inverter_id}");
+            match &action {
+                ShutdownAction::Create { since } => {
+                    let power = self
+                        .latest_power
+                        .lock()
+                        .expect("poisoned power lock")
+                        .get(&inverter_id)
+                        .map_or_else(|| "unknown".to_string(), |power| format!("{power} W"));
+                    let data = json!({
+                        "notification_id": notification_id,
+                        "title": format!("Solar inverter {inverter_id} shut down"),
+                        "message": format!(
+                            "Inverter {inverter_id} is shut down since {}: {value}. Last power: {power}.",
+                            since.format("%Y-%m-%d %H:%M")
+                        ),
+                    });
+                    log::warn!("Inverter {inverter_id} is shut down since {since}: {value}");
+                    self.homeassistant
+                        .call_service("persistent_notification", "create", data)
+                        .await?;
+                }
+                ShutdownAction::Dismiss => {
+                    log::info!("Inverter {inverter_id} is no longer shut down: {value}");
+                    let data = json!({ "notification_id": notification_id });
+                    self.homeassistant
+                        .call_service("persistent_notification", "dismiss", data)
+                        .await?;
+                }
+            }
+            if let Some(tracker) = self
+                .shutdowns
+                .lock()
+                .expect("poisoned shutdowns lock")
+                .get_mut(&inverter_id)
+            {
+                tracker.done(&action);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fires the status changed event, and the fault event when the inverter enters a fault.
+    /// Errors are logged, the status is published anyway.
+    async fn fire_status_events(&self, inverter_id: u8, old: &Status, new: &Status) {
+        let data = json!({
+            "inverter_id": inverter_id,
+            "old_status": old.text,
+            "new_status": new.text,
+            "old_category": old.category.to_string(),
+            "category": new.category.to_string(),
+        });
+        let mut events = vec![STATUS_CHANGED_EVENT];
+        if new.fault && !old.fault {
+            log::warn!("Inverter {inverter_id} is shut down by a fault: {new}");
+            events.push(FAULT_EVENT);
+        }
+        for event in events {
+            if let Err(e) = self.homeassistant.fire_event(event, data.clone()).await {
+                log::error!("Error firing {event} event: {e}");
+            }
+        }
+    }
+
+    /// Checks the connection to Home Assistant and the source, and reads each synchronized sensor once.
+    /// The inverters are discovered first. Nothing is published to Home Assistant.
+    pub async fn preflight(&self) -> PreflightReport {
+        let mut report = PreflightReport::default();
+        report.record("Home Assistant API", self.homeassistant.check_api().await);
+        let name = self.source.name();
+        let connection = self.source.connect().await;
+        report.record(
+            &format!("{name} connection"),
+            connection.map(|_| "connected"),
+        );
+        let inverters = self.discover_inverters().await;
+        report.record(
+            &format!("{name} inverters"),
+            inverters.map(|i| format!("{i:?}")),
+        );
+        let snapshot = self.read_snapshot().await.map_err(|e| e.to_string());
+        report.record(
+            &format!("{name} power"),
+            snapshot
+                .as_ref()
+                .map(|s| format!("{} W", Self::total_power(&s.power))),
+        );
+        report.record(
+            &format!("{name} energy"),
+            snapshot.as_ref().map(|s| {
+                let wh = Self::total_energy(&s.energy).map_or(0, |(_, wh)| wh);
+                format!("{wh} Wh")
+            }),
+        );
+        report.record(
+            &format!("{name} energy total"),
+            snapshot.as_ref().map(|s| format!("{} Wh", s.energy_total)),
+        );
+        report.record(
+            &format!("{name} status"),
+            snapshot.as_ref().map(|s| {
+                s.status
+                    .values()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }),
+        );
+        if self.sync_consumption_interval.is_some() {
+            let consumption = self.solarlog.get_consumption().await;
+            report.record(
+                "SolarLog consumption",
+                consumption.map(|c| format!("{} W", c.power)),
+            );
+        }
+        if self.sync_dc_strings_interval.is_some() {
+            let dc_strings = self.read_dc_strings().await;
+            report.record(
+                "SolarLog DC strings",
+                dc_strings.map(|d| format!("{} strings", d.values().map(Vec::len).sum::<usize>())),
+            );
+        }
+        report
     }
 
-    pub fn day_midnight(day: &NaiveDate) -> DateTime<chrono::Local> {
-        day.and_hms_opt(0, 0, 0)
-            .expect("invalid time")
+    /// Reads the power, energy and status of each inverter from the source.
+    /// A snapshot younger than half the shortest sync interval is reused, so the tasks polling at the same time
+    /// share a single read instead of each sending its own request.
+    async fn read_snapshot(&self) -> Result<Arc<Snapshot>, SourceError> {
+        let _reading = self.snapshot_read.lock().await;
+        let max_age = self
+            .sync_power_interval
+            .min(self.sync_energy_interval)
+            .min(self.sync_status_interval)
+            / 2;
+        if let Some((read_at, snapshot)) = self
+            .snapshot
+            .lock()
+            .expect("poisoned snapshot lock")
+            .as_ref()
+        {
+            if read_at.elapsed() < max_age {
+                return Ok(Arc::clone(snapshot));
+            }
+        }
+        let inverters = self.inverters().await;
+        let read_at = Instant::now();
+        let snapshot = Arc::new(
+            self.source
+                .read_snapshot(&inverters)
+                .await
+                .map_err(SourceError)?,
+        );
+        *self.snapshot.lock().expect("poisoned snapshot lock") =
+            Some((read_at, Arc::clone(&snapshot)));
+        Ok(snapshot)
+    }
+
+    /// Forgets the last snapshot, so the next sync reads the source again.
+    fn clear_snapshot(&self) {
+        *self.snapshot.lock().expect("poisoned snapshot lock") = None;
+    }
+
+    /// Reads the DC measurements of each string of each inverter.
+    async fn read_dc_strings(&self) -> solarlog::Result<InverterValues<Vec<solarlog::DcString>>> {
+        let inverters = self.inverters().await;
+        let snapshot = self
+            .solarlog
+            .query()
+            .inverters(&inverters)
+            .dc_strings()
+            .send()
+            .await?;
+        Ok(snapshot.dc_strings)
+    }
+
+    /// Returns the key of a value published to the sinks, such as `power` or `power_inverter_1`.
+    fn key(name: &str, inverter_id: Option<u8>) -> String {
+        match inverter_id {
+            Some(inverter_id) => format!("{name}_inverter_{inverter_id}"),
+            None => name.to_string(),
+        }
+    }
+
+    /// Returns the total power of all inverters.
+    fn total_power(power: &InverterValues<i64>) -> i64 {
+        power.values().sum()
+    }
+
+    /// Returns the total energy of all inverters for the most recent day.
+    fn total_energy(energy: &InverterValues<(NaiveDate, i64)>) -> Option<(NaiveDate, i64)> {
+        let day = energy.values().map(|(day, _)| *day).max()?;
+        let total = energy
+            .values()
+            .filter(|(other_day, _)| *other_day == day)
+            .map(|(_, wh)| wh)
+            .sum();
+        Some((day, total))
+    }
+
+    /// Returns the local midnight starting the given day, the earliest one if ambiguous.
+    /// Returns `None` if the daylight saving time change skips midnight on that day.
+    pub fn day_midnight(day: &NaiveDate) -> Option<DateTime<chrono::Local>> {
+        day.and_hms_opt(0, 0, 0)?
             .and_local_timezone(chrono::Local)
-            .single()
-            .expect("ambiguous timezone")
+            .earliest()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::SolarBridgeBackgroundService;
+    use super::{InverterValues, SolarBridgeBackgroundService};
     use chrono::{Datelike, NaiveDate, Timelike};
 
+    #[test]
+    fn test_total_power() {
+        let power = InverterValues::from([(0, 1200), (1, 800), (2, -5)]);
+        assert_eq!(SolarBridgeBackgroundService::total_power(&power), 1995);
+        assert_eq!(
+            SolarBridgeBackgroundService::total_power(&InverterValues::new()),
+            0
+        );
+    }
+
+    #[test]
+    fn test_total_energy() {
+        let yesterday = NaiveDate::from_ymd_opt(2025, 6, 24).unwrap();
+        let today = NaiveDate::from_ymd_opt(2025, 6, 25).unwrap();
+        let energy =
+            InverterValues::from([(0, (today, 510)), (1, (today, 260)), (2, (yesterday, 9000))]);
+        assert_eq!(
+            SolarBridgeBackgroundService::total_energy(&energy),
+            Some((today, 770))
+        );
+        assert_eq!(
+            SolarBridgeBackgroundService::total_energy(&InverterValues::new()),
+            None
+        );
+    }
+
     #[test]
     fn test_day_midnight() {
         let static_date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
-        let midnight = SolarBridgeBackgroundService::day_midnight(&static_date);
+        let midnight = SolarBridgeBackgroundService::day_midnight(&static_date).unwrap();
 
         assert_eq!(midnight.year(), 2024);
         assert_eq!(midnight.month(), 6);
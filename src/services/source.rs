@@ -0,0 +1,252 @@
+//! Data sources.
+//! The power, energy and status are read from a source, Solar-Log being the first one.
+//! The status of each source is mapped onto the source-neutral `Status`, so the sinks do not depend on the source.
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use std::fmt;
+use std::sync::Arc;
+use strum_macros::{Display, EnumIter, EnumString};
+use thiserror::Error;
+
+use super::solarbridge::InverterValues;
+use crate::integration::solarlog::{self, InverterStatus};
+
+/// Input of the synchronized values.
+/// The values are keyed by inverter ID, a source without inverters reports the whole site as inverter 0.
+#[async_trait]
+pub trait Source: Send + Sync {
+    /// Returns the name of the source, used in the logs and the startup check.
+    fn name(&self) -> &str;
+
+    /// Connects to the source, such as logging in.
+    /// No operation is performed if already connected.
+    async fn connect(&self) -> anyhow::Result<()>;
+
+    /// Returns the IDs of the inverters connected to the source.
+    async fn discover_inverters(&self) -> anyhow::Result<Vec<u8>>;
+
+    /// Reads the power, energy and status of the given inverters at once.
+    async fn read_snapshot(&self, inverters: &[u8]) -> anyhow::Result<Snapshot>;
+}
+
+/// Values read from a source at the same time, shared by the power, energy and status syncs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Current power of each inverter in W.
+    pub power: InverterValues<i64>,
+    /// Energy produced on the last day by each inverter in Wh.
+    pub energy: InverterValues<(NaiveDate, i64)>,
+    /// Energy produced since installation by all inverters in Wh.
+    pub energy_total: i64,
+    /// Status of each inverter.
+    pub status: InverterValues<Status>,
+}
+
+/// Error reading a value from the source.
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct SourceError(pub anyhow::Error);
+
+/// Coarse category of a status, common to all sources.
+#[derive(Debug, PartialEq, Eq, EnumString, Display, EnumIter, Clone, Copy)]
+#[strum(serialize_all = "snake_case")]
+pub enum StatusCategory {
+    /// Not producing, waiting for irradiation or the grid.
+    Idle,
+    /// Connected to the grid and producing power.
+    Producing,
+    /// Stopped by a fault, a command or a limit.
+    Shutdown,
+    /// Running a self-check or an inspection.
+    Check,
+    /// Status not known.
+    Unknown,
+}
+
+/// Status of an inverter, independent of the source.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Status {
+    /// Status as reported by the source.
+    pub text: String,
+    /// Coarse category of the status.
+    pub category: StatusCategory,
+    /// `true` if the inverter is shut down by a fault, not by a command, a limit or a switch.
+    pub fault: bool,
+}
+
+impl Status {
+    /// Returns `true` if the inverter is shut down.
+    pub fn is_shutdown(&self) -> bool {
+        self.category == StatusCategory::Shutdown
+    }
+
+    /// Returns `true` if the inverter is producing power.
+    pub fn is_producing(&self) -> bool {
+        self.category == StatusCategory::Producing
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Starting is considered idle and grid dispatch is considered producing.
+impl From<&InverterStatus> for StatusCategory {
+    fn from(status: &InverterStatus) -> Self {
+        match status {
+            InverterStatus::Unknown(_) => StatusCategory::Unknown,
+            status if status.is_idle() => StatusCategory::Idle,
+            status if status.is_on_grid() => StatusCategory::Producing,
+            status if status.is_shutdown() => StatusCategory::Shutdown,
+            InverterStatus::Starting => StatusCategory::Idle,
+            InverterStatus::GridDispatchCosPhiPCurve | InverterStatus::GridDispatchQuCurve => {
+                StatusCategory::Producing
+            }
+            _ => StatusCategory::Check,
+        }
+    }
+}
+
+impl From<InverterStatus> for Status {
+    fn from(status: InverterStatus) -> Self {
+        Status {
+            category: StatusCategory::from(&status),
+            fault: status.is_fault(),
+            text: status.to_string(),
+        }
+    }
+}
+
+/// Solar-Log source, reading the power and energy from the given data source.
+pub struct SolarLogSource {
+    client: Arc<solarlog::Client>,
+    data_source: solarlog::DataSource,
+}
+
+impl SolarLogSource {
+    /// Creates a new instance of `SolarLogSource`.
+    pub fn new(client: Arc<solarlog::Client>, data_source: solarlog::DataSource) -> Self {
+        SolarLogSource {
+            client,
+            data_source,
+        }
+    }
+
+    /// Maps the status of each inverter onto the source-neutral status.
+    fn status(status: InverterValues<InverterStatus>) -> InverterValues<Status> {
+        status
+            .into_iter()
+            .map(|(inverter_id, status)| (inverter_id, status.into()))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Source for SolarLogSource {
+    fn name(&self) -> &str {
+        "SolarLog"
+    }
+
+    async fn connect(&self) -> anyhow::Result<()> {
+        Ok(self.client.login().await?)
+    }
+
+    async fn discover_inverters(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.client.get_inverters().await?)
+    }
+
+    /// All values are read in a single request with the index data source.
+    /// Only the site total is read with the Open JSON data source, the status is always read per inverter.
+    async fn read_snapshot(&self, inverters: &[u8]) -> anyhow::Result<Snapshot> {
+        match self.data_source {
+            solarlog::DataSource::Index => {
+                let snapshot = self
+                    .client
+                    .query()
+                    .inverters(inverters)
+                    .current_power()
+                    .energy_of_last_day()
+                    .energy_total()
+                    .status()
+                    .send()
+                    .await?;
+                Ok(Snapshot {
+                    power: snapshot.current_power,
+                    energy: snapshot.energy_of_last_day,
+                    energy_total: snapshot.energy_total.values().sum(),
+                    status: Self::status(snapshot.status),
+                })
+            }
+            solarlog::DataSource::OpenJson => {
+                let live_data = self.client.get_live_data().await?;
+                let snapshot = self
+                    .client
+                    .query()
+                    .inverters(inverters)
+                    .status()
+                    .send()
+                    .await?;
+                Ok(Snapshot {
+                    power: InverterValues::from([(0, live_data.power_ac)]),
+                    energy: InverterValues::from([(
+                        0,
+                        (live_data.last_update.date(), live_data.yield_day),
+                    )]),
+                    energy_total: live_data.yield_total,
+                    status: Self::status(snapshot.status),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn test_status_from_inverter_status() {
+        let status = Status::from(InverterStatus::ShutdownFault);
+
+        assert_eq!(status.text, "Shutdown Fault");
+        assert_eq!(status.category, StatusCategory::Shutdown);
+        assert!(status.fault);
+        assert!(status.is_shutdown());
+        assert!(!status.is_producing());
+    }
+
+    #[test]
+    fn test_status_from_unknown_inverter_status() {
+        let status = Status::from(InverterStatus::Unknown("Standby".to_string()));
+
+        assert_eq!(status.to_string(), "Standby");
+        assert_eq!(status.category, StatusCategory::Unknown);
+        assert!(!status.fault);
+    }
+
+    #[rstest]
+    #[case(InverterStatus::IdleNoIrradiation, StatusCategory::Idle)]
+    #[case(InverterStatus::Starting, StatusCategory::Idle)]
+    #[case(InverterStatus::OnGrid, StatusCategory::Producing)]
+    #[case(InverterStatus::OnGridPowerLimit, StatusCategory::Producing)]
+    #[case(InverterStatus::GridDispatchQuCurve, StatusCategory::Producing)]
+    #[case(InverterStatus::ShutdownFault, StatusCategory::Shutdown)]
+    #[case(InverterStatus::SpotCheck, StatusCategory::Check)]
+    #[case(InverterStatus::IvScanning, StatusCategory::Check)]
+    #[case(InverterStatus::Unknown("Standby".to_string()), StatusCategory::Unknown)]
+    fn test_status_category_from_inverter_status(
+        #[case] status: InverterStatus,
+        #[case] expected: StatusCategory,
+    ) {
+        assert_eq!(StatusCategory::from(&status), expected);
+    }
+
+    #[test]
+    fn test_status_category_display() {
+        assert_eq!(StatusCategory::Producing.to_string(), "producing");
+        assert_eq!(StatusCategory::Unknown.to_string(), "unknown");
+    }
+}
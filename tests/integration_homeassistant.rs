@@ -1,11 +1,16 @@
 //! Integration tests for the Home Assistant client.
-use crate::mockserver_homeassistant::HomeAssistantMockServer;
+use crate::mockserver_homeassistant::{HomeAssistantMockServer, HomeAssistantWebSocketMockServer};
+use crate::mockserver_mqtt::MqttMockBroker;
 use chrono::TimeZone;
-use grelsolar::integration::homeassistant::{Client, Error};
+use grelsolar::integration::homeassistant::{
+    Client, EntityOptions, Error, MqttClient, SensorGroup,
+};
 use rstest::fixture;
 use rstest::*;
+use serde_json::json;
 
 mod mockserver_homeassistant;
+mod mockserver_mqtt;
 
 #[fixture]
 /// Combined fixture yielding a client and its HomeAssistantMockServer
@@ -21,6 +26,250 @@ async fn client_server() -> (Client, HomeAssistantMockServer) {
     (client, server)
 }
 
+#[fixture]
+/// Combined fixture yielding a client and its HomeAssistantWebSocketMockServer
+async fn client_websocket_server() -> (Client, HomeAssistantWebSocketMockServer) {
+    let server = HomeAssistantWebSocketMockServer::start().await;
+    let client = Client::new(server.url(), server.token());
+    (client, server)
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_get_states(
+    #[future] client_websocket_server: (Client, HomeAssistantWebSocketMockServer),
+) {
+    let (client, server) = client_websocket_server.await;
+    server
+        .set_states(vec![json!({
+            "entity_id": "sensor.solar_power",
+            "state": "1234",
+            "attributes": {"unit_of_measurement": "W"},
+            "last_changed": "2025-06-25T10:00:00+00:00",
+        })])
+        .await;
+
+    let states = client.get_states().await.expect("failed to get states");
+
+    assert_eq!(states.len(), 1);
+    assert_eq!(states[0].entity_id, "sensor.solar_power");
+    assert_eq!(states[0].state, "1234");
+    assert_eq!(states[0].attributes["unit_of_measurement"], "W");
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_call_service(
+    #[future] client_websocket_server: (Client, HomeAssistantWebSocketMockServer),
+) {
+    let (client, server) = client_websocket_server.await;
+
+    let result = client
+        .call_service(
+            "persistent_notification",
+            "create",
+            json!({"message": "Inverter shutdown"}),
+        )
+        .await;
+
+    assert!(result.is_ok());
+    let commands = server.commands().await;
+    assert_eq!(commands[0]["type"], "call_service");
+    assert_eq!(commands[0]["domain"], "persistent_notification");
+    assert_eq!(commands[0]["service"], "create");
+    assert_eq!(
+        commands[0]["service_data"],
+        json!({"message": "Inverter shutdown"})
+    );
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_subscribe_events(
+    #[future] client_websocket_server: (Client, HomeAssistantWebSocketMockServer),
+) {
+    let (client, server) = client_websocket_server.await;
+
+    let mut subscription = client
+        .subscribe_events(Some("homeassistant_started"))
+        .await
+        .expect("failed to subscribe");
+    server.wait_for_subscriptions(1).await;
+    server.fire_event("state_changed", json!({})).await;
+    server.fire_event("homeassistant_started", json!({})).await;
+
+    let event = subscription.next().await.expect("no event received");
+    assert_eq!(event["event_type"], "homeassistant_started");
+    server.disconnect_all().await;
+    assert!(subscription.next().await.is_none());
+}
+
+#[fixture]
+/// Combined fixture yielding a client publishing over MQTT and its broker
+async fn client_broker() -> (Client, MqttMockBroker) {
+    let broker = MqttMockBroker::start().await;
+    let mqtt = MqttClient::new(
+        broker.url(),
+        None,
+        None,
+        "homeassistant".to_string(),
+        "grelsolar".to_string(),
+    );
+    let client = Client::new(
+        reqwest::Url::parse("http://localhost:8123").unwrap(),
+        "test_token".to_string(),
+    )
+    .with_mqtt(mqtt);
+    (client, broker)
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_mqtt_set_solar_current_power(
+    #[future] client_broker: (Client, MqttMockBroker),
+) {
+    let (client, broker) = client_broker.await;
+
+    let result = client.set_solar_current_power(1234).await;
+
+    assert!(result.is_ok());
+    let config = broker
+        .wait_for("homeassistant/sensor/grelsolar/solar_power/config")
+        .await;
+    assert!(config.retain);
+    assert_eq!(
+        config.json(),
+        json!({
+            "name": "Solar Power",
+            "unique_id": "grelsolar_solar_power",
+            "object_id": "solar_power",
+            "state_topic": "grelsolar/solar_power/state",
+            "availability": [
+                {"topic": "grelsolar/availability"},
+                {"topic": "grelsolar/solar_power/availability"},
+            ],
+            "availability_mode": "all",
+            "unit_of_measurement": "W",
+            "device_class": "power",
+            "state_class": "measurement",
+            "device": {
+                "identifiers": ["grelsolar"],
+                "name": "Solar-Log",
+                "manufacturer": "Solare Datensysteme",
+                "model": "Solar-Log",
+            },
+        })
+    );
+    let state = broker.wait_for("grelsolar/solar_power/state").await;
+    assert_eq!(state.payload, "1234");
+    assert!(state.retain);
+    let availability = broker.wait_for("grelsolar/availability").await;
+    assert_eq!(availability.payload, "online");
+    let availability = broker.wait_for("grelsolar/solar_power/availability").await;
+    assert_eq!(availability.payload, "online");
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_mqtt_set_state_beyond_request_queue(
+    #[future] client_broker: (Client, MqttMockBroker),
+) {
+    let (client, broker) = client_broker.await;
+
+    // Each first state is published with its config and availability, more than the queue holds
+    for inverter_id in 0..30 {
+        client
+            .set_inverter_current_power(inverter_id, 1000)
+            .await
+            .unwrap();
+    }
+
+    broker
+        .wait_for("grelsolar/solar_power_inverter_29/state")
+        .await;
+    let states = broker
+        .messages()
+        .await
+        .into_iter()
+        .filter(|message| message.topic.ends_with("/state"))
+        .count();
+    assert_eq!(states, 30);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_mqtt_set_unavailable(#[future] client_broker: (Client, MqttMockBroker)) {
+    let (client, broker) = client_broker.await;
+    client.set_solar_current_power(1234).await.unwrap();
+
+    client.set_unavailable(SensorGroup::Power).await.unwrap();
+    client.set_solar_current_power(1300).await.unwrap();
+
+    let availability = broker
+        .wait_for_count("grelsolar/solar_power/availability", 3)
+        .await;
+    let payloads: Vec<_> = availability.iter().map(|m| m.payload.as_str()).collect();
+    assert_eq!(payloads, vec!["online", "offline", "online"]);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_mqtt_set_solar_energy(#[future] client_broker: (Client, MqttMockBroker)) {
+    let (client, broker) = client_broker.await;
+    let last_reset = chrono::Utc.with_ymd_and_hms(2025, 6, 23, 0, 0, 0).unwrap();
+
+    client.set_solar_energy(1280, &last_reset).await.unwrap();
+    client.set_solar_energy(1500, &last_reset).await.unwrap();
+
+    let states = broker
+        .wait_for_count("grelsolar/solar_energy/state", 2)
+        .await;
+    assert_eq!(states[0].payload, "1.28");
+    assert_eq!(states[1].payload, "1.5");
+    let attributes = broker.wait_for("grelsolar/solar_energy/attributes").await;
+    assert_eq!(
+        attributes.json(),
+        json!({"last_reset": "2025-06-23T00:00:00+00:00"})
+    );
+    let configs = broker
+        .wait_for_count("homeassistant/sensor/grelsolar/solar_energy/config", 1)
+        .await;
+    assert_eq!(configs.len(), 1);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_set_solar_current_power_with_entity_options(
+    #[future] client_server: (Client, HomeAssistantMockServer),
+) {
+    let (client, server) = client_server.await;
+    let client = client.with_entity_options(EntityOptions {
+        prefix: "roof".to_string(),
+        names: [("power".to_string(), "Roof Power".to_string())].into(),
+        icons: [("power".to_string(), "mdi:solar-power".to_string())].into(),
+        attributes: [("installation".to_string(), json!("roof"))].into(),
+    });
+    let mock = server
+        .mock_set_state_with_attributes(
+            "sensor.roof_power",
+            "1234",
+            json!({
+                "unit_of_measurement": "W",
+                "friendly_name": "Roof Power",
+                "device_class": "power",
+                "state_class": "measurement",
+                "icon": "mdi:solar-power",
+                "installation": "roof",
+            }),
+        )
+        .await;
+
+    let result = client.set_solar_current_power(1234).await;
+
+    mock.assert_async().await;
+    assert!(result.is_ok());
+}
+
 #[rstest]
 #[tokio::test]
 async fn test_client_set_solar_energy(#[future] client_server: (Client, HomeAssistantMockServer)) {
@@ -57,18 +306,200 @@ async fn test_client_set_solar_current_power(
 async fn test_client_set_solar_status(#[future] client_server: (Client, HomeAssistantMockServer)) {
     let (client, server) = client_server.await;
     let status = "On-grid";
+    let options = vec!["On-grid".to_string(), "Shutdown Fault".to_string()];
     let mock = server.mock_set_solar_status(status).await;
 
-    let result = client.set_solar_status(status).await;
+    let result = client.set_solar_status(status, &options).await;
 
     mock.assert_async().await;
     assert!(result.is_ok());
 }
 
+#[rstest]
+#[tokio::test]
+async fn test_client_check_api(#[future] client_server: (Client, HomeAssistantMockServer)) {
+    let (client, server) = client_server.await;
+    let mock = server.mock_get_api().await;
+
+    let result = client.check_api().await;
+
+    mock.assert_async().await;
+    assert_eq!(result.unwrap(), "API running.");
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_check_api_unauthorized(
+    #[future] client_server: (Client, HomeAssistantMockServer),
+) {
+    let (client, server) = client_server.await;
+    let mock = server.mock_get_api_unauthorized().await;
+
+    let result = client.check_api().await;
+
+    mock.assert_async().await;
+    assert!(matches!(
+        result,
+        Err(Error::RequestFailed(e)) if e.status() == Some(reqwest::StatusCode::UNAUTHORIZED)
+    ));
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_fire_event(#[future] client_server: (Client, HomeAssistantMockServer)) {
+    let (client, server) = client_server.await;
+    let data = json!({"inverter_id": 0, "new_status": "On-grid"});
+    let mock = server
+        .mock_fire_event("grelsolar_status_changed", data.clone())
+        .await;
+
+    let result = client.fire_event("grelsolar_status_changed", data).await;
+
+    mock.assert_async().await;
+    assert!(result.is_ok());
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_get_state(#[future] client_server: (Client, HomeAssistantMockServer)) {
+    let (client, server) = client_server.await;
+    let mock = server
+        .mock_get_state(
+            "sensor.solar_energy_total",
+            "24870.5",
+            json!({"unit_of_measurement": "kWh"}),
+        )
+        .await;
+
+    let state = client.get_state("energy_total").await.unwrap().unwrap();
+    let missing = client.get_state("power").await;
+
+    mock.assert_async().await;
+    assert_eq!(state.entity_id, "sensor.solar_energy_total");
+    assert_eq!(state.state, "24870.5");
+    assert_eq!(state.attributes["unit_of_measurement"], "kWh");
+    assert!(matches!(missing, Ok(None)));
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_reconcile_up_to_date(
+    #[future] client_server: (Client, HomeAssistantMockServer),
+) {
+    let (client, server) = client_server.await;
+    let get_mock = server
+        .mock_get_state(
+            "sensor.solar_power",
+            "1234",
+            json!({
+                "friendly_name": "Solar Power",
+                "unit_of_measurement": "W",
+                "device_class": "power",
+                "state_class": "measurement",
+            }),
+        )
+        .await;
+    let up_to_date_mock = server.mock_set_state("sensor.solar_power", "1234").await;
+    let changed_mock = server.mock_set_state("sensor.solar_power", "1300").await;
+
+    client.set_solar_current_power(1234).await.unwrap();
+    client.set_solar_current_power(1300).await.unwrap();
+    client.set_solar_current_power(1234).await.unwrap();
+
+    assert_eq!(
+        get_mock.hits_async().await,
+        1,
+        "an up-to-date state should be reconciled"
+    );
+    assert_eq!(up_to_date_mock.hits_async().await, 1);
+    changed_mock.assert_async().await;
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_load_states_up_to_date_then_unavailable(
+    #[future] client_server: (Client, HomeAssistantMockServer),
+) {
+    let (client, server) = client_server.await;
+    let get_states_mock = server
+        .mock_get_states(json!([
+            {
+                "entity_id": "sensor.solar_power",
+                "state": "1234",
+                "attributes": {
+                    "friendly_name": "Solar Power",
+                    "unit_of_measurement": "W",
+                    "device_class": "power",
+                    "state_class": "measurement",
+                },
+            },
+            {"entity_id": "light.kitchen", "state": "on"},
+        ]))
+        .await;
+    let get_mock = server
+        .mock_get_state("sensor.solar_power", "1234", json!({}))
+        .await;
+    let up_to_date_mock = server.mock_set_state("sensor.solar_power", "1234").await;
+    let unavailable_mock = server
+        .mock_set_state("sensor.solar_power", "unavailable")
+        .await;
+
+    let count = client.load_states().await.unwrap();
+    client.set_solar_current_power(1234).await.unwrap();
+    client.set_unavailable(SensorGroup::Power).await.unwrap();
+
+    assert_eq!(count, 1, "only the states of the sensors should be kept");
+    get_states_mock.assert_async().await;
+    assert_eq!(get_mock.hits_async().await, 0);
+    assert_eq!(up_to_date_mock.hits_async().await, 0);
+    unavailable_mock.assert_async().await;
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_reconcile_energy_backwards(
+    #[future] client_server: (Client, HomeAssistantMockServer),
+) {
+    let (client, server) = client_server.await;
+    let get_mock = server
+        .mock_get_state(
+            "sensor.solar_energy_total",
+            "24870.5",
+            json!({
+                "unit_of_measurement": "kWh",
+                "device_class": "energy",
+                "state_class": "total_increasing",
+            }),
+        )
+        .await;
+    let backwards_mock = server
+        .mock_set_state("sensor.solar_energy_total", "1000")
+        .await;
+    let forwards_mock = server
+        .mock_set_state("sensor.solar_energy_total", "24871")
+        .await;
+
+    let still_backwards_mock = server
+        .mock_set_state("sensor.solar_energy_total", "24000")
+        .await;
+
+    client.set_solar_energy_total(1_000_000).await.unwrap();
+    client.set_solar_energy_total(24_000_000).await.unwrap();
+    client.set_solar_energy_total(24_871_000).await.unwrap();
+
+    assert_eq!(get_mock.hits_async().await, 3);
+    assert_eq!(backwards_mock.hits_async().await, 0);
+    assert_eq!(still_backwards_mock.hits_async().await, 0);
+    forwards_mock.assert_async().await;
+}
+
 #[rstest]
 #[tokio::test]
 async fn test_client_with_server_error(#[future] client_server: (Client, HomeAssistantMockServer)) {
     let (client, server) = client_server.await;
+    let reconciled_mock = server.mock_set_state("sensor.solar_power", "1000").await;
+    client.set_solar_current_power(1000).await.unwrap();
+    reconciled_mock.delete_async().await;
     let mock = server.mock_error_solar_power().await;
 
     let result_call_1 = client.set_solar_current_power(1234).await;
@@ -84,3 +515,53 @@ async fn test_client_with_server_error(#[future] client_server: (Client, HomeAss
         "circuit breaker should reject the request due to repeated failures"
     );
 }
+
+#[tokio::test]
+async fn test_client_import_solar_energy_total_statistics() {
+    let server = HomeAssistantWebSocketMockServer::start().await;
+    let client = Client::new(server.url(), server.token());
+    let start = chrono::FixedOffset::east_opt(7200).unwrap();
+    let periods = [
+        (start.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap(), 21700),
+        (start.with_ymd_and_hms(2025, 6, 2, 0, 0, 0).unwrap(), 9550),
+    ];
+
+    let result = client
+        .import_solar_energy_total_statistics(&periods, 1031250)
+        .await;
+
+    assert!(result.is_ok());
+    assert_eq!(
+        server.commands().await,
+        vec![serde_json::json!({
+            "id": 1,
+            "type": "recorder/import_statistics",
+            "metadata": {
+                "has_mean": false,
+                "has_sum": true,
+                "name": "Solar Energy Total",
+                "source": "recorder",
+                "statistic_id": "sensor.solar_energy_total",
+                "unit_of_measurement": "kWh"
+            },
+            "stats": [
+                {"start": "2025-06-01T00:00:00+02:00", "state": 1021.7, "sum": 1021.7},
+                {"start": "2025-06-02T00:00:00+02:00", "state": 1031.25, "sum": 1031.25}
+            ]
+        })]
+    );
+}
+
+#[tokio::test]
+async fn test_client_import_statistics_authentication_failed() {
+    let server = HomeAssistantWebSocketMockServer::start().await;
+    let client = Client::new(server.url(), String::from("invalid_token"));
+    let start = chrono::Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+
+    let result = client
+        .import_solar_energy_total_statistics(&[(start, 21700)], 21700)
+        .await;
+
+    assert!(matches!(result, Err(Error::AuthenticationFailed(_))));
+    assert!(server.commands().await.is_empty());
+}
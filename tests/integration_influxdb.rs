@@ -0,0 +1,198 @@
+//! Integration tests for the InfluxDB client.
+use crate::mockserver_influxdb::InfluxDbMockServer;
+use chrono::{TimeZone, Utc};
+use grelsolar::integration::influxdb::{Client, Error};
+use rstest::fixture;
+use rstest::*;
+use std::sync::Arc;
+use tokio::time::{Duration, sleep};
+use tokio_util::sync::CancellationToken;
+
+mod mockserver_influxdb;
+
+#[fixture]
+/// Combined fixture yielding a client and its InfluxDbMockServer
+async fn client_server() -> (Client, InfluxDbMockServer) {
+    let server = InfluxDbMockServer::start().await;
+    let client = Client::new(server.url(), server.org(), server.bucket(), server.token());
+    (client, server)
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_write_power(#[future] client_server: (Client, InfluxDbMockServer)) {
+    let (client, server) = client_server.await;
+    let mock = server
+        .mock_write(&["solar,inverter=site power=1200i"])
+        .await;
+
+    let result = client.write_power(None, 1200).await;
+
+    mock.assert_async().await;
+    assert!(result.is_ok());
+    assert_eq!(client.pending(), 0);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_write_status(#[future] client_server: (Client, InfluxDbMockServer)) {
+    let (client, server) = client_server.await;
+    let mock = server
+        .mock_write(&[r#"solar,inverter=1 status="Shutdown Fault",category="shutdown",fault=true"#])
+        .await;
+
+    let result = client
+        .write_status(Some(1), "Shutdown Fault", "shutdown", true)
+        .await;
+
+    mock.assert_async().await;
+    assert!(result.is_ok());
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_write_batch(#[future] client_server: (Client, InfluxDbMockServer)) {
+    let (client, server) = client_server.await;
+    let client = client.with_batch(3, Duration::from_secs(3600));
+    let mock = server
+        .mock_write(&[
+            "solar,inverter=0 power=1200i",
+            "solar,inverter=1 power=800i",
+            "solar,inverter=site energy_total=24870500i",
+        ])
+        .await;
+
+    client.write_power(Some(0), 1200).await.unwrap();
+    client.write_power(Some(1), 800).await.unwrap();
+    assert_eq!(mock.hits_async().await, 0);
+    assert_eq!(client.pending(), 2);
+    client.write_energy_total(24870500).await.unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(client.pending(), 0);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_flush(#[future] client_server: (Client, InfluxDbMockServer)) {
+    let (client, server) = client_server.await;
+    let client = client.with_batch(100, Duration::from_secs(3600));
+    let day_start = Utc.with_ymd_and_hms(2025, 6, 25, 0, 0, 0).unwrap();
+    let mock = server
+        .mock_write_at("solar,inverter=site energy=5100i", day_start.timestamp())
+        .await;
+
+    client.write_energy(None, &day_start, 5100).await.unwrap();
+    assert_eq!(client.pending(), 1);
+    let result = client.flush().await;
+
+    mock.assert_async().await;
+    assert!(result.is_ok());
+    assert_eq!(client.pending(), 0);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_write_server_error_retried(
+    #[future] client_server: (Client, InfluxDbMockServer),
+) {
+    let (client, server) = client_server.await;
+    let mock = server.mock_write_error(503).await;
+
+    let result = client.write_power(None, 1200).await;
+
+    assert_eq!(mock.hits_async().await, 4);
+    assert_eq!(client.request_latencies().count, 4, "each retry is timed");
+    assert!(
+        result.is_ok(),
+        "failed writes are retried in the background"
+    );
+    assert_eq!(
+        client.pending(),
+        1,
+        "failed points are kept for the next flush"
+    );
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_write_client_error_not_retried(
+    #[future] client_server: (Client, InfluxDbMockServer),
+) {
+    let (client, server) = client_server.await;
+    let mock = server.mock_write_error(401).await;
+
+    let result = client.write_power(None, 1200).await;
+
+    assert_eq!(mock.hits_async().await, 1);
+    assert!(result.is_ok());
+    assert_eq!(client.pending(), 0, "rejected points are dropped");
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_circuit_breaker(#[future] client_server: (Client, InfluxDbMockServer)) {
+    let (client, server) = client_server.await;
+    let mock = server.mock_write_error(500).await;
+
+    let _ = client.write_power(None, 1200).await;
+    let _ = client.flush().await;
+    let result = client.flush().await;
+
+    assert_eq!(mock.hits_async().await, 5);
+    assert!(matches!(result, Err(Error::RequestRejected)));
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_run_flushes_batch(#[future] client_server: (Client, InfluxDbMockServer)) {
+    let (client, server) = client_server.await;
+    let client = Arc::new(client.with_batch(100, Duration::from_millis(50)));
+    let mock = server
+        .mock_write(&["solar,inverter=site power=1200i"])
+        .await;
+    let token = CancellationToken::new();
+    let task = tokio::spawn({
+        let client = Arc::clone(&client);
+        let token = token.clone();
+        async move { client.run(token).await }
+    });
+
+    client.write_power(None, 1200).await.unwrap();
+    assert_eq!(client.pending(), 1);
+    sleep(Duration::from_millis(200)).await;
+
+    mock.assert_async().await;
+    assert_eq!(client.pending(), 0);
+    token.cancel();
+    task.await.unwrap();
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_client_run_retries_failed_points(
+    #[future] client_server: (Client, InfluxDbMockServer),
+) {
+    let (client, server) = client_server.await;
+    let client = Arc::new(client.with_batch(1, Duration::from_millis(50)));
+    let error_mock = server.mock_write_error(503).await;
+
+    client.write_power(None, 1200).await.unwrap();
+    assert_eq!(client.pending(), 1);
+    error_mock.delete_async().await;
+    let mock = server
+        .mock_write(&["solar,inverter=site power=1200i"])
+        .await;
+    let token = CancellationToken::new();
+    let task = tokio::spawn({
+        let client = Arc::clone(&client);
+        let token = token.clone();
+        async move { client.run(token).await }
+    });
+    sleep(Duration::from_millis(200)).await;
+
+    mock.assert_async().await;
+    assert_eq!(client.pending(), 0);
+    token.cancel();
+    task.await.unwrap();
+}
@@ -0,0 +1,58 @@
+//! Integration tests for the Prometheus metrics exporter.
+use grelsolar::services::{Metrics, MetricsExporter, Sink};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Serves the given metrics on a free local port, returns the base URL.
+async fn serve(metrics: Arc<Metrics>, token: CancellationToken) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("cannot bind listener");
+    let address: SocketAddr = listener.local_addr().expect("no local address");
+    let exporter = MetricsExporter::new(address, metrics);
+    tokio::spawn(async move { exporter.serve(listener, token).await });
+    format!("http://{address}")
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint() {
+    let metrics = Arc::new(Metrics::default());
+    metrics.publish_power(None, 2000).await.unwrap();
+    metrics.record_sync("power", true, Duration::from_millis(30));
+    metrics.register_circuit_breaker("homeassistant", || false);
+    let token = CancellationToken::new();
+    let url = serve(metrics, token.clone()).await;
+
+    let response = reqwest::get(format!("{url}/metrics")).await.unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/plain; version=0.0.4; charset=utf-8"
+    );
+    let body = response.text().await.unwrap();
+    assert!(body.contains("grelsolar_power_watts{inverter=\"site\"} 2000\n"));
+    assert!(body.contains("grelsolar_syncs_total{task=\"power\",result=\"success\"} 1\n"));
+    assert!(body.contains("grelsolar_circuit_breaker_open{client=\"homeassistant\"} 0\n"));
+    token.cancel();
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_not_found() {
+    let token = CancellationToken::new();
+    let url = serve(Arc::new(Metrics::default()), token.clone()).await;
+
+    let not_found = reqwest::get(format!("{url}/health")).await.unwrap();
+    let not_allowed = reqwest::Client::new()
+        .post(format!("{url}/metrics"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(not_found.status(), 404);
+    assert_eq!(not_allowed.status(), 405);
+    token.cancel();
+}
@@ -1,7 +1,7 @@
 // Integration test for the server
 use envconfig::Envconfig;
 use grelsolar::core::config::Config;
-use grelsolar::server::server;
+use grelsolar::server::{ServerError, server};
 use tokio::time::{Duration, sleep, timeout};
 use tokio_util::sync::CancellationToken;
 
@@ -13,6 +13,7 @@ async fn test_start_and_stop() {
             ("SOLARLOG_PASSWORD", Some("pw")),
             ("HOMEASSISTANT_URL", Some("http://localhost:5678")),
             ("HOMEASSISTANT_TOKEN", Some("token")),
+            ("STARTUP_CHECK", Some("off")),
         ],
         async {
             // Start the app function in a background task
@@ -38,7 +39,38 @@ async fn test_start_and_stop() {
             // The task should have exited gracefully or been cancelled
             result
                 .expect("App task did not finish in time")
-                .expect("App task failed");
+                .expect("App task failed")
+                .expect("App failed");
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_startup_check_abort() {
+    temp_env::async_with_vars(
+        [
+            ("SOLARLOG_URL", Some("http://localhost:1234")),
+            ("SOLARLOG_PASSWORD", Some("pw")),
+            ("HOMEASSISTANT_URL", Some("http://localhost:5678")),
+            ("HOMEASSISTANT_TOKEN", Some("token")),
+            ("STARTUP_CHECK", Some("abort")),
+        ],
+        async {
+            let config = Config::init_from_env().expect("cannot load config");
+
+            // The server stops by itself since neither SolarLog nor Home Assistant is reachable
+            let result = timeout(
+                Duration::from_secs(10),
+                server(config, CancellationToken::new()),
+            )
+            .await
+            .expect("App did not stop in time");
+
+            assert!(matches!(
+                result,
+                Err(ServerError::StartupCheckFailed(report)) if !report.is_ok()
+            ));
         },
     )
     .await;
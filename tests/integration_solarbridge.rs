@@ -1,20 +1,58 @@
 //! Integration tests for the SolarBridgeBackgroundService.
-use crate::mockserver_homeassistant::HomeAssistantMockServer;
+use crate::mockserver_homeassistant::{HomeAssistantMockServer, HomeAssistantWebSocketMockServer};
+use crate::mockserver_influxdb::InfluxDbMockServer;
 use crate::mockserver_solarlog::SolarlogMockServer;
-use grelsolar::integration::homeassistant::Client as HomeAssistantClient;
-use grelsolar::integration::solarlog::{self, Client as SolarLogClient};
-use grelsolar::services::solarbridge::SolarBridgeBackgroundService;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use grelsolar::integration::homeassistant::{Client as HomeAssistantClient, SensorGroup};
+use grelsolar::integration::influxdb::Client as InfluxDbClient;
+use grelsolar::integration::solarlog::{self, Client as SolarLogClient, InverterStatus};
+use grelsolar::services::solarbridge::{InverterValues, SolarBridgeBackgroundService};
+use grelsolar::services::{Metrics, Sink, Snapshot, Source, Status, StatusCategory};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::time::Duration;
 use tokio_util::sync::CancellationToken;
 
 mod mockserver_homeassistant;
+mod mockserver_influxdb;
 mod mockserver_solarlog;
 
 async fn mock_setup() -> (
     SolarlogMockServer,
     HomeAssistantMockServer,
     SolarBridgeBackgroundService,
+) {
+    mock_setup_with_data_source(solarlog::DataSource::Index).await
+}
+
+async fn mock_setup_with_interval(
+    interval: Duration,
+) -> (
+    SolarlogMockServer,
+    HomeAssistantMockServer,
+    SolarBridgeBackgroundService,
+) {
+    mock_setup_with(solarlog::DataSource::Index, interval).await
+}
+
+async fn mock_setup_with_data_source(
+    data_source: solarlog::DataSource,
+) -> (
+    SolarlogMockServer,
+    HomeAssistantMockServer,
+    SolarBridgeBackgroundService,
+) {
+    mock_setup_with(data_source, Duration::from_micros(1)).await
+}
+
+async fn mock_setup_with(
+    data_source: solarlog::DataSource,
+    interval: Duration,
+) -> (
+    SolarlogMockServer,
+    HomeAssistantMockServer,
+    SolarBridgeBackgroundService,
 ) {
     let solarlog_mockserver = SolarlogMockServer::start().await;
     let homeassistant_mockserver = HomeAssistantMockServer::start().await;
@@ -38,18 +76,202 @@ async fn mock_setup() -> (
     let service = SolarBridgeBackgroundService::new(
         solarlog_client,
         homeassistant_client,
-        Duration::from_micros(1),
-        Duration::from_micros(1),
-        Duration::from_micros(1),
-    );
+        data_source,
+        interval,
+        interval,
+        interval,
+    )
+    .with_sync_consumption_interval(Some(interval));
+
+    (solarlog_mockserver, homeassistant_mockserver, service)
+}
 
+/// Same as `mock_setup` with the Home Assistant WebSocket stand-in instead of the REST mock server.
+async fn mock_setup_with_websocket() -> (
+    SolarlogMockServer,
+    HomeAssistantWebSocketMockServer,
+    SolarBridgeBackgroundService,
+) {
+    mock_setup_with_websocket_and_interval(Duration::from_micros(1)).await
+}
+
+async fn mock_setup_with_websocket_and_interval(
+    interval: Duration,
+) -> (
+    SolarlogMockServer,
+    HomeAssistantWebSocketMockServer,
+    SolarBridgeBackgroundService,
+) {
+    let solarlog_mockserver = SolarlogMockServer::start().await;
+    let homeassistant_mockserver = HomeAssistantWebSocketMockServer::start().await;
+    let solarlog_client = Arc::new(SolarLogClient::new(
+        solarlog_mockserver.url(),
+        solarlog_mockserver.password(),
+    ));
+    let homeassistant_client = Arc::new(HomeAssistantClient::new(
+        homeassistant_mockserver.url(),
+        homeassistant_mockserver.token(),
+    ));
+    solarlog_mockserver.mock_login_ok().await;
+    solarlog_client
+        .login()
+        .await
+        .expect("login failed in fixture");
+    let service = SolarBridgeBackgroundService::new(
+        solarlog_client,
+        homeassistant_client,
+        solarlog::DataSource::Index,
+        interval,
+        interval,
+        interval,
+    );
     (solarlog_mockserver, homeassistant_mockserver, service)
 }
 
+#[tokio::test]
+async fn test_notify_shutdowns() {
+    let (_solarlog_mockserver, homeassistant_mockserver, service) =
+        mock_setup_with_websocket().await;
+    let service = service.with_shutdown_notification_after(Some(Duration::ZERO));
+
+    let shutdown = InverterValues::from([(0, Status::from(InverterStatus::ShutdownFault))]);
+    service.notify_shutdowns(&shutdown).await.unwrap();
+    service.notify_shutdowns(&shutdown).await.unwrap();
+    let idle = InverterValues::from([(0, Status::from(InverterStatus::IdleInitializing))]);
+    service.notify_shutdowns(&idle).await.unwrap();
+    let on_grid = InverterValues::from([(0, Status::from(InverterStatus::OnGrid))]);
+    service.notify_shutdowns(&on_grid).await.unwrap();
+
+    let commands = homeassistant_mockserver.commands().await;
+    assert_eq!(commands.len(), 2, "{commands:?}");
+    assert_eq!(commands[0]["domain"], "persistent_notification");
+    assert_eq!(commands[0]["service"], "create");
+    let data = &commands[0]["service_data"];
+    assert_eq!(data["notification_id"], "grelsolar_shutdown_inverter_0");
+    assert_eq!(data["title"], "Solar inverter 0 shut down");
+    let message = data["message"].as_str().unwrap();
+    assert!(message.contains("Shutdown Fault"), "{message}");
+    assert!(message.contains("Last power: unknown"), "{message}");
+    assert_eq!(commands[1]["service"], "dismiss");
+    assert_eq!(
+        commands[1]["service_data"],
+        serde_json::json!({"notification_id": "grelsolar_shutdown_inverter_0"})
+    );
+}
+
+#[tokio::test]
+async fn test_notify_shutdowns_disabled() {
+    let (_solarlog_mockserver, homeassistant_mockserver, service) =
+        mock_setup_with_websocket().await;
+
+    let shutdown = InverterValues::from([(0, Status::from(InverterStatus::ShutdownFault))]);
+    service.notify_shutdowns(&shutdown).await.unwrap();
+
+    assert!(homeassistant_mockserver.commands().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_backfill_energy_history() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) =
+        mock_setup_with_websocket().await;
+    let (solarlog_mock, periods) = solarlog_mockserver.mock_energy_history().await;
+    let (snapshot_mock, _) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1200, "On-grid", 9550)])
+        .await;
+
+    let result = service.backfill_energy_history().await;
+
+    solarlog_mock.assert_async().await;
+    snapshot_mock.assert_async().await;
+    assert_eq!(result.unwrap(), periods.len());
+    let commands = homeassistant_mockserver.commands().await;
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0]["type"], "recorder/import_statistics");
+    let stats = commands[0]["stats"].as_array().unwrap();
+    assert_eq!(stats.len(), periods.len());
+    assert_eq!(
+        stats[0]["start"],
+        SolarBridgeBackgroundService::day_midnight(&periods[0].0)
+            .unwrap()
+            .to_rfc3339()
+    );
+    assert_eq!(stats[0]["sum"], 24229.25);
+    assert_eq!(stats[periods.len() - 1]["sum"], 24870.5);
+}
+
+#[tokio::test]
+async fn test_sync_intraday() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) =
+        mock_setup_with_websocket().await;
+    let (solarlog_mock, day) = solarlog_mockserver.mock_intraday_curve().await;
+
+    let result = service.sync_intraday(None).await;
+
+    solarlog_mock.assert_async().await;
+    let last_hour = day.and_hms_opt(11, 0, 0).unwrap();
+    assert_eq!(result.unwrap(), Some(last_hour));
+    let commands = homeassistant_mockserver.commands().await;
+    assert_eq!(commands.len(), 2);
+    assert_eq!(commands[0]["type"], "recorder/statistics_during_period");
+    assert_eq!(commands[0]["statistic_ids"][0], "sensor.solar_power");
+    assert_eq!(
+        commands[1]["metadata"]["statistic_id"],
+        "sensor.solar_power"
+    );
+    let stats = commands[1]["stats"].as_array().unwrap();
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[0]["mean"], 1500.0);
+    assert_eq!(stats[1]["max"], 3600.0);
+}
+
+#[tokio::test]
+async fn test_sync_intraday_keeps_compiled_hours() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) =
+        mock_setup_with_websocket().await;
+    let (solarlog_mock, day) = solarlog_mockserver.mock_intraday_curve().await;
+    let compiled = day
+        .and_hms_opt(10, 0, 0)
+        .unwrap()
+        .and_local_timezone(chrono::Local)
+        .unwrap();
+    homeassistant_mockserver
+        .set_statistics(serde_json::json!({"sensor.solar_power": [
+            {"start": compiled.timestamp_millis(), "mean": 1450.0}
+        ]}))
+        .await;
+
+    let result = service.sync_intraday(None).await;
+
+    solarlog_mock.assert_async().await;
+    assert_eq!(result.unwrap(), Some(day.and_hms_opt(11, 0, 0).unwrap()));
+    let commands = homeassistant_mockserver.commands().await;
+    assert_eq!(commands.len(), 2);
+    let stats = commands[1]["stats"].as_array().unwrap();
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0]["max"], 3600.0);
+}
+
+#[tokio::test]
+async fn test_sync_intraday_no_gap() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) =
+        mock_setup_with_websocket().await;
+    let (solarlog_mock, day) = solarlog_mockserver.mock_intraday_curve().await;
+    let last_hour = day.and_hms_opt(11, 0, 0).unwrap();
+
+    let result = service.sync_intraday(Some(last_hour)).await;
+
+    solarlog_mock.assert_async().await;
+    assert_eq!(result.unwrap(), Some(last_hour));
+    assert!(homeassistant_mockserver.commands().await.is_empty());
+}
+
 #[tokio::test]
 async fn test_sync_solar_power() {
     let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
-    let (solarlog_mock, expected) = solarlog_mockserver.mock_current_power().await;
+    let expected = 1234;
+    let (solarlog_mock, _day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, expected, "On-grid", 510)])
+        .await;
     let homeassistant_mock = homeassistant_mockserver
         .mock_set_solar_power(expected)
         .await;
@@ -58,70 +280,727 @@ async fn test_sync_solar_power() {
 
     solarlog_mock.assert_async().await;
     homeassistant_mock.assert_async().await;
-    assert_eq!(result.unwrap(), Some(expected));
+    assert_eq!(result.unwrap(), Some(InverterValues::from([(0, expected)])));
+}
+
+#[tokio::test]
+async fn test_sync_solar_power_reconciled() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let expected = 1234;
+    let (solarlog_mock, _day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, expected, "On-grid", 510)])
+        .await;
+    let get_mock = homeassistant_mockserver
+        .mock_get_state(
+            "sensor.solar_power",
+            &expected.to_string(),
+            serde_json::json!({
+                "friendly_name": "Solar Power",
+                "unit_of_measurement": "W",
+                "device_class": "power",
+                "state_class": "measurement",
+            }),
+        )
+        .await;
+    let homeassistant_mock = homeassistant_mockserver
+        .mock_set_solar_power(expected)
+        .await;
+
+    // Home Assistant already holds the power after a restart of the bridge
+    let result = service.sync_solar_power(None).await;
+
+    solarlog_mock.assert_async().await;
+    get_mock.assert_async().await;
+    assert_eq!(homeassistant_mock.hits_async().await, 0);
+    assert_eq!(result.unwrap(), Some(InverterValues::from([(0, expected)])));
+}
+
+/// Sink that is always down.
+struct FailingSink;
+
+#[async_trait]
+impl Sink for FailingSink {
+    fn name(&self) -> &str {
+        "failing"
+    }
+
+    async fn publish_power(&self, _: Option<u8>, _: i64) -> anyhow::Result<()> {
+        anyhow::bail!("sink down")
+    }
+
+    async fn publish_energy(&self, _: Option<u8>, _: NaiveDate, _: i64) -> anyhow::Result<()> {
+        anyhow::bail!("sink down")
+    }
+
+    async fn publish_energy_total(&self, _: i64) -> anyhow::Result<()> {
+        anyhow::bail!("sink down")
+    }
+
+    async fn publish_status(&self, _: Option<u8>, _: &Status) -> anyhow::Result<()> {
+        anyhow::bail!("sink down")
+    }
+
+    async fn publish_state(&self, _: &Status) -> anyhow::Result<()> {
+        anyhow::bail!("sink down")
+    }
+
+    async fn set_unavailable(&self, _: SensorGroup) -> anyhow::Result<()> {
+        anyhow::bail!("sink down")
+    }
+}
+
+#[tokio::test]
+async fn test_sync_solar_power_failing_sink() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let service = service.with_sink(Arc::new(FailingSink));
+    solarlog_mockserver.mock_inverters().await;
+    solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1200, "On-grid", 510), (1, 800, "On-grid", 260)])
+        .await;
+    let homeassistant_mock_0 = homeassistant_mockserver
+        .mock_set_inverter_power(0, 1200)
+        .await;
+    let homeassistant_mock_1 = homeassistant_mockserver
+        .mock_set_inverter_power(1, 800)
+        .await;
+    let homeassistant_total_mock = homeassistant_mockserver.mock_set_solar_power(2000).await;
+    service
+        .discover_inverters()
+        .await
+        .expect("cannot discover inverters");
+
+    let result = service.sync_solar_power(None).await;
+
+    homeassistant_mock_0.assert_async().await;
+    homeassistant_mock_1.assert_async().await;
+    homeassistant_total_mock.assert_async().await;
+    assert_eq!(
+        result.unwrap(),
+        Some(InverterValues::from([(0, 1200), (1, 800)])),
+        "a failing sink should not keep the power from advancing"
+    );
+}
+
+/// Sink recording the published energy totals, failing the first one.
+#[derive(Default)]
+struct FailingOnceSink {
+    energy_total: std::sync::Mutex<Vec<i64>>,
+}
+
+#[async_trait]
+impl Sink for FailingOnceSink {
+    fn name(&self) -> &str {
+        "failing_once"
+    }
+
+    async fn publish_power(&self, _: Option<u8>, _: i64) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn publish_energy(&self, _: Option<u8>, _: NaiveDate, _: i64) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn publish_energy_total(&self, energy_total: i64) -> anyhow::Result<()> {
+        let mut published = self.energy_total.lock().unwrap();
+        published.push(energy_total);
+        if published.len() == 1 {
+            anyhow::bail!("sink down");
+        }
+        Ok(())
+    }
+
+    async fn publish_status(&self, _: Option<u8>, _: &Status) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn publish_state(&self, _: &Status) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn set_unavailable(&self, _: SensorGroup) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_sync_solar_energy_total_retries_failed_sink() {
+    let (_solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let sink = Arc::new(FailingOnceSink::default());
+    let service = service
+        .with_source(Arc::new(SimulatedSource))
+        .with_sink(sink.clone());
+    let homeassistant_mock = homeassistant_mockserver
+        .mock_set_state("sensor.solar_energy_total", "1000")
+        .await;
+
+    let total = service.sync_solar_energy_total(None).await.unwrap();
+    let result = service.sync_solar_energy_total(total).await;
+    let result_again = service.sync_solar_energy_total(total).await;
+
+    assert_eq!(total, Some(1_000_000));
+    assert_eq!(result.unwrap(), total);
+    assert_eq!(result_again.unwrap(), total);
+    assert_eq!(
+        *sink.energy_total.lock().unwrap(),
+        vec![1_000_000, 1_000_000],
+        "the failed sink should receive the same total on the next poll"
+    );
+    assert_eq!(
+        homeassistant_mock.hits_async().await,
+        1,
+        "the other sinks should not receive the same total again"
+    );
+}
+
+#[tokio::test]
+async fn test_sync_solar_power_influxdb_sink() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let influxdb_mockserver = InfluxDbMockServer::start().await;
+    let influxdb_client = Arc::new(InfluxDbClient::new(
+        influxdb_mockserver.url(),
+        influxdb_mockserver.org(),
+        influxdb_mockserver.bucket(),
+        influxdb_mockserver.token(),
+    ));
+    let service = service.with_sink(influxdb_client);
+    let expected = 1234;
+    solarlog_mockserver
+        .mock_source_snapshot(&[(0, expected, "On-grid", 510)])
+        .await;
+    let homeassistant_mock = homeassistant_mockserver
+        .mock_set_solar_power(expected)
+        .await;
+    let influxdb_mock = influxdb_mockserver
+        .mock_write(&[&format!("solar,inverter=site power={expected}i")])
+        .await;
+
+    let result = service.sync_solar_power(None).await;
+
+    homeassistant_mock.assert_async().await;
+    influxdb_mock.assert_async().await;
+    assert_eq!(result.unwrap(), Some(InverterValues::from([(0, expected)])));
+}
+
+#[tokio::test]
+async fn test_sync_solar_power_metrics() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let metrics = Arc::new(Metrics::default());
+    let service = service.with_metrics(metrics.clone());
+    let expected = 1234;
+    solarlog_mockserver
+        .mock_source_snapshot(&[(0, expected, "On-grid", 510)])
+        .await;
+    homeassistant_mockserver
+        .mock_set_solar_power(expected)
+        .await;
+
+    let result = service.sync_solar_power(None).await;
+
+    assert!(result.is_ok());
+    assert!(metrics.render().contains(&format!(
+        "grelsolar_power_watts{{inverter=\"site\"}} {expected}\n"
+    )));
+}
+
+/// Source simulating two inverters producing at a fixed power.
+struct SimulatedSource;
+
+#[async_trait]
+impl Source for SimulatedSource {
+    fn name(&self) -> &str {
+        "Simulated"
+    }
+
+    async fn connect(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn discover_inverters(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(vec![0, 1])
+    }
+
+    async fn read_snapshot(&self, inverters: &[u8]) -> anyhow::Result<Snapshot> {
+        let day = NaiveDate::from_ymd_opt(2025, 6, 25).unwrap();
+        let status = Status {
+            text: "Running".to_string(),
+            category: StatusCategory::Producing,
+            fault: false,
+        };
+        Ok(Snapshot {
+            power: inverters.iter().map(|id| (*id, 1000)).collect(),
+            energy: inverters.iter().map(|id| (*id, (day, 5000))).collect(),
+            energy_total: inverters.len() as i64 * 1_000_000,
+            status: inverters.iter().map(|id| (*id, status.clone())).collect(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_sync_solar_power_simulated_source() {
+    let (_solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let service = service.with_source(Arc::new(SimulatedSource));
+    let homeassistant_mock_0 = homeassistant_mockserver
+        .mock_set_inverter_power(0, 1000)
+        .await;
+    let homeassistant_mock_1 = homeassistant_mockserver
+        .mock_set_inverter_power(1, 1000)
+        .await;
+    let homeassistant_total_mock = homeassistant_mockserver.mock_set_solar_power(2000).await;
+    let inverters = service
+        .discover_inverters()
+        .await
+        .expect("cannot discover inverters");
+
+    let result = service.sync_solar_power(None).await;
+
+    assert_eq!(inverters, vec![0, 1]);
+    homeassistant_mock_0.assert_async().await;
+    homeassistant_mock_1.assert_async().await;
+    homeassistant_total_mock.assert_async().await;
+    assert_eq!(
+        result.unwrap(),
+        Some(InverterValues::from([(0, 1000), (1, 1000)]))
+    );
+}
+
+#[tokio::test]
+async fn test_sync_solar_status_simulated_source() {
+    let (_solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let service = service.with_source(Arc::new(SimulatedSource));
+    let homeassistant_mock = homeassistant_mockserver
+        .mock_set_solar_status("Running")
+        .await;
+    let homeassistant_state_mock = homeassistant_mockserver
+        .mock_set_state("sensor.solar_state", "producing")
+        .await;
+
+    let result = service.sync_solar_status(None).await;
+
+    homeassistant_mock.assert_async().await;
+    homeassistant_state_mock.assert_async().await;
+    assert_eq!(result.unwrap().unwrap()[&0].text, "Running");
 }
 
 #[tokio::test]
 async fn test_sync_solar_power_no_change() {
     let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
-    let (solarlog_mock, expected) = solarlog_mockserver.mock_current_power().await;
+    let expected = 1234;
+    let (solarlog_mock, _day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, expected, "On-grid", 510)])
+        .await;
 
     let homeassistant_mock = homeassistant_mockserver
         .mock_set_solar_power(expected)
         .await;
 
     // Second sync should not change anything
-    let result = service.sync_solar_power(Some(expected)).await;
+    let last_power = InverterValues::from([(0, expected)]);
+    let result = service.sync_solar_power(Some(&last_power)).await;
 
     solarlog_mock.assert_async().await;
     assert_eq!(homeassistant_mock.hits_async().await, 0);
-    assert_eq!(result.unwrap(), Some(expected));
+    assert_eq!(result.unwrap(), Some(last_power));
+}
+
+#[tokio::test]
+async fn test_sync_solar_power_multiple_inverters() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let (inverters_mock, inverters) = solarlog_mockserver.mock_inverters().await;
+    let (power_mock, _day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1200, "On-grid", 510), (1, 800, "On-grid", 260)])
+        .await;
+    let homeassistant_mock_0 = homeassistant_mockserver
+        .mock_set_inverter_power(0, 1200)
+        .await;
+    let homeassistant_mock_1 = homeassistant_mockserver
+        .mock_set_inverter_power(1, 800)
+        .await;
+    let homeassistant_total_mock = homeassistant_mockserver.mock_set_solar_power(2000).await;
+
+    let discovered = service.discover_inverters().await;
+    let result = service.sync_solar_power(None).await;
+
+    inverters_mock.assert_async().await;
+    power_mock.assert_async().await;
+    homeassistant_mock_0.assert_async().await;
+    homeassistant_mock_1.assert_async().await;
+    homeassistant_total_mock.assert_async().await;
+    assert_eq!(discovered.unwrap(), inverters);
+    assert_eq!(
+        result.unwrap(),
+        Some(InverterValues::from([(0, 1200), (1, 800)]))
+    );
+}
+
+#[tokio::test]
+async fn test_sync_solar_power_multiple_inverters_partial_change() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    solarlog_mockserver.mock_inverters().await;
+    solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1200, "On-grid", 510), (1, 800, "On-grid", 260)])
+        .await;
+    let homeassistant_mock_0 = homeassistant_mockserver
+        .mock_set_inverter_power(0, 1200)
+        .await;
+    let homeassistant_mock_1 = homeassistant_mockserver
+        .mock_set_inverter_power(1, 800)
+        .await;
+    let homeassistant_total_mock = homeassistant_mockserver.mock_set_solar_power(2000).await;
+    service
+        .discover_inverters()
+        .await
+        .expect("cannot discover inverters");
+
+    let last_power = InverterValues::from([(0, 1200), (1, 700)]);
+    let result = service.sync_solar_power(Some(&last_power)).await;
+
+    assert_eq!(homeassistant_mock_0.hits_async().await, 0);
+    homeassistant_mock_1.assert_async().await;
+    homeassistant_total_mock.assert_async().await;
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn test_preflight() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let api_mock = homeassistant_mockserver.mock_get_api().await;
+    solarlog_mockserver.mock_single_inverter().await;
+    solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, "On-grid", 510)])
+        .await;
+    solarlog_mockserver.mock_live_data().await;
+
+    let report = service.preflight().await;
+
+    api_mock.assert_async().await;
+    assert!(report.is_ok(), "{report}");
+    let checks: Vec<_> = report
+        .checks
+        .iter()
+        .map(|check| (check.name.as_str(), check.result.clone().unwrap()))
+        .collect();
+    assert_eq!(
+        checks,
+        vec![
+            ("Home Assistant API", "API running.".to_string()),
+            ("SolarLog connection", "connected".to_string()),
+            ("SolarLog inverters", "[0]".to_string()),
+            ("SolarLog power", "1234 W".to_string()),
+            ("SolarLog energy", "510 Wh".to_string()),
+            ("SolarLog energy total", "24870500 Wh".to_string()),
+            ("SolarLog status", "On-grid".to_string()),
+            ("SolarLog consumption", "640 W".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_preflight_failures() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    homeassistant_mockserver.mock_get_api_unauthorized().await;
+    solarlog_mockserver.mock_single_inverter().await;
+    solarlog_mockserver.mock_query_server_error().await;
+
+    let report = service.preflight().await;
+
+    assert!(!report.is_ok());
+    let failures: Vec<_> = report.failures().map(|check| check.name.as_str()).collect();
+    assert_eq!(
+        failures,
+        vec![
+            "Home Assistant API",
+            "SolarLog power",
+            "SolarLog energy",
+            "SolarLog energy total",
+            "SolarLog status",
+            "SolarLog consumption",
+        ]
+    );
 }
 
 #[tokio::test]
 async fn test_sync_solar_status() {
     let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
-    let (solarlog_mock, expected) = solarlog_mockserver.mock_status().await;
+    let expected = "On-grid";
+    let (solarlog_mock, _day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, expected, 510)])
+        .await;
     let homeassistant_mock = homeassistant_mockserver
         .mock_set_solar_status(expected)
         .await;
+    let homeassistant_state_mock = homeassistant_mockserver
+        .mock_set_state("sensor.solar_state", "producing")
+        .await;
 
     let result = service.sync_solar_status(None).await;
 
     solarlog_mock.assert_async().await;
     homeassistant_mock.assert_async().await;
+    homeassistant_state_mock.assert_async().await;
     assert_eq!(
-        result.unwrap().map(|s| s.to_string()),
+        result
+            .unwrap()
+            .and_then(|s| s.get(&0).map(|s| s.to_string())),
         Some(expected.to_string())
     );
 }
 
+#[tokio::test]
+async fn test_sync_solar_status_unknown() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let expected = "Standby Night mode";
+    let (solarlog_mock, _day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, expected, 510)])
+        .await;
+    let homeassistant_mock = homeassistant_mockserver
+        .mock_set_solar_status(expected)
+        .await;
+    let homeassistant_state_mock = homeassistant_mockserver
+        .mock_set_state("sensor.solar_state", "unknown")
+        .await;
+
+    let result = service.sync_solar_status(None).await;
+
+    solarlog_mock.assert_async().await;
+    homeassistant_mock.assert_async().await;
+    homeassistant_state_mock.assert_async().await;
+    assert_eq!(
+        result.unwrap(),
+        Some(InverterValues::from([(
+            0,
+            Status::from(InverterStatus::Unknown(expected.to_string()))
+        )]))
+    );
+}
+
+#[tokio::test]
+async fn test_sync_solar_status_changed_event() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let expected = "On-grid";
+    solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, expected, 510)])
+        .await;
+    homeassistant_mockserver
+        .mock_set_solar_status(expected)
+        .await;
+    homeassistant_mockserver
+        .mock_set_state("sensor.solar_state", "producing")
+        .await;
+    let changed_mock = homeassistant_mockserver
+        .mock_fire_event(
+            "grelsolar_status_changed",
+            serde_json::json!({
+                "inverter_id": 0,
+                "old_status": "Starting",
+                "new_status": "On-grid",
+                "old_category": "idle",
+                "category": "producing",
+            }),
+        )
+        .await;
+    let fault_mock = homeassistant_mockserver
+        .mock_fire_event("grelsolar_fault", serde_json::json!({}))
+        .await;
+
+    let last_status = InverterValues::from([(0, Status::from(InverterStatus::Starting))]);
+    let result = service.sync_solar_status(Some(&last_status)).await;
+
+    assert!(result.is_ok());
+    changed_mock.assert_async().await;
+    assert_eq!(fault_mock.hits_async().await, 0);
+}
+
+#[tokio::test]
+async fn test_sync_solar_status_event_fired_once_on_publish_failure() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, "On-grid", 510)])
+        .await;
+    homeassistant_mockserver
+        .mock_set_state("sensor.solar_state", "producing")
+        .await;
+    let changed_mock = homeassistant_mockserver
+        .mock_fire_event("grelsolar_status_changed", serde_json::json!({}))
+        .await;
+
+    let last_status = InverterValues::from([(0, Status::from(InverterStatus::Starting))]);
+    let status = service.sync_solar_status(Some(&last_status)).await.unwrap();
+    let result = service.sync_solar_status(status.as_ref()).await;
+
+    assert_eq!(result.unwrap(), status);
+    assert_eq!(changed_mock.hits_async().await, 1);
+}
+
+#[tokio::test]
+async fn test_sync_solar_status_changed_event_after_resync() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let (solarlog_mock, _day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, 0, "Starting", 510)])
+        .await;
+    homeassistant_mockserver
+        .mock_set_state("sensor.solar_status", "Starting")
+        .await;
+    homeassistant_mockserver
+        .mock_set_state("sensor.solar_status", "On-grid")
+        .await;
+    homeassistant_mockserver
+        .mock_set_state("sensor.solar_state", "idle")
+        .await;
+    homeassistant_mockserver
+        .mock_set_state("sensor.solar_state", "producing")
+        .await;
+    let changed_mock = homeassistant_mockserver
+        .mock_fire_event(
+            "grelsolar_status_changed",
+            serde_json::json!({"old_status": "Starting", "new_status": "On-grid"}),
+        )
+        .await;
+    let last_status = InverterValues::from([(0, Status::from(InverterStatus::OnGrid))]);
+    service.sync_solar_status(Some(&last_status)).await.unwrap();
+    solarlog_mock.delete_async().await;
+    solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, "On-grid", 510)])
+        .await;
+
+    // The status changes while resyncing, without a last status
+    service.resync();
+    let result = service.sync_solar_status(None).await;
+    service.resync();
+    let result_again = service.sync_solar_status(None).await;
+
+    assert!(result.is_ok());
+    assert!(result_again.is_ok());
+    assert_eq!(
+        changed_mock.hits_async().await,
+        1,
+        "the event should be fired once for the change during the resync"
+    );
+}
+
+#[tokio::test]
+async fn test_sync_solar_status_fault_event() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let expected = "Shutdown Fault";
+    solarlog_mockserver
+        .mock_source_snapshot(&[(0, 0, expected, 510)])
+        .await;
+    homeassistant_mockserver
+        .mock_set_solar_status(expected)
+        .await;
+    homeassistant_mockserver
+        .mock_set_state("sensor.solar_state", "shutdown")
+        .await;
+    let expected_data = serde_json::json!({
+        "inverter_id": 0,
+        "old_status": "On-grid",
+        "new_status": "Shutdown Fault",
+        "category": "shutdown",
+    });
+    let changed_mock = homeassistant_mockserver
+        .mock_fire_event("grelsolar_status_changed", expected_data.clone())
+        .await;
+    let fault_mock = homeassistant_mockserver
+        .mock_fire_event("grelsolar_fault", expected_data)
+        .await;
+
+    let last_status = InverterValues::from([(0, Status::from(InverterStatus::OnGrid))]);
+    let result = service.sync_solar_status(Some(&last_status)).await;
+
+    assert!(result.is_ok());
+    changed_mock.assert_async().await;
+    fault_mock.assert_async().await;
+}
+
 #[tokio::test]
 async fn test_sync_solar_status_no_change() {
     let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
-    let (solarlog_mock, expected) = solarlog_mockserver.mock_status().await;
+    let expected = "On-grid";
+    let (solarlog_mock, _day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, expected, 510)])
+        .await;
 
     let homeassistant_mock = homeassistant_mockserver
         .mock_set_solar_status(expected)
         .await;
 
-    let inverter_status =
-        solarlog::InverterStatus::try_from(expected).expect("cannot parse inverter status");
-    let result = service.sync_solar_status(Some(&inverter_status)).await;
+    let inverter_status = InverterStatus::try_from(expected).expect("cannot parse inverter status");
+    let last_status = InverterValues::from([(0, Status::from(inverter_status))]);
+    let result = service.sync_solar_status(Some(&last_status)).await;
 
     solarlog_mock.assert_async().await;
     assert_eq!(homeassistant_mock.hits_async().await, 0);
-    assert_eq!(
-        result.unwrap().map(|s| s.to_string()),
-        Some(expected.to_string())
-    );
+    assert_eq!(result.unwrap(), Some(last_status));
+}
+
+#[tokio::test]
+async fn test_sync_dc_strings() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let (solarlog_mock, expected) = solarlog_mockserver.mock_dc_strings().await;
+    let mut homeassistant_mocks = Vec::new();
+    for (index, (voltage, current, power)) in expected.iter().enumerate() {
+        let string = index + 1;
+        for (device_class, value) in [
+            ("voltage", voltage.to_string()),
+            ("current", current.to_string()),
+            ("power", power.to_string()),
+        ] {
+            let entity_id = format!("sensor.solar_dc_{device_class}_inverter_0_string_{string}");
+            homeassistant_mocks.push(
+                homeassistant_mockserver
+                    .mock_set_state(&entity_id, &value)
+                    .await,
+            );
+        }
+    }
+
+    let result = service.sync_dc_strings(None).await;
+
+    solarlog_mock.assert_async().await;
+    for homeassistant_mock in homeassistant_mocks {
+        homeassistant_mock.assert_async().await;
+    }
+    assert_eq!(result.unwrap().unwrap()[&0].len(), expected.len());
+}
+
+#[tokio::test]
+async fn test_sync_dc_strings_partial_change() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let (solarlog_mock, _expected) = solarlog_mockserver.mock_dc_strings().await;
+    let homeassistant_mock = homeassistant_mockserver
+        .mock_set_state("sensor.solar_dc_power_inverter_0_string_2", "3887")
+        .await;
+    let last_dc_strings = InverterValues::from([(
+        0,
+        vec![
+            solarlog::DcString {
+                voltage: 612.4,
+                current: 6.8,
+                power: 4165,
+            },
+            solarlog::DcString {
+                voltage: 598.0,
+                current: 6.5,
+                power: 3650,
+            },
+        ],
+    )]);
+
+    let result = service.sync_dc_strings(Some(&last_dc_strings)).await;
+
+    solarlog_mock.assert_async().await;
+    homeassistant_mock.assert_async().await;
+    assert!(result.is_ok());
 }
 
 #[tokio::test]
 async fn test_sync_solar_energy() {
     let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
-    let (solarlog_mock, day, expected) = solarlog_mockserver.mock_energy_daily().await;
-    let last_reset = SolarBridgeBackgroundService::day_midnight(&day);
+    let expected = 510;
+    let (solarlog_mock, day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, "On-grid", expected)])
+        .await;
+    let last_reset = SolarBridgeBackgroundService::day_midnight(&day).unwrap();
     let energy_kwh = (expected as f64) / 1000.0; // Convert to kWh
     let homeassistant_mock = homeassistant_mockserver
         .mock_set_solar_energy(energy_kwh, &last_reset)
@@ -131,25 +1010,452 @@ async fn test_sync_solar_energy() {
 
     solarlog_mock.assert_async().await;
     homeassistant_mock.assert_async().await;
-    assert_eq!(result.unwrap(), Some((day, expected)));
+    assert_eq!(
+        result.unwrap(),
+        Some(InverterValues::from([(0, (day, expected))]))
+    );
 }
 
 #[tokio::test]
 async fn test_sync_solar_energy_no_change() {
     let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
-    let (solarlog_mock, day, expected) = solarlog_mockserver.mock_energy_daily().await;
-    let last_reset = SolarBridgeBackgroundService::day_midnight(&day);
+    let expected = 510;
+    let (solarlog_mock, day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, "On-grid", expected)])
+        .await;
+    let last_reset = SolarBridgeBackgroundService::day_midnight(&day).unwrap();
     let energy_kwh = (expected as f64) / 1000.0; // Convert to kWh
     let homeassistant_mock = homeassistant_mockserver
         .mock_set_solar_energy(energy_kwh, &last_reset)
         .await;
 
     // Second sync should not change anything
-    let result = service.sync_solar_energy(Some((day, expected))).await;
+    let last_value = InverterValues::from([(0, (day, expected))]);
+    let result = service.sync_solar_energy(Some(&last_value)).await;
 
     solarlog_mock.assert_async().await;
     assert_eq!(homeassistant_mock.hits_async().await, 0);
-    assert_eq!(result.unwrap(), Some((day, expected)));
+    assert_eq!(result.unwrap(), Some(last_value));
+}
+
+#[tokio::test]
+async fn test_sync_solar_power_open_json() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) =
+        mock_setup_with_data_source(solarlog::DataSource::OpenJson).await;
+    let (solarlog_mock, _day, expected, _energy) = solarlog_mockserver.mock_live_data().await;
+    solarlog_mockserver.mock_status().await;
+    let homeassistant_mock = homeassistant_mockserver
+        .mock_set_solar_power(expected)
+        .await;
+
+    let result = service.sync_solar_power(None).await;
+
+    solarlog_mock.assert_async().await;
+    homeassistant_mock.assert_async().await;
+    assert_eq!(result.unwrap(), Some(InverterValues::from([(0, expected)])));
+}
+
+#[tokio::test]
+async fn test_sync_solar_energy_open_json() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) =
+        mock_setup_with_data_source(solarlog::DataSource::OpenJson).await;
+    let (solarlog_mock, day, _power, expected) = solarlog_mockserver.mock_live_data().await;
+    solarlog_mockserver.mock_status().await;
+    let last_reset = SolarBridgeBackgroundService::day_midnight(&day).unwrap();
+    let energy_kwh = (expected as f64) / 1000.0;
+    let homeassistant_mock = homeassistant_mockserver
+        .mock_set_solar_energy(energy_kwh, &last_reset)
+        .await;
+
+    let result = service.sync_solar_energy(None).await;
+
+    solarlog_mock.assert_async().await;
+    homeassistant_mock.assert_async().await;
+    assert_eq!(
+        result.unwrap(),
+        Some(InverterValues::from([(0, (day, expected))]))
+    );
+}
+
+#[tokio::test]
+async fn test_sync_solar_energy_total() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let expected = 24870500;
+    let (solarlog_mock, _day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, "On-grid", 510)])
+        .await;
+    let homeassistant_mock = homeassistant_mockserver
+        .mock_set_state("sensor.solar_energy_total", "24870.5")
+        .await;
+
+    let result = service.sync_solar_energy_total(None).await;
+
+    solarlog_mock.assert_async().await;
+    homeassistant_mock.assert_async().await;
+    assert_eq!(result.unwrap(), Some(expected));
+}
+
+#[tokio::test]
+async fn test_sync_solar_energy_total_no_change() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let expected = 24870500;
+    let (solarlog_mock, _day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, "On-grid", 510)])
+        .await;
+    let homeassistant_mock = homeassistant_mockserver
+        .mock_set_state("sensor.solar_energy_total", "24870.5")
+        .await;
+
+    let result = service.sync_solar_energy_total(Some(expected)).await;
+
+    solarlog_mock.assert_async().await;
+    assert_eq!(homeassistant_mock.hits_async().await, 0);
+    assert_eq!(result.unwrap(), Some(expected));
+}
+
+/// Mock the consumption sensors set from `mock_live_data`, the grid energy meters starting from zero.
+async fn mock_set_consumption(
+    homeassistant_mockserver: &HomeAssistantMockServer,
+) -> Vec<httpmock::Mock<'_>> {
+    let mut mocks = Vec::new();
+    for (key, state) in [
+        ("consumption_power", "640"),
+        ("consumption_energy", "5.21"),
+        ("grid_import_power", "0"),
+        ("grid_export_power", "3480"),
+        ("grid_import_energy", "0"),
+        ("grid_export_energy", "0"),
+        ("self_consumption_ratio", "15.5"),
+    ] {
+        mocks.push(
+            homeassistant_mockserver
+                .mock_set_state(&format!("sensor.solar_{key}"), state)
+                .await,
+        );
+    }
+    mocks
+}
+
+#[tokio::test]
+async fn test_sync_consumption() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let (solarlog_mock, day, _power, _energy) = solarlog_mockserver.mock_live_data().await;
+    let homeassistant_mocks = mock_set_consumption(&homeassistant_mockserver).await;
+
+    let result = service.sync_consumption(None).await;
+
+    solarlog_mock.assert_async().await;
+    for homeassistant_mock in homeassistant_mocks {
+        homeassistant_mock.assert_async().await;
+    }
+    let (consumption, grid_energy) = result.unwrap().unwrap();
+    assert_eq!(
+        consumption,
+        solarlog::Consumption {
+            last_update: day.and_hms_opt(14, 5, 0).unwrap(),
+            power: 640,
+            energy_of_day: (day, 5210),
+            production_power: 4120,
+        }
+    );
+    assert_eq!((grid_energy.import, grid_energy.export), (0.0, 0.0));
+}
+
+#[tokio::test]
+async fn test_sync_consumption_no_change() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    let (solarlog_mock, _day, _power, _energy) = solarlog_mockserver.mock_live_data().await;
+    let homeassistant_mocks = mock_set_consumption(&homeassistant_mockserver).await;
+    let last = service.sync_consumption(None).await.unwrap();
+
+    // Second sync of the same reading should not change anything
+    let result = service.sync_consumption(last.as_ref()).await;
+
+    assert_eq!(solarlog_mock.hits_async().await, 2);
+    for homeassistant_mock in homeassistant_mocks {
+        assert_eq!(homeassistant_mock.hits_async().await, 1);
+    }
+    assert_eq!(result.unwrap(), last);
+}
+
+#[tokio::test]
+async fn test_sync_consumption_grid_energy_from_homeassistant() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
+    solarlog_mockserver.mock_live_data().await;
+    homeassistant_mockserver
+        .mock_get_state(
+            "sensor.solar_grid_import_energy",
+            "12.5",
+            serde_json::json!({
+                "friendly_name": "Solar Grid Import Energy",
+                "unit_of_measurement": "kWh",
+                "device_class": "energy",
+                "state_class": "total_increasing",
+            }),
+        )
+        .await;
+    mock_set_consumption(&homeassistant_mockserver).await;
+    let import_energy_mock = homeassistant_mockserver
+        .mock_set_state("sensor.solar_grid_import_energy", "12.5")
+        .await;
+
+    let result = service.sync_consumption(None).await;
+
+    // Home Assistant already holds the grid import energy, the meter continues from it
+    assert_eq!(import_energy_mock.hits_async().await, 0);
+    let (_consumption, grid_energy) = result.unwrap().unwrap();
+    assert_eq!((grid_energy.import, grid_energy.export), (12500.0, 0.0));
+}
+
+#[tokio::test]
+async fn test_service_run_resyncs_on_homeassistant_restart() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) =
+        mock_setup_with_websocket_and_interval(Duration::from_secs(3600)).await;
+    let (solarlog_power_mock, _) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, "On-grid", 510)])
+        .await;
+    let cancel_token = CancellationToken::new();
+    let service = Arc::new(service);
+    let service_handle = tokio::spawn({
+        let service = Arc::clone(&service);
+        let cancel_token = cancel_token.clone();
+        async move { service.run(cancel_token).await }
+    });
+    homeassistant_mockserver.wait_for_subscriptions(1).await;
+    wait_for_hits(&solarlog_power_mock, 1).await;
+
+    homeassistant_mockserver
+        .fire_event("homeassistant_started", serde_json::json!({}))
+        .await;
+
+    wait_for_hits(&solarlog_power_mock, 2).await;
+    let commands = homeassistant_mockserver.commands().await;
+    assert_eq!(commands[0]["type"], "subscribe_events");
+    assert_eq!(commands[0]["event_type"], "homeassistant_started");
+    cancel_token.cancel();
+    service_handle.await.expect("service failed");
+}
+
+#[tokio::test]
+async fn test_service_resync() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) =
+        mock_setup_with_interval(Duration::from_secs(3600)).await;
+    let expected_power = 1234;
+    let (solarlog_power_mock, _day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, expected_power, "On-grid", 510)])
+        .await;
+    let homeassistant_power_mock = homeassistant_mockserver
+        .mock_set_solar_power(expected_power)
+        .await;
+    let cancel_token = CancellationToken::new();
+    let service = Arc::new(service);
+    let service_handle = tokio::spawn({
+        let service = Arc::clone(&service);
+        let cancel_token = cancel_token.clone();
+        async move { service.run(cancel_token).await }
+    });
+    wait_for_hits(&homeassistant_power_mock, 1).await;
+
+    service.resync();
+
+    wait_for_hits(&homeassistant_power_mock, 2).await;
+    assert_eq!(solarlog_power_mock.hits_async().await, 2);
+    cancel_token.cancel();
+    service_handle.await.expect("service failed");
+}
+
+#[tokio::test]
+async fn test_service_run_records_every_task() {
+    let (solarlog_mockserver, _homeassistant_mockserver, service) =
+        mock_setup_with_interval(Duration::from_secs(3600)).await;
+    let metrics = Arc::new(Metrics::default());
+    let service = Arc::new(
+        service
+            .with_metrics(metrics.clone())
+            .with_sync_intraday_interval(Some(Duration::from_secs(3600))),
+    );
+    solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, "On-grid", 510)])
+        .await;
+    let cancel_token = CancellationToken::new();
+    let service_handle = tokio::spawn({
+        let service = Arc::clone(&service);
+        let cancel_token = cancel_token.clone();
+        async move { service.run(cancel_token).await }
+    });
+
+    let tasks = ["power", "energy", "energy_total", "status", "intraday"];
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !tasks.iter().all(|task| {
+            metrics.render().contains(&format!(
+                "grelsolar_sync_duration_seconds_count{{task=\"{task}\"}} 1\n"
+            ))
+        }) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("a task is not recorded in the metrics");
+    cancel_token.cancel();
+    service_handle.await.expect("service failed");
+}
+
+/// Run the service with the power sync only, and check that the power sensor is marked unavailable
+/// while SolarLog is unreachable and restored once it is back.
+async fn assert_power_unavailable_and_restored(failures: Option<u32>, timeout: Option<Duration>) {
+    let (solarlog_mockserver, homeassistant_mockserver, service) =
+        mock_setup_with_interval(Duration::from_millis(20)).await;
+    let service = Arc::new(service.with_unavailable_after(failures, timeout));
+    let expected_power = 1234;
+    let (solarlog_power_mock, _day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, expected_power, "On-grid", 510)])
+        .await;
+    let homeassistant_power_mock = homeassistant_mockserver
+        .mock_set_state("sensor.solar_power", &expected_power.to_string())
+        .await;
+    let homeassistant_unavailable_mock = homeassistant_mockserver
+        .mock_set_state("sensor.solar_power", "unavailable")
+        .await;
+    let cancel_token = CancellationToken::new();
+    let service_handle = tokio::spawn({
+        let service = Arc::clone(&service);
+        let cancel_token = cancel_token.clone();
+        async move { service.run(cancel_token).await }
+    });
+    wait_for_hits(&homeassistant_power_mock, 1).await;
+
+    solarlog_power_mock.delete_async().await;
+    wait_for_hits(&homeassistant_unavailable_mock, 1).await;
+    assert_eq!(homeassistant_power_mock.hits_async().await, 1);
+
+    let _solarlog_power_mock = solarlog_mockserver
+        .mock_source_snapshot(&[(0, expected_power, "On-grid", 510)])
+        .await;
+    wait_for_hits(&homeassistant_power_mock, 2).await;
+    assert_eq!(homeassistant_unavailable_mock.hits_async().await, 1);
+    cancel_token.cancel();
+    service_handle.await.expect("service failed");
+}
+
+#[tokio::test]
+async fn test_service_run_unavailable_after_failures() {
+    assert_power_unavailable_and_restored(Some(2), None).await;
+}
+
+#[tokio::test]
+async fn test_service_run_unavailable_after_timeout() {
+    assert_power_unavailable_and_restored(None, Some(Duration::from_millis(50))).await;
+}
+
+/// Source simulating two inverters, down from the given read on except the read it is up at.
+struct FlakySource {
+    reads: AtomicUsize,
+    down_from: AtomicUsize,
+    up_at: AtomicUsize,
+}
+
+impl FlakySource {
+    fn new() -> Self {
+        FlakySource {
+            reads: AtomicUsize::new(0),
+            down_from: AtomicUsize::new(usize::MAX),
+            up_at: AtomicUsize::new(usize::MAX),
+        }
+    }
+}
+
+#[async_trait]
+impl Source for FlakySource {
+    fn name(&self) -> &str {
+        "Flaky"
+    }
+
+    async fn connect(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn discover_inverters(&self) -> anyhow::Result<Vec<u8>> {
+        SimulatedSource.discover_inverters().await
+    }
+
+    async fn read_snapshot(&self, inverters: &[u8]) -> anyhow::Result<Snapshot> {
+        let read = self.reads.fetch_add(1, Ordering::SeqCst);
+        if read >= self.down_from.load(Ordering::SeqCst)
+            && read != self.up_at.load(Ordering::SeqCst)
+        {
+            anyhow::bail!("source down");
+        }
+        SimulatedSource.read_snapshot(inverters).await
+    }
+}
+
+#[tokio::test]
+async fn test_service_run_energy_total_restored() {
+    let solarlog_mockserver = SolarlogMockServer::start().await;
+    let homeassistant_mockserver = HomeAssistantMockServer::start().await;
+    let source = Arc::new(FlakySource::new());
+    let hour = Duration::from_secs(3600);
+    let service = Arc::new(
+        SolarBridgeBackgroundService::new(
+            Arc::new(SolarLogClient::new(
+                solarlog_mockserver.url(),
+                solarlog_mockserver.password(),
+            )),
+            Arc::new(HomeAssistantClient::new(
+                homeassistant_mockserver.url(),
+                homeassistant_mockserver.token(),
+            )),
+            solarlog::DataSource::Index,
+            hour,
+            Duration::from_millis(200),
+            hour,
+        )
+        .with_source(source.clone())
+        .with_unavailable_after(Some(1), None),
+    );
+    let total_mock = homeassistant_mockserver
+        .mock_set_state("sensor.solar_energy_total", "2000")
+        .await;
+    let unavailable_mock = homeassistant_mockserver
+        .mock_set_state("sensor.solar_energy_total", "unavailable")
+        .await;
+    let cancel_token = CancellationToken::new();
+    let service_handle = tokio::spawn({
+        let service = Arc::clone(&service);
+        let cancel_token = cancel_token.clone();
+        async move { service.run(cancel_token).await }
+    });
+    wait_for_hits(&total_mock, 1).await;
+
+    // The source is down, then back
+    source
+        .down_from
+        .store(source.reads.load(Ordering::SeqCst), Ordering::SeqCst);
+    wait_for_hits(&unavailable_mock, 1).await;
+    source.down_from.store(usize::MAX, Ordering::SeqCst);
+    wait_for_hits(&total_mock, 2).await;
+
+    // The energy read fails and marks the group unavailable, only the total read right after succeeds
+    let next = source.reads.load(Ordering::SeqCst);
+    source.up_at.store(next + 1, Ordering::SeqCst);
+    source.down_from.store(next, Ordering::SeqCst);
+    wait_for_hits(&unavailable_mock, 2).await;
+
+    wait_for_hits(&total_mock, 3).await;
+    assert_eq!(unavailable_mock.hits_async().await, 2);
+    cancel_token.cancel();
+    service_handle.await.expect("service failed");
+}
+
+/// Wait until the mock has been hit at least `count` times, panics after 5 seconds.
+async fn wait_for_hits(mock: &httpmock::Mock<'_>, count: usize) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while mock.hits_async().await < count {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("mock hit less than {count} times"));
 }
 
 #[tokio::test]
@@ -157,19 +1463,17 @@ async fn test_service_run_starts_and_polls() {
     let (solarlog_mockserver, homeassistant_mockserver, service) = mock_setup().await;
 
     // Set up mocks for one expected poll of each endpoint
-    let (solarlog_power_mock, expected_power) = solarlog_mockserver.mock_current_power().await;
+    let (expected_power, expected_status, expected_energy) = (1234, "On-grid", 510);
+    let (solarlog_mock, day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, expected_power, expected_status, expected_energy)])
+        .await;
     let _homeassistant_power_mock = homeassistant_mockserver
         .mock_set_solar_power(expected_power)
         .await;
-
-    let (solarlog_status_mock, expected_status) = solarlog_mockserver.mock_status().await;
     let _homeassistant_status_mock = homeassistant_mockserver
         .mock_set_solar_status(expected_status)
         .await;
-
-    let (solarlog_energy_mock, day, expected_energy) =
-        solarlog_mockserver.mock_energy_daily().await;
-    let last_reset = SolarBridgeBackgroundService::day_midnight(&day);
+    let last_reset = SolarBridgeBackgroundService::day_midnight(&day).unwrap();
     let energy_kwh = (expected_energy as f64) / 1000.0;
     let _homeassistant_energy_mock = homeassistant_mockserver
         .mock_set_solar_energy(energy_kwh, &last_reset)
@@ -191,8 +1495,41 @@ async fn test_service_run_starts_and_polls() {
     // Drop the service to stop the background tasks
     drop(service_handle);
 
-    // Assert that the mocks were hit at least once
-    assert!(solarlog_energy_mock.hits_async().await > 0);
-    assert!(solarlog_power_mock.hits_async().await > 0);
-    assert!(solarlog_status_mock.hits_async().await > 0);
+    // Assert that the mock was hit at least once
+    assert!(solarlog_mock.hits_async().await > 0);
+}
+
+#[tokio::test]
+async fn test_service_run_shares_snapshot() {
+    let (solarlog_mockserver, homeassistant_mockserver, service) =
+        mock_setup_with_interval(Duration::from_secs(3600)).await;
+    let (solarlog_mock, day) = solarlog_mockserver
+        .mock_source_snapshot(&[(0, 1234, "On-grid", 510)])
+        .await;
+    let homeassistant_power_mock = homeassistant_mockserver.mock_set_solar_power(1234).await;
+    let homeassistant_status_mock = homeassistant_mockserver
+        .mock_set_solar_status("On-grid")
+        .await;
+    let homeassistant_energy_mock = homeassistant_mockserver
+        .mock_set_solar_energy(
+            0.51,
+            &SolarBridgeBackgroundService::day_midnight(&day).unwrap(),
+        )
+        .await;
+    let cancel_token = CancellationToken::new();
+    let service = Arc::new(service);
+    let service_handle = tokio::spawn({
+        let service = Arc::clone(&service);
+        let cancel_token = cancel_token.clone();
+        async move { service.run(cancel_token).await }
+    });
+
+    wait_for_hits(&homeassistant_power_mock, 1).await;
+    wait_for_hits(&homeassistant_status_mock, 1).await;
+    wait_for_hits(&homeassistant_energy_mock, 1).await;
+
+    // The power, energy and status tasks poll at the same time and share a single read
+    assert_eq!(solarlog_mock.hits_async().await, 1);
+    cancel_token.cancel();
+    service_handle.await.expect("service failed");
 }
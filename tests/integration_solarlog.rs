@@ -39,6 +39,7 @@ async fn test_login_ok(#[future] client_server: (Client, SolarlogMockServer)) {
 
     mock.assert_async().await;
     assert!(result.is_ok());
+    assert_eq!(client.request_latencies().count, 1);
 }
 
 #[rstest]
@@ -145,6 +146,159 @@ async fn test_get_energy_of_month(#[future] client_server_logged: (Client, Solar
     assert_eq!(energy.expect("failed to get energy of month"), expected);
 }
 
+#[rstest]
+#[tokio::test]
+async fn test_get_inverters(#[future] client_server_logged: (Client, SolarlogMockServer)) {
+    let (client, server) = client_server_logged.await;
+    let (mock, expected) = server.mock_inverters().await;
+
+    let inverters = client.get_inverters().await;
+
+    mock.assert_async().await;
+    assert_eq!(inverters.expect("failed to get inverters"), expected);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_get_inverter_current_power(
+    #[future] client_server_logged: (Client, SolarlogMockServer),
+) {
+    let (client, server) = client_server_logged.await;
+    let mock = server.mock_inverters_current_power(&[(2, 4321)]).await;
+
+    let power = client.get_inverter_current_power(2).await;
+
+    mock.assert_async().await;
+    assert_eq!(power.expect("failed to get inverter current power"), 4321);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_query_snapshot(#[future] client_server_logged: (Client, SolarlogMockServer)) {
+    let (client, server) = client_server_logged.await;
+    let (mock, day, power, status, energy_of_day, energy_of_month) = server.mock_snapshot().await;
+
+    let snapshot = client
+        .query()
+        .current_power()
+        .status()
+        .energy_of_last_day()
+        .energy_of_last_month()
+        .send()
+        .await
+        .expect("failed to query snapshot");
+
+    mock.assert_async().await;
+    assert_eq!(snapshot.current_power.get(&0), Some(&power));
+    assert_eq!(
+        snapshot.status.get(&0).map(|s| s.to_string()),
+        Some(status.to_string())
+    );
+    assert_eq!(
+        snapshot.energy_of_last_day.get(&0),
+        Some(&(day, energy_of_day))
+    );
+    assert_eq!(
+        snapshot.energy_of_last_month.get(&0).map(|(_, wh)| *wh),
+        Some(energy_of_month)
+    );
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_get_energy_of_year(#[future] client_server_logged: (Client, SolarlogMockServer)) {
+    let (client, server) = client_server_logged.await;
+    let (mock, year, expected, _total) = server.mock_energy_yearly().await;
+
+    let energy = client.get_energy_of_year(year).await;
+
+    mock.assert_async().await;
+    assert_eq!(energy.expect("failed to get energy of year"), expected);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_get_energy_total(#[future] client_server_logged: (Client, SolarlogMockServer)) {
+    let (client, server) = client_server_logged.await;
+    let (mock, _year, _energy, expected) = server.mock_energy_yearly().await;
+
+    let energy = client.get_energy_total().await;
+
+    mock.assert_async().await;
+    assert_eq!(energy.expect("failed to get total energy"), expected);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_get_energy_history(#[future] client_server_logged: (Client, SolarlogMockServer)) {
+    let (client, server) = client_server_logged.await;
+    let (mock, expected) = server.mock_energy_history().await;
+
+    let history = client.get_energy_history(&[0]).await;
+
+    mock.assert_async().await;
+    assert_eq!(
+        history.expect("failed to get energy history").periods(),
+        expected
+    );
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_get_inverter_dc_strings(
+    #[future] client_server_logged: (Client, SolarlogMockServer),
+) {
+    let (client, server) = client_server_logged.await;
+    let (mock, expected) = server.mock_dc_strings().await;
+
+    let dc_strings = client
+        .get_inverter_dc_strings(0)
+        .await
+        .expect("failed to get DC strings");
+
+    mock.assert_async().await;
+    let dc_strings: Vec<_> = dc_strings
+        .iter()
+        .map(|s| (s.voltage, s.current, s.power))
+        .collect();
+    assert_eq!(dc_strings, expected);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_get_intraday_curve(#[future] client_server_logged: (Client, SolarlogMockServer)) {
+    let (client, server) = client_server_logged.await;
+    let (mock, day) = server.mock_intraday_curve().await;
+
+    let curve = client
+        .get_intraday_curve(day)
+        .await
+        .expect("failed to get intraday curve");
+
+    mock.assert_async().await;
+    assert_eq!(curve.samples.len(), 5);
+    assert!(curve.samples.iter().all(|sample| sample.time.date() == day));
+    assert_eq!(curve.samples[0].power, 1000);
+    assert_eq!(curve.samples[4].energy, 9100);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_get_live_data(#[future] client_server_logged: (Client, SolarlogMockServer)) {
+    let (client, server) = client_server_logged.await;
+    let (mock, day, power, energy) = server.mock_live_data().await;
+
+    let live_data = client
+        .get_live_data()
+        .await
+        .expect("failed to get live data");
+
+    mock.assert_async().await;
+    assert_eq!(live_data.last_update.date(), day);
+    assert_eq!(live_data.power_ac, power);
+    assert_eq!(live_data.yield_day, energy);
+}
+
 #[rstest]
 #[tokio::test]
 async fn test_is_logged_in_true(#[future] client_server_logged: (Client, SolarlogMockServer)) {
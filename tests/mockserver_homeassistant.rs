@@ -1,8 +1,18 @@
 //! Mock server for Home Assistant API
 use chrono::{DateTime, TimeZone};
-use httpmock::{Method::POST, Mock, MockServer};
+use futures_util::{SinkExt, StreamExt};
+use httpmock::{
+    Method::{GET, POST},
+    Mock, MockServer,
+};
 use reqwest::Url;
-use serde_json::json;
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
 
 /// Wrapper around `MockServer` for Home Assistant endpoint mocks.
 pub struct HomeAssistantMockServer {
@@ -88,6 +98,7 @@ impl HomeAssistantMockServer {
                         "attributes": {
                             "unit_of_measurement": "W",
                             "friendly_name": "Solar Power",
+                            "device_class": "power",
                             "state_class": "measurement"
                         }
                     }));
@@ -98,6 +109,7 @@ impl HomeAssistantMockServer {
                         "state": power.to_string(),
                         "attributes": {
                             "unit_of_measurement": "W",
+                            "device_class": "power",
                             "state_class": "measurement",
                             "friendly_name": "Solar Power"
                         },
@@ -115,6 +127,7 @@ impl HomeAssistantMockServer {
     }
 
     /// Mock the set state for solar status with sample request/response.
+    /// The status must be published as an enum sensor.
     pub async fn mock_set_solar_status<'a>(&'a self, status: &str) -> Mock<'a> {
         self.server
             .mock_async(move |when, then| {
@@ -122,10 +135,16 @@ impl HomeAssistantMockServer {
                     .path("/api/states/sensor.solar_status")
                     .header("Authorization", format!("Bearer {}", self.token()))
                     .header("Content-Type", "application/json")
-                    .json_body(json!({
-                        "state": status,
-                        "attributes": { "friendly_name": "Solar Status" }
-                    }));
+                    .json_body_partial(
+                        json!({
+                            "state": status,
+                            "attributes": {
+                                "friendly_name": "Solar Status",
+                                "device_class": "enum",
+                            }
+                        })
+                        .to_string(),
+                    );
                 then.status(200)
                     .header("content-type", "application/json")
                     .json_body(json!({
@@ -145,6 +164,149 @@ impl HomeAssistantMockServer {
             .await
     }
 
+    /// Mock the set state for the power of the given inverter.
+    pub async fn mock_set_inverter_power<'a>(&'a self, inverter_id: u8, power: i64) -> Mock<'a> {
+        self.server
+            .mock_async(move |when, then| {
+                when.method(POST)
+                    .path(format!(
+                        "/api/states/sensor.solar_power_inverter_{inverter_id}"
+                    ))
+                    .header("Authorization", format!("Bearer {}", self.token()))
+                    .header("Content-Type", "application/json")
+                    .json_body(json!({
+                        "state": power.to_string(),
+                        "attributes": {
+                            "unit_of_measurement": "W",
+                            "friendly_name": format!("Solar Power Inverter {inverter_id}"),
+                            "device_class": "power",
+                            "state_class": "measurement"
+                        }
+                    }));
+                then.status(200).header("content-type", "application/json");
+            })
+            .await
+    }
+
+    /// Mock the set state for any entity, matching only the state value.
+    pub async fn mock_set_state<'a>(&'a self, entity_id: &str, state: &str) -> Mock<'a> {
+        self.server
+            .mock_async(move |when, then| {
+                when.method(POST)
+                    .path(format!("/api/states/{entity_id}"))
+                    .header("Authorization", format!("Bearer {}", self.token()))
+                    .header("Content-Type", "application/json")
+                    .json_body_partial(json!({ "state": state }).to_string());
+                then.status(200).header("content-type", "application/json");
+            })
+            .await
+    }
+
+    /// Mock firing an event, matching the given data.
+    pub async fn mock_fire_event<'a>(&'a self, event_type: &str, data: Value) -> Mock<'a> {
+        self.server
+            .mock_async(move |when, then| {
+                when.method(POST)
+                    .path(format!("/api/events/{event_type}"))
+                    .header("Authorization", format!("Bearer {}", self.token()))
+                    .header("Content-Type", "application/json")
+                    .json_body_partial(data.to_string());
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .json_body(json!({ "message": format!("Event {event_type} fired.") }));
+            })
+            .await
+    }
+
+    /// Mock the states held by Home Assistant for all entities.
+    pub async fn mock_get_states<'a>(&'a self, states: Value) -> Mock<'a> {
+        self.server
+            .mock_async(move |when, then| {
+                when.method(GET)
+                    .path("/api/states")
+                    .header("Authorization", format!("Bearer {}", self.token()));
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .json_body(states);
+            })
+            .await
+    }
+
+    /// Mock the state held by Home Assistant for an entity.
+    pub async fn mock_get_state<'a>(
+        &'a self,
+        entity_id: &str,
+        state: &str,
+        attributes: Value,
+    ) -> Mock<'a> {
+        self.server
+            .mock_async(move |when, then| {
+                when.method(GET)
+                    .path(format!("/api/states/{entity_id}"))
+                    .header("Authorization", format!("Bearer {}", self.token()));
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .json_body(json!({
+                        "entity_id": entity_id,
+                        "state": state,
+                        "attributes": attributes,
+                        "last_changed": "2025-06-23T04:07:37.906287+00:00",
+                        "last_reported": "2025-06-23T04:07:37.906287+00:00",
+                        "last_updated": "2025-06-23T04:07:37.906287+00:00",
+                        "context": {
+                            "id": "X7TQ47E2AGDK5CWNR3VPYDJP01",
+                            "parent_id": null,
+                            "user_id": null
+                        }
+                    }));
+            })
+            .await
+    }
+
+    /// Mock the set state for any entity, matching the state value and the given attributes.
+    pub async fn mock_set_state_with_attributes<'a>(
+        &'a self,
+        entity_id: &str,
+        state: &str,
+        attributes: Value,
+    ) -> Mock<'a> {
+        self.server
+            .mock_async(move |when, then| {
+                when.method(POST)
+                    .path(format!("/api/states/{entity_id}"))
+                    .header("Authorization", format!("Bearer {}", self.token()))
+                    .json_body_partial(
+                        json!({ "state": state, "attributes": attributes }).to_string(),
+                    );
+                then.status(200).header("content-type", "application/json");
+            })
+            .await
+    }
+
+    /// Mock the API status, answering only requests with the valid token.
+    pub async fn mock_get_api<'a>(&'a self) -> Mock<'a> {
+        self.server
+            .mock_async(move |when, then| {
+                when.method(GET)
+                    .path("/api/")
+                    .header("Authorization", format!("Bearer {}", self.token()));
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .json_body(json!({ "message": "API running." }));
+            })
+            .await
+    }
+
+    /// Mock the API status rejecting the token.
+    pub async fn mock_get_api_unauthorized<'a>(&'a self) -> Mock<'a> {
+        self.server
+            .mock_async(move |when, then| {
+                when.method(GET).path("/api/");
+                then.status(401).body("401: Unauthorized");
+            })
+            .await
+    }
+
     /// Mock a server error on setting solar power to test retry/circuit breaker.
     pub async fn mock_error_solar_power<'a>(&'a self) -> Mock<'a> {
         self.server
@@ -155,3 +317,223 @@ impl HomeAssistantMockServer {
             .await
     }
 }
+
+/// Local stand-in for the Home Assistant WebSocket API.
+/// It authenticates the `test_token`, records every command and answers with a successful result.
+/// `get_states` returns the states given with `set_states`, `subscribe_events` subscriptions receive `fire_event`.
+/// `recorder/statistics_during_period` returns the statistics given with `set_statistics`.
+pub struct HomeAssistantWebSocketMockServer {
+    address: SocketAddr,
+    shared: Arc<Shared>,
+    handle: JoinHandle<()>,
+}
+
+/// State shared between the mock server and its connections.
+#[derive(Default)]
+struct Shared {
+    commands: Mutex<Vec<Value>>,
+    states: Mutex<Vec<Value>>,
+    statistics: Mutex<Value>,
+    connections: Mutex<Vec<mpsc::UnboundedSender<Outgoing>>>,
+    subscriptions: Mutex<Vec<Subscription>>,
+}
+
+/// Event subscription of a connection.
+struct Subscription {
+    id: Value,
+    event_type: Option<String>,
+    sender: mpsc::UnboundedSender<Outgoing>,
+}
+
+/// Message pushed by the server to a connection.
+enum Outgoing {
+    Message(Value),
+    Close,
+}
+
+#[allow(dead_code)]
+impl HomeAssistantWebSocketMockServer {
+    /// Start and return a running WebSocket server on a random local port.
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("cannot bind WebSocket mock server");
+        let address = listener.local_addr().expect("invalid local address");
+        let shared = Arc::new(Shared::default());
+        let handle = tokio::spawn(Self::accept(listener, Arc::clone(&shared)));
+        HomeAssistantWebSocketMockServer {
+            address,
+            shared,
+            handle,
+        }
+    }
+
+    /// Get the base URL to use when constructing the client.
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("http://{}", self.address)).expect("invalid mock server URL")
+    }
+
+    /// Token accepted by the authentication.
+    pub fn token(&self) -> String {
+        String::from("test_token")
+    }
+
+    /// Commands received so far, in order.
+    pub async fn commands(&self) -> Vec<Value> {
+        self.shared.commands.lock().await.clone()
+    }
+
+    /// Set the states returned by `get_states`.
+    pub async fn set_states(&self, states: Vec<Value>) {
+        *self.shared.states.lock().await = states;
+    }
+
+    /// Set the statistics returned by `recorder/statistics_during_period`, keyed by statistic ID.
+    pub async fn set_statistics(&self, statistics: Value) {
+        *self.shared.statistics.lock().await = statistics;
+    }
+
+    /// Number of active event subscriptions.
+    pub async fn subscriptions(&self) -> usize {
+        self.shared
+            .subscriptions
+            .lock()
+            .await
+            .iter()
+            .filter(|subscription| !subscription.sender.is_closed())
+            .count()
+    }
+
+    /// Wait until at least `count` event subscriptions are active.
+    /// Panics after 5 seconds.
+    pub async fn wait_for_subscriptions(&self, count: usize) {
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while self.subscriptions().await < count {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("less than {count} event subscriptions"));
+    }
+
+    /// Send an event to the subscriptions of its type.
+    pub async fn fire_event(&self, event_type: &str, data: Value) {
+        let subscriptions = self.shared.subscriptions.lock().await;
+        for subscription in subscriptions.iter() {
+            if subscription
+                .event_type
+                .as_ref()
+                .is_none_or(|subscribed| subscribed == event_type)
+            {
+                let event = json!({
+                    "id": subscription.id,
+                    "type": "event",
+                    "event": {
+                        "event_type": event_type,
+                        "data": data,
+                        "origin": "LOCAL",
+                        "time_fired": "2025-06-25T10:00:00+00:00",
+                    },
+                });
+                subscription.sender.send(Outgoing::Message(event)).ok();
+            }
+        }
+    }
+
+    /// Close all connections, as Home Assistant does when it restarts.
+    pub async fn disconnect_all(&self) {
+        self.shared.subscriptions.lock().await.clear();
+        for connection in self.shared.connections.lock().await.drain(..) {
+            connection.send(Outgoing::Close).ok();
+        }
+    }
+
+    /// Accept connections until the server is dropped.
+    async fn accept(listener: TcpListener, shared: Arc<Shared>) {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(Self::handle_connection(stream, Arc::clone(&shared)));
+        }
+    }
+
+    /// Authenticate the client and answer its commands.
+    async fn handle_connection(stream: TcpStream, shared: Arc<Shared>) {
+        let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else {
+            return;
+        };
+        let send = |message: Value| Message::text(message.to_string());
+        if ws
+            .send(send(
+                json!({"type": "auth_required", "ha_version": "2025.6.0"}),
+            ))
+            .await
+            .is_err()
+        {
+            return;
+        }
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        shared.connections.lock().await.push(sender.clone());
+        loop {
+            let message = tokio::select! {
+                message = ws.next() => message,
+                outgoing = receiver.recv() => match outgoing {
+                    Some(Outgoing::Message(message)) => {
+                        if ws.send(send(message)).await.is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                    Some(Outgoing::Close) | None => {
+                        ws.close(None).await.ok();
+                        return;
+                    }
+                },
+            };
+            let Some(Ok(message)) = message else {
+                return;
+            };
+            let Message::Text(text) = message else {
+                continue;
+            };
+            let Ok(message) = serde_json::from_str::<Value>(&text) else {
+                continue;
+            };
+            let reply = match message["type"].as_str() {
+                Some("auth") if message["access_token"] == "test_token" => {
+                    json!({"type": "auth_ok", "ha_version": "2025.6.0"})
+                }
+                Some("auth") => json!({"type": "auth_invalid", "message": "Invalid access token"}),
+                Some("ping") => json!({"id": message["id"], "type": "pong"}),
+                Some(command) => {
+                    let id = message["id"].clone();
+                    let result = match command {
+                        "get_states" => Value::Array(shared.states.lock().await.clone()),
+                        "recorder/statistics_during_period" => {
+                            shared.statistics.lock().await.clone()
+                        }
+                        "subscribe_events" => {
+                            shared.subscriptions.lock().await.push(Subscription {
+                                id: id.clone(),
+                                event_type: message["event_type"].as_str().map(String::from),
+                                sender: sender.clone(),
+                            });
+                            Value::Null
+                        }
+                        _ => Value::Null,
+                    };
+                    shared.commands.lock().await.push(message);
+                    json!({"id": id, "type": "result", "success": true, "result": result})
+                }
+                None => continue,
+            };
+            if ws.send(send(reply)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl Drop for HomeAssistantWebSocketMockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
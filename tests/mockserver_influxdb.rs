@@ -0,0 +1,84 @@
+//! Mock server for InfluxDB v2 write API
+use httpmock::{Method::POST, Mock, MockServer};
+use reqwest::Url;
+
+/// Wrapper around `MockServer` for InfluxDB endpoint mocks.
+pub struct InfluxDbMockServer {
+    pub server: MockServer,
+}
+
+#[allow(dead_code)]
+impl InfluxDbMockServer {
+    /// Start and return a running MockServer for InfluxDB.
+    pub async fn start() -> Self {
+        let server = MockServer::start_async().await;
+        InfluxDbMockServer { server }
+    }
+
+    /// Get the base URL to use when constructing the client.
+    pub fn url(&self) -> Url {
+        Url::parse(&self.server.base_url()).expect("invalid mock server URL")
+    }
+
+    /// Organization to write to.
+    pub fn org(&self) -> String {
+        String::from("test_org")
+    }
+
+    /// Bucket to write to.
+    pub fn bucket(&self) -> String {
+        String::from("test_bucket")
+    }
+
+    /// Token to use in Authorization headers in mocks.
+    pub fn token(&self) -> String {
+        String::from("test_token")
+    }
+
+    /// Mock a write whose body contains each of the given lines, without their timestamp.
+    pub async fn mock_write<'a>(&'a self, lines: &[&str]) -> Mock<'a> {
+        let lines: Vec<String> = lines.iter().map(|line| format!("{line} ")).collect();
+        self.server
+            .mock_async(move |when, then| {
+                let mut when = when
+                    .method(POST)
+                    .path("/api/v2/write")
+                    .query_param("org", self.org())
+                    .query_param("bucket", self.bucket())
+                    .query_param("precision", "s")
+                    .header("Authorization", format!("Token {}", self.token()))
+                    .header("Content-Type", "text/plain; charset=utf-8");
+                for line in &lines {
+                    when = when.body_contains(line);
+                }
+                then.status(204);
+            })
+            .await
+    }
+
+    /// Mock a write whose body contains the given line with the given timestamp in seconds.
+    pub async fn mock_write_at<'a>(&'a self, line: &str, timestamp: i64) -> Mock<'a> {
+        let line = format!("{line} {timestamp}");
+        self.server
+            .mock_async(move |when, then| {
+                when.method(POST)
+                    .path("/api/v2/write")
+                    .query_param("precision", "s")
+                    .body_contains(&line);
+                then.status(204);
+            })
+            .await
+    }
+
+    /// Mock a write failing with the given HTTP status.
+    pub async fn mock_write_error(&self, status: u16) -> Mock<'_> {
+        self.server
+            .mock_async(move |when, then| {
+                when.method(POST).path("/api/v2/write");
+                then.status(status)
+                    .header("content-type", "application/json")
+                    .body(r#"{"code":"internal error","message":"unavailable"}"#);
+            })
+            .await
+    }
+}
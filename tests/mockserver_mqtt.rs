@@ -0,0 +1,159 @@
+//! Mock MQTT broker
+//! The broker accepts any client, acknowledges its packets and records the published messages.
+use bytes::BytesMut;
+use reqwest::Url;
+use rumqttc::{ConnAck, ConnectReturnCode, Packet, PubAck, Publish, SubAck, SubscribeReasonCode};
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// Maximum packet size accepted by the broker.
+const MAX_PACKET_SIZE: usize = 1024 * 1024;
+
+/// Message published by a client.
+#[derive(Debug, Clone)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: String,
+    pub retain: bool,
+}
+
+#[allow(dead_code)]
+impl MqttMessage {
+    /// Parse the payload as JSON.
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.payload).expect("invalid JSON payload")
+    }
+}
+
+/// MQTT broker listening on a random local port.
+pub struct MqttMockBroker {
+    address: SocketAddr,
+    messages: Arc<Mutex<Vec<MqttMessage>>>,
+    handle: JoinHandle<()>,
+}
+
+#[allow(dead_code)]
+impl MqttMockBroker {
+    /// Start and return a running broker.
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("cannot bind MQTT mock broker");
+        let address = listener.local_addr().expect("invalid local address");
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let handle = tokio::spawn(Self::accept(listener, Arc::clone(&messages)));
+        MqttMockBroker {
+            address,
+            messages,
+            handle,
+        }
+    }
+
+    /// Get the broker URL to use when constructing the client.
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("mqtt://{}", self.address)).expect("invalid mock broker URL")
+    }
+
+    /// Messages published so far, in order.
+    pub async fn messages(&self) -> Vec<MqttMessage> {
+        self.messages.lock().await.clone()
+    }
+
+    /// Wait until the given topic has been published and return its last message.
+    /// Panics after 5 seconds.
+    pub async fn wait_for(&self, topic: &str) -> MqttMessage {
+        self.wait_for_count(topic, 1)
+            .await
+            .pop()
+            .expect("no message")
+    }
+
+    /// Wait until the given topic has been published at least `count` times and return its messages.
+    /// Panics after 5 seconds.
+    pub async fn wait_for_count(&self, topic: &str, count: usize) -> Vec<MqttMessage> {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let messages: Vec<MqttMessage> = self
+                    .messages()
+                    .await
+                    .into_iter()
+                    .filter(|message| message.topic == topic)
+                    .collect();
+                if messages.len() >= count {
+                    return messages;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("less than {count} messages published on '{topic}'"))
+    }
+
+    /// Accept connections until the broker is dropped.
+    async fn accept(listener: TcpListener, messages: Arc<Mutex<Vec<MqttMessage>>>) {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(Self::handle_connection(stream, Arc::clone(&messages)));
+        }
+    }
+
+    /// Read the packets of a client and answer them.
+    async fn handle_connection(mut stream: TcpStream, messages: Arc<Mutex<Vec<MqttMessage>>>) {
+        let mut buffer = BytesMut::new();
+        loop {
+            let packet = match Packet::read(&mut buffer, MAX_PACKET_SIZE) {
+                Ok(packet) => packet,
+                Err(rumqttc::Error::InsufficientBytes(_)) => {
+                    match stream.read_buf(&mut buffer).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(_) => continue,
+                    }
+                }
+                Err(_) => return,
+            };
+            let reply = match packet {
+                Packet::Connect(_) => Some(Packet::ConnAck(ConnAck::new(
+                    ConnectReturnCode::Success,
+                    false,
+                ))),
+                Packet::Publish(publish) => Self::record(publish, &messages).await,
+                Packet::Subscribe(subscribe) => Some(Packet::SubAck(SubAck::new(
+                    subscribe.pkid,
+                    vec![SubscribeReasonCode::Success(rumqttc::QoS::AtMostOnce)],
+                ))),
+                Packet::PingReq => Some(Packet::PingResp),
+                Packet::Disconnect => return,
+                _ => None,
+            };
+            if let Some(reply) = reply {
+                let mut out = BytesMut::new();
+                if reply.write(&mut out, MAX_PACKET_SIZE).is_err()
+                    || stream.write_all(&out).await.is_err()
+                {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Record a published message and return its acknowledgement, if any.
+    async fn record(publish: Publish, messages: &Mutex<Vec<MqttMessage>>) -> Option<Packet> {
+        messages.lock().await.push(MqttMessage {
+            topic: publish.topic.clone(),
+            payload: String::from_utf8_lossy(&publish.payload).to_string(),
+            retain: publish.retain,
+        });
+        (publish.pkid != 0).then(|| Packet::PubAck(PubAck::new(publish.pkid)))
+    }
+}
+
+impl Drop for MqttMockBroker {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
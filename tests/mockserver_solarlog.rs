@@ -145,6 +145,45 @@ impl SolarlogMockServer {
         (mock, "On-grid")
     }
 
+    /// Mock inverter status shut down by a fault
+    /// Returns a tuple with the mock and the expected status string
+    pub async fn mock_status_fault<'a>(&'a self) -> (Mock<'a>, &'static str) {
+        let mock =
+            self.server
+                .mock_async(|when, then| {
+                    when.method(POST)
+                .path("/getjp")
+                .header(
+                    "cookie",
+                    "SolarLog=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=",
+                )
+                .body(r#"token=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=;{"608":{"0":null}}"#);
+                    then.status(200).body(r#"{"608":{"0":"Shutdown Fault"}}"#);
+                })
+                .await;
+        (mock, "Shutdown Fault")
+    }
+
+    /// Mock inverter status unknown by the client, such as a status added by a firmware update
+    /// Returns a tuple with the mock and the expected status string
+    pub async fn mock_status_unknown<'a>(&'a self) -> (Mock<'a>, &'static str) {
+        let mock =
+            self.server
+                .mock_async(|when, then| {
+                    when.method(POST)
+                .path("/getjp")
+                .header(
+                    "cookie",
+                    "SolarLog=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=",
+                )
+                .body(r#"token=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=;{"608":{"0":null}}"#);
+                    then.status(200)
+                        .body(r#"{"608":{"0":"Standby Night mode"}}"#);
+                })
+                .await;
+        (mock, "Standby Night mode")
+    }
+
     /// Mock energy today
     /// Returns a tuple with the mock, the day date, and the expected energy value
     pub async fn mock_energy_daily<'a>(&'a self) -> (Mock<'a>, NaiveDate, i64) {
@@ -225,6 +264,316 @@ impl SolarlogMockServer {
         (mock, month, 550370)
     }
 
+    /// Mock daily and monthly energy history
+    /// Returns a tuple with the mock and the expected periods without overlap
+    pub async fn mock_energy_history<'a>(&'a self) -> (Mock<'a>, Vec<(NaiveDate, i64)>) {
+        let mock = self
+            .server
+            .mock_async(|when, then| {
+                when.method(POST)
+                .path("/getjp")
+                .header(
+                    "cookie",
+                    "SolarLog=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=",
+                )
+                .body(r#"token=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=;{"777":{"0":null},"779":{"0":null}}"#);
+                then.status(200).json_body(json!(
+                    {
+                        "777": {
+                            "0": [["31.05.25", [22000]], ["01.06.25", [21700]], ["02.06.25", [9550]]]
+                        },
+                        "779": {
+                            "0": [["01.04.25", [480000]], ["01.05.25", [610000]], ["01.06.25", [31250]]]
+                        }
+                    }
+                ));
+            })
+            .await;
+        let date = |year, month, day| {
+            NaiveDate::from_ymd_opt(year, month, day).expect("cannot create date")
+        };
+        let periods = vec![
+            (date(2025, 4, 1), 480000),
+            (date(2025, 5, 1), 588000),
+            (date(2025, 5, 31), 22000),
+            (date(2025, 6, 1), 21700),
+            (date(2025, 6, 2), 9550),
+        ];
+        (mock, periods)
+    }
+
+    /// Mock DC measurements of the strings of the first inverter
+    /// Returns a tuple with the mock and the expected (voltage, current, power) of each string
+    pub async fn mock_dc_strings<'a>(&'a self) -> (Mock<'a>, Vec<(f64, f64, i64)>) {
+        let mock =
+            self.server
+                .mock_async(|when, then| {
+                    when.method(POST)
+                .path("/getjp")
+                .header(
+                    "cookie",
+                    "SolarLog=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=",
+                )
+                .body(r#"token=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=;{"776":{"0":null}}"#);
+                    then.status(200).json_body(json!(
+                        {
+                            "776": {
+                                "0": [[612.4, 6.8, 4165], [598.0, 6.5, 3887]]
+                            }
+                        }
+                    ));
+                })
+                .await;
+        (mock, vec![(612.4, 6.8, 4165), (598.0, 6.5, 3887)])
+    }
+
+    /// Mock intraday curve of the first inverter, from 10:00 to 12:05
+    /// Returns a tuple with the mock and the day of the curve
+    pub async fn mock_intraday_curve<'a>(&'a self) -> (Mock<'a>, NaiveDate) {
+        let mock =
+            self.server
+                .mock_async(|when, then| {
+                    when.method(POST)
+                .path("/getjp")
+                .header(
+                    "cookie",
+                    "SolarLog=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=",
+                )
+                .body(r#"token=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=;{"778":{"0":null}}"#);
+                    then.status(200).json_body(json!(
+                        {
+                            "778": {
+                                "0": [
+                                    ["24.06.25 23:55:00", [0, 28430]],
+                                    ["25.06.25 10:00:00", [1000, 4000]],
+                                    ["25.06.25 10:30:00", [2000, 4750]],
+                                    ["25.06.25 11:00:00", [2400, 5800]],
+                                    ["25.06.25 11:30:00", [3600, 7300]],
+                                    ["25.06.25 12:05:00", [3900, 9100]]
+                                ]
+                            }
+                        }
+                    ));
+                })
+                .await;
+        let day = NaiveDate::from_ymd_opt(2025, 6, 25).expect("cannot create day date");
+        (mock, day)
+    }
+
+    /// Mock inverter discovery
+    /// Returns a tuple with the mock and the expected inverter IDs
+    pub async fn mock_inverters<'a>(&'a self) -> (Mock<'a>, Vec<u8>) {
+        let mock = self
+            .server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/getjp")
+                    .header(
+                        "cookie",
+                        "SolarLog=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=",
+                    )
+                    .body(r#"token=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=;{"740":null}"#);
+                then.status(200)
+                    .body(r#"{"740":{"0":"Huawei","1":"Huawei","2":"Err","3":"Err"}}"#);
+            })
+            .await;
+        (mock, vec![0, 1])
+    }
+
+    /// Mock inverter discovery with a single inverter
+    /// Returns a tuple with the mock and the expected inverter IDs
+    pub async fn mock_single_inverter<'a>(&'a self) -> (Mock<'a>, Vec<u8>) {
+        let mock = self
+            .server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/getjp")
+                    .header(
+                        "cookie",
+                        "SolarLog=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=",
+                    )
+                    .body(r#"token=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=;{"740":null}"#);
+                then.status(200)
+                    .body(r#"{"740":{"0":"Huawei","1":"Err","2":"Err","3":"Err"}}"#);
+            })
+            .await;
+        (mock, vec![0])
+    }
+
+    /// Mock current power of the given inverters in a single query
+    pub async fn mock_inverters_current_power<'a>(&'a self, powers: &[(u8, i64)]) -> Mock<'a> {
+        let query: serde_json::Map<String, serde_json::Value> = powers
+            .iter()
+            .map(|(inverter_id, _)| (inverter_id.to_string(), serde_json::Value::Null))
+            .collect();
+        let response: serde_json::Map<String, serde_json::Value> = powers
+            .iter()
+            .map(|(inverter_id, power)| (inverter_id.to_string(), json!(power.to_string())))
+            .collect();
+        self.server
+            .mock_async(move |when, then| {
+                when.method(POST)
+                    .path("/getjp")
+                    .header(
+                        "cookie",
+                        "SolarLog=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=",
+                    )
+                    .body(format!(
+                        "token=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=;{}",
+                        json!({ "782": query })
+                    ));
+                then.status(200).json_body(json!({ "782": response }));
+            })
+            .await
+    }
+
+    /// Mock the power, status, energy of the day and yearly energy of the given inverters in a single query,
+    /// as read by the SolarLog source. Each inverter is given with its power, status and energy of the day,
+    /// and reports the yearly energy of `mock_energy_yearly`.
+    /// Returns a tuple with the mock and the day date
+    pub async fn mock_source_snapshot<'a>(
+        &'a self,
+        inverters: &[(u8, i64, &str, i64)],
+    ) -> (Mock<'a>, NaiveDate) {
+        let query: serde_json::Map<String, serde_json::Value> = inverters
+            .iter()
+            .map(|(inverter_id, ..)| (inverter_id.to_string(), serde_json::Value::Null))
+            .collect();
+        let mut response = json!({"608": {}, "777": {}, "782": {}, "854": {}});
+        for (inverter_id, power, status, energy) in inverters {
+            let inverter_id = inverter_id.to_string();
+            response["608"][&inverter_id] = json!(status);
+            response["777"][&inverter_id] = json!([["24.06.25", [28430]], ["25.06.25", [energy]]]);
+            response["782"][&inverter_id] = json!(power.to_string());
+            response["854"][&inverter_id] = json!([
+                ["01.01.23", [9542100]],
+                ["01.01.24", [10125400]],
+                ["01.01.25", [5203000]]
+            ]);
+        }
+        let mock = self
+            .server
+            .mock_async(move |when, then| {
+                when.method(POST)
+                    .path("/getjp")
+                    .header(
+                        "cookie",
+                        "SolarLog=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=",
+                    )
+                    .body(format!(
+                        "token=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=;{}",
+                        json!({ "608": query, "777": query, "782": query, "854": query })
+                    ));
+                then.status(200).json_body(response);
+            })
+            .await;
+        let day = NaiveDate::from_ymd_opt(2025, 6, 25).expect("cannot create day date");
+        (mock, day)
+    }
+
+    /// Mock a snapshot of power, status, daily and monthly energy in a single query
+    /// Returns a tuple with the mock, the day date, and the expected power, status, daily and monthly energy values
+    pub async fn mock_snapshot<'a>(&'a self) -> (Mock<'a>, NaiveDate, i64, &'static str, i64, i64) {
+        let mock = self
+            .server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/getjp")
+                    .header(
+                        "cookie",
+                        "SolarLog=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=",
+                    )
+                    .body(
+                        r#"token=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=;{"608":{"0":null},"777":{"0":null},"779":{"0":null},"782":{"0":null}}"#,
+                    );
+                then.status(200).json_body(json!(
+                    {
+                        "608": {"0": "On-grid"},
+                        "777": {"0": [["24.06.25", [28430]], ["25.06.25", [510]]]},
+                        "779": {"0": [["01.06.25", [550370]]]},
+                        "782": {"0": "1234"}
+                    }
+                ));
+            })
+            .await;
+        let day = NaiveDate::from_ymd_opt(2025, 6, 25).expect("cannot create day date");
+        (mock, day, 1234, "On-grid", 510, 550370)
+    }
+
+    /// Mock energy yearly
+    /// Returns a tuple with the mock, the year date, the expected energy of the year and the expected total energy
+    pub async fn mock_energy_yearly<'a>(&'a self) -> (Mock<'a>, NaiveDate, i64, i64) {
+        let mock =
+            self.server
+                .mock_async(|when, then| {
+                    when.method(POST)
+                .path("/getjp")
+                .header(
+                    "cookie",
+                    "SolarLog=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=",
+                )
+                .body(r#"token=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=;{"854":{"0":null}}"#);
+                    then.status(200).json_body(json!(
+                        {
+                            "854": {
+                                "0": [
+                                    ["01.01.23", [9542100]],
+                                    ["01.01.24", [10125400]],
+                                    ["01.01.25", [5203000]]
+                                ]
+                            }
+                        }
+                    ));
+                })
+                .await;
+        let year = NaiveDate::from_ymd_opt(2024, 1, 1).expect("cannot create year date");
+        (mock, year, 10125400, 24870500)
+    }
+
+    /// Mock Open JSON live data
+    /// Returns a tuple with the mock, the day date, the expected AC power and the expected energy of the day
+    pub async fn mock_live_data<'a>(&'a self) -> (Mock<'a>, NaiveDate, i64, i64) {
+        let mock = self
+            .server
+            .mock_async(|when, then| {
+                when.method(POST)
+                .path("/getjp")
+                .header(
+                    "cookie",
+                    "SolarLog=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=",
+                )
+                .body(r#"token=Wazi4Y08JTGY1W56wqPMjMVOa7MxLttaB5n/1Z7NKvg=;{"801":{"170":null}}"#);
+                then.status(200).json_body(json!(
+                    {
+                        "801": {
+                            "170": {
+                                "100": "25.06.25 14:05:00",
+                                "101": 4120,
+                                "102": 4300,
+                                "103": 231,
+                                "104": 612,
+                                "105": 18230,
+                                "106": 28430,
+                                "107": 550370,
+                                "108": 3125400,
+                                "109": 48211000,
+                                "110": 640,
+                                "111": 5210,
+                                "112": 9870,
+                                "113": 190200,
+                                "114": 1320500,
+                                "115": 20450000,
+                                "116": 9900
+                            }
+                        }
+                    }
+                ));
+            })
+            .await;
+        let day = NaiveDate::from_ymd_opt(2025, 6, 25).expect("cannot create day date");
+        (mock, day, 4120, 18230)
+    }
+
     /// Mock query server error
     pub async fn mock_query_server_error<'a>(&'a self) -> Mock<'a> {
         self.server